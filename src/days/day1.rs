@@ -1,51 +1,205 @@
-use crate::AOCDay;
+use crate::{AOCDay, StreamingDay};
+use crate::utils::{parse, ParseError};
 
-use std::str::FromStr;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::BufRead;
 
 /*
- * Template for a implementing a day
+ * Day 1: Sonar Sweep
+ *
+ * Count how often the depth increases, between measurements or between sums of sliding windows.
  */
 
 pub struct Day1();
 
 impl AOCDay for Day1 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(increased_measurements(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(sliding_window(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { increased_measurements(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { sliding_window(_input) }
     fn get_num(&self) -> u32 { 1 }
 }
 
-fn increased_measurements(input: &str) -> String {
-    let mut measurements = input.split_whitespace();
-    let mut prev = parse_u32(measurements.next().unwrap());
-    let mut count: u32 = 0;
+impl StreamingDay for Day1 {
+    fn part1_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> { stream_increases(input, 1) }
+    fn part2_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> { stream_increases(input, 3) }
+}
+
+fn increased_measurements(input: &str) -> Result<String, ParseError> {
+    let sweep = Sweep::from_measurements(1, &parse(input)?);
+    Ok(sweep.increases.to_string())
+}
+
+fn sliding_window(input: &str) -> Result<String, ParseError> {
+    let sweep = Sweep::from_measurements(3, &parse(input)?);
+    Ok(sweep.increases.to_string())
+}
 
-    for num_str in measurements {
-        let num = parse_u32(num_str);
-        if num > prev {
-            count+=1;
+fn stream_increases(input: &mut dyn BufRead, window: usize) -> Result<String, ParseError> {
+    let mut sweep = Sweep::new(window);
+    let mut empty = true;
+    parse::stream_lines(input, |line| {
+        for m in parse::ints::<u32>(line)? {
+            sweep.push(m);
+            empty = false;
         }
-        prev = num;
+        Ok(())
+    })?;
+    if empty {
+        return Err(ParseError::new("no measurements"));
     }
-    count.to_string()
+    Ok(sweep.increases.to_string())
 }
 
-fn sliding_window(input: &str) -> String {
-    let measurements: Vec<u32> = input.split_whitespace().map(|s| parse_u32(s)).collect();
-    let len = measurements.len();
+/// Analyzes the sums of sliding windows of `window` measurements
+pub fn sonar(input: &str, window: usize) -> Result<Sweep, ParseError> {
+    if window == 0 {
+        return Err(ParseError::new("a window needs at least one measurement"));
+    }
+    let measurements = parse(input)?;
+    if measurements.len() < window {
+        return Err(ParseError::new(format!("a window of {} needs at least {} measurements, got {}", window, window, measurements.len())));
+    }
+    Ok(Sweep::from_measurements(window, &measurements))
+}
+
+/// Statistics of the sums of sliding windows over the measurements, computed in a single pass.
+/// Every change compares the sum of a window with the sum of the window before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sweep {
+    window: usize,
+    recent: VecDeque<u32>, // the last `window` measurements
+    sum: u64, // sum of `recent`
+    windows: usize, // number of complete windows
+    increases: usize,
+    decreases: usize,
+    plateaus: usize,
+    run: usize, // increases in a row, up to the last window
+    longest_run: usize,
+    largest_jump: Option<(usize, i64)>, // change of the largest magnitude and the window it leads into
+    deepest: u64, // largest sum of a window
+}
+
+impl Sweep {
+    fn new(window: usize) -> Self {
+        Sweep {
+            window, recent: VecDeque::with_capacity(window + 1), sum: 0, windows: 0,
+            increases: 0, decreases: 0, plateaus: 0, run: 0, longest_run: 0, largest_jump: None, deepest: 0,
+        }
+    }
+
+    fn from_measurements(window: usize, measurements: &[u32]) -> Self {
+        let mut sweep = Sweep::new(window);
+        measurements.iter().for_each(|m| sweep.push(*m));
+        sweep
+    }
+
+    fn push(&mut self, measurement: u32) {
+        self.recent.push_back(measurement);
+        self.sum += measurement as u64;
+        if self.recent.len() <= self.window {
+            if self.recent.len() == self.window {
+                self.windows += 1;
+                self.deepest = self.sum;
+            }
+            return;
+        }
+        // neighbouring windows share all but the measurement leaving and the one entering
+        let first = self.recent.pop_front().unwrap();
+        self.sum -= first as u64;
+        self.windows += 1;
+        self.deepest = self.deepest.max(self.sum);
+
+        let change = measurement as i64 - first as i64;
+        match change.cmp(&0) {
+            Ordering::Greater => {
+                self.increases += 1;
+                self.run += 1;
+                self.longest_run = self.longest_run.max(self.run);
+            },
+            Ordering::Less => {self.decreases += 1; self.run = 0;},
+            Ordering::Equal => {self.plateaus += 1; self.run = 0;},
+        }
+        if self.largest_jump.is_none_or(|(_, jump)| change.abs() > jump.abs()) {
+            self.largest_jump = Some((self.windows, change));
+        }
+    }
+}
 
-    let mut count = 0;
-    let mut prev_sum = measurements[0] + measurements[1] + measurements[2];
-    for i in 1..(len-2) {
-        let sum = measurements[i] + measurements[i+1] + measurements[i+2];
-        if sum > prev_sum { count += 1;}
-        prev_sum = sum;
+impl fmt::Display for Sweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "windows: {}", self.windows)?;
+        writeln!(f, "increases: {}", self.increases)?;
+        writeln!(f, "decreases: {}", self.decreases)?;
+        writeln!(f, "plateaus: {}", self.plateaus)?;
+        writeln!(f, "longest increasing run: {}", self.longest_run)?;
+        match self.largest_jump {
+            Some((window, jump)) => writeln!(f, "largest jump: {:+} (into window {})", jump, window)?,
+            None => writeln!(f, "largest jump: none")?,
+        }
+        write!(f, "deepest window: {}", self.deepest)
     }
-    count.to_string()
 }
 
-fn parse_u32(num: &str) -> u32 {
-    u32::from_str(num).unwrap()
+/// Parses the depth measurements, one per line
+fn parse(input: &str) -> Result<Vec<u32>, ParseError> {
+    let measurements: Vec<u32> = parse::ints(input)?;
+    if measurements.is_empty() {
+        return Err(ParseError::new("no measurements"));
+    }
+    Ok(measurements)
 }
 
 pub fn get() -> Day1 {Day1()}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        let input = "199\n200\n208\n210\n200\n207\n240\n269\n260\n263\n";
+        assert_eq!(increased_measurements(input), Ok(String::from("7")));
+        assert_eq!(sliding_window(input), Ok(String::from("5")));
+        assert!(get().part1_stream(&mut "\n\n".as_bytes()).is_err());
+        assert_eq!(sliding_window("1\n2\n"), Ok(String::from("0")));
+    }
+
+    #[test]
+    fn test_sonar() {
+        let sweep = sonar("1\n2\n3\n3\n3\n10\n1\n", 2).unwrap();
+        // window sums 3, 5, 6, 6, 13, 11
+        assert_eq!((sweep.windows, sweep.increases, sweep.decreases, sweep.plateaus), (6, 3, 1, 1));
+        assert_eq!((sweep.longest_run, sweep.largest_jump, sweep.deepest), (2, Some((5, 7)), 13));
+        assert!(sonar("1\n2\n", 3).is_err());
+        assert!(sonar("1\n2\n", 0).is_err());
+        assert_eq!(sonar("7\n", 1).unwrap().largest_jump, None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("199\n2x0\n").is_err());
+        assert!(parse("-3\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_stream(measurements in prop::collection::vec(0..20u32, 1..50)) {
+            let input = Lines(&measurements).to_string();
+            prop_assert_eq!(get().part1_stream(&mut input.as_bytes()), increased_measurements(&input));
+            prop_assert_eq!(get().part2_stream(&mut input.as_bytes()), sliding_window(&input));
+        }
+
+        #[test]
+        fn test_roundtrip(measurements in prop::collection::vec(any::<u32>(), 1..50)) {
+            prop_assert_eq!(parse(&Lines(&measurements).to_string()), Ok(measurements));
+        }
+
+        #[test]
+        fn test_garbage(input in "[0-9a-z \n-]{0,40}") {
+            let _ = parse(&input);
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::AOCDay;
+use crate::{AOCDay, StreamingDay};
+use crate::utils::{parse, ParseError};
+
+use std::io::BufRead;
 
 /*
  * Template for a implementing a day
@@ -7,88 +10,195 @@ use crate::AOCDay;
 pub struct Day10();
 
 impl AOCDay for Day10 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 10 }
 }
 
+/// Part 1 only keeps the total score, part 2 keeps the score of every incomplete line.
+impl StreamingDay for Day10 {
+    fn part1_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> {
+        let language = Language::puzzle();
+        let mut score = 0;
+        parse::stream_lines(input, |l| {
+            score += language.error_score(&language.check(l)?);
+            Ok(())
+        })?;
+        Ok(format!("{}", score))
+    }
+
+    fn part2_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> {
+        let language = Language::puzzle();
+        let mut scores = Vec::new();
+        parse::stream_lines(input, |l| {
+            scores.extend(language.completion_score(&language.check(l)?));
+            Ok(())
+        })?;
+        middle_score(scores)
+    }
+}
+
 pub fn get() -> Day10 {Day10()}
 
-fn part1(input: &str) -> String {
-    let score: u32 = input.lines().map(syntax_err_score).sum();
-    String::from(format!("{}", score))
+fn part1(input: &str) -> Result<String, ParseError> {
+    let language = Language::puzzle();
+    let score: u64 = parse(input, &language)?.iter().map(|status| language.error_score(status)).sum();
+    Ok(format!("{}", score))
+}
+
+fn part2(input: &str) -> Result<String, ParseError> {
+    let language = Language::puzzle();
+    // complete lines need an empty completion, scoring 0
+    let scores = parse(input, &language)?.iter().filter_map(|status| language.completion_score(status)).collect();
+    middle_score(scores)
 }
 
-fn part2(input: &str) -> String {
-    let lines = input.lines().filter(|l| syntax_err_score(l) == 0); // complete and incomplete lines
-    let mut scores: Vec<u64> = lines.map(|s| completion_score(&complete_syntax(s))).collect();
+fn middle_score(mut scores: Vec<u64>) -> Result<String, ParseError> {
+    if scores.is_empty() {
+        return Err(ParseError::new("no incomplete lines"));
+    }
     scores.sort();
-    String::from(format!("{}", scores[scores.len() / 2]))
+    Ok(format!("{}", scores[scores.len() / 2]))
+}
+
+/// A pair of brackets and its scores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pair {
+    pub open: char,
+    pub close: char,
+    pub error: u64, // score of a line corrupted by this closing bracket
+    pub completion: u64, // score of this closing bracket in a completion
+}
+
+/// The lines of a bracket language, in which every opened bracket is closed by its partner in reverse order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+    pairs: Vec<Pair>,
+    base: u64, // every bracket of a completion multiplies the score of those before it by the base
+}
+
+/// State of a line after reading all of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Complete,
+    /// The bracket at column `col` does not close the last open bracket, `expected` is none if no bracket is open
+    Corrupted {col: usize, expected: Option<char>, found: char},
+    /// Some brackets are still open, `completion` closes them
+    Incomplete {completion: String},
 }
 
-fn completion_score(line: &str) -> u64 {
-    let mut score = 0;
-    for c in line.chars() {
-        match c {
-            ')' => score = (score * 5) + 1,
-            ']' => score = (score * 5) + 2,
-            '}' => score = (score * 5) + 3,
-            '>' => score = (score * 5) + 4,
-            _ => panic!("Unexpected char: {}", c)
+impl Language {
+    /// The language of the brackets given as opening and closing bracket one after another, e.g. `"()[]"`.
+    /// Brackets score by their position, starting at 1, and completions use the number of pairs plus one as base.
+    pub fn new(brackets: &str) -> Result<Self, ParseError> {
+        let chars: Vec<char> = brackets.chars().collect();
+        if chars.len() % 2 == 1 {
+            return Err(ParseError::new(format!("bracket {:?} has no partner", chars[chars.len() - 1])));
+        }
+        if let Some((i, c)) = chars.iter().enumerate().find(|(i, c)| chars[..*i].contains(c) || c.is_whitespace()) {
+            return Err(ParseError::new(format!("bracket {:?} can only be used once", c)).at(1, i + 1));
         }
+        let pairs: Vec<Pair> = chars.chunks(2).zip(1..).map(|(pair, score)| {
+            Pair {open: pair[0], close: pair[1], error: score, completion: score}
+        }).collect();
+        let base = pairs.len() as u64 + 1;
+        Ok(Language {pairs, base})
+    }
+
+    /// The brackets and scores of the puzzle
+    pub fn puzzle() -> Self {
+        Language::new("()[]{}<>").unwrap().with_error_scores(&[3, 57, 1197, 25137])
+    }
+
+    /// Replaces the scores of corrupted lines, given for every pair in order
+    pub fn with_error_scores(mut self, scores: &[u64]) -> Self {
+        assert_eq!(scores.len(), self.pairs.len(), "every pair needs a score");
+        self.pairs.iter_mut().zip(scores).for_each(|(pair, &score)| pair.error = score);
+        self
+    }
+
+    /// Replaces the scores of closing brackets in completions, given for every pair in order, and their base
+    pub fn with_completion_scores(mut self, scores: &[u64], base: u64) -> Self {
+        assert_eq!(scores.len(), self.pairs.len(), "every pair needs a score");
+        self.pairs.iter_mut().zip(scores).for_each(|(pair, &score)| pair.completion = score);
+        self.base = base;
+        self
     }
-    score
-}
 
-fn complete_syntax(line: &str) -> String {
-    let chars = line.trim().chars();
-    let mut stack: Vec<char> = Vec::new();
-    for c in chars {
-        match c {
-            '[' => {stack.push(']')},
-            '{' => {stack.push('}')},
-            '(' => {stack.push(')')},
-            '<' => {stack.push('>')},
-            ')' => {stack.pop();},
-            ']' => {stack.pop();},
-            '}' => {stack.pop();},
-            '>' => {stack.pop();},
-            _ => {panic!("unrecognized char: {}", c);}
+    /// Reads the brackets of a line, surrounding whitespace is ignored.
+    /// Columns count from the start of the line, starting at 1.
+    pub fn check(&self, line: &str) -> Result<Status, ParseError> {
+        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+        let mut open: Vec<&Pair> = Vec::new();
+        for (i, c) in line.trim().chars().enumerate() {
+            let col = indent + i + 1;
+            if let Some(pair) = self.pairs.iter().find(|p| p.open == c) {
+                open.push(pair);
+            } else if self.pairs.iter().any(|p| p.close == c) {
+                match open.pop() {
+                    Some(pair) if pair.close == c => {},
+                    last => return Ok(Status::Corrupted {col, expected: last.map(|p| p.close), found: c}),
+                }
+            } else {
+                return Err(ParseError::new(format!("unrecognized char {:?}", c)).at(1, col));
+            }
         }
+        Ok(match open.is_empty() {
+            true => Status::Complete,
+            false => Status::Incomplete {completion: open.iter().rev().map(|p| p.close).collect()},
+        })
     }
-    stack.iter().rev().collect::<String>()
-}
 
+    fn closing(&self, c: char) -> Option<&Pair> {
+        self.pairs.iter().find(|p| p.close == c)
+    }
 
-fn syntax_err_score(line: &str) -> u32 {
-    let chars = line.trim().chars();
-    let mut stack: Vec<char> = Vec::new();
-    for c in chars {
-        match c {
-            '[' => {stack.push(c)},
-            '{' => {stack.push(c)},
-            '(' => {stack.push(c)},
-            '<' => {stack.push(c)},
-            ')' => {
-                let s = stack.pop().unwrap();
-                if s != '(' {return 3;}
-            },
-            ']' => {
-                let s = stack.pop().unwrap();
-                if s != '[' {return 57;}
-            },
-            '}' => {
-                let s = stack.pop().unwrap();
-                if s != '{' {return 1197;}
+    /// Score of a corrupted line, 0 for all others
+    pub fn error_score(&self, status: &Status) -> u64 {
+        match status {
+            Status::Corrupted {found, ..} => self.closing(*found).map_or(0, |p| p.error),
+            _ => 0,
+        }
+    }
+
+    /// Score of the completion of a line, none for corrupted lines, which can not be completed
+    pub fn completion_score(&self, status: &Status) -> Option<u64> {
+        match status {
+            Status::Complete => Some(0),
+            Status::Incomplete {completion} => Some(completion.chars().fold(0, |score, c| {
+                score * self.base + self.closing(c).map_or(0, |p| p.completion)
+            })),
+            Status::Corrupted {..} => None,
+        }
+    }
+}
+
+/// Every line of the input that is not complete, with a marker at the offending bracket, followed by a summary
+pub fn lint(input: &str, language: &Language) -> String {
+    let mut diagnostics = Vec::new();
+    let (mut complete, mut corrupted, mut incomplete, mut invalid) = (0, 0, 0, 0);
+    for (i, line) in input.lines().enumerate() {
+        let error = match language.check(line) {
+            Ok(Status::Complete) => {complete += 1; continue},
+            Ok(Status::Corrupted {col, expected, found}) => {
+                corrupted += 1;
+                let message = match expected {
+                    Some(expected) => format!("expected {:?}, found {:?}", expected, found),
+                    None => format!("found {:?}, but no bracket is open", found),
+                };
+                ParseError::new(message).at(1, col)
             },
-            '>' => {
-                let s = stack.pop().unwrap();
-                if s != '<' {return 25137;}
+            Ok(Status::Incomplete {completion}) => {
+                incomplete += 1;
+                let end = line.trim_end().chars().count() + 1;
+                ParseError::new(format!("incomplete, missing {:?}", completion)).at(1, end)
             },
-            _ => {panic!("unrecognized char: {}", c);}
-        }
+            Err(e) => {invalid += 1; e},
+        };
+        diagnostics.push(error.offset(i + 1).render(input));
     }
-    0
+    diagnostics.push(format!("{} complete, {} corrupted, {} incomplete and {} invalid lines", complete, corrupted, incomplete, invalid));
+    diagnostics.join("\n\n")
 }
 
 fn test_input() -> String {
@@ -103,3 +213,108 @@ fn test_input() -> String {
 <{([([[(<>()){}]>(<<{{
 <{([{{}}[<[[[<>{}]]]>[]]")
 }
+
+/// Parses the navigation subsystem, lines consisting solely of brackets of the language
+fn parse(input: &str, language: &Language) -> Result<Vec<Status>, ParseError> {
+    parse::lines(input, |l| language.check(l))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Input;
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("26397")));
+        assert_eq!(part2(&test_input()), Ok(String::from("288957")));
+        assert!(part2("(]\n").is_err());
+    }
+
+    #[test]
+    fn test_status() {
+        let language = Language::puzzle();
+        let status = parse(&test_input(), &language).unwrap();
+        assert_eq!(status[0], Status::Incomplete {completion: String::from("}}]])})]")});
+        assert_eq!(status[2], Status::Corrupted {col: 13, expected: Some(']'), found: '}'});
+        assert_eq!(language.check("  ()]"), Ok(Status::Corrupted {col: 5, expected: None, found: ']'}));
+        assert_eq!(language.check("[<>]\t"), Ok(Status::Complete));
+        let e = language.check("(x)").unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(1), Some(2)));
+        let e = parse("()\n(<a>)\n", &language).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(2), Some(3)));
+        // columns count characters, also in a long indent
+        let line = format!("{}\u{3000}{}]", " ".repeat(100000), "()".repeat(100000));
+        assert_eq!(language.check(&line), Ok(Status::Corrupted {col: 300002, expected: None, found: ']'}));
+    }
+
+    #[test]
+    fn test_language() {
+        let language = Language::new("()/\\").unwrap().with_error_scores(&[10, 20]).with_completion_scores(&[1, 2], 10);
+        assert_eq!(language.check("(/()"), Ok(Status::Incomplete {completion: String::from("\\)")}));
+        assert_eq!(language.completion_score(&language.check("(/()").unwrap()), Some(21));
+        assert_eq!(language.error_score(&language.check("(/)").unwrap()), 10);
+        assert_eq!(language.completion_score(&language.check("(/)").unwrap()), None);
+        assert!(language.check("[]").is_err());
+
+        assert!(Language::new("()[").is_err());
+        let e = Language::new("()[(").unwrap_err();
+        assert_eq!(e.col(), Some(4));
+        assert!(Language::new("( ").is_err());
+    }
+
+    #[test]
+    fn test_lint() {
+        let input = "[<>]\n{()()()>\n  ((\n)\n(?)";
+        assert_eq!(lint(input, &Language::puzzle()), "\
+Parse error at line 2, column 8: expected '}', found '>'
+2 | {()()()>
+  |        ^
+
+Parse error at line 3, column 5: incomplete, missing \"))\"
+3 |   ((
+  |     ^
+
+Parse error at line 4, column 1: found ')', but no bracket is open
+4 | )
+  | ^
+
+Parse error at line 5, column 2: unrecognized char '?'
+5 | (?)
+  |  ^
+
+1 complete, 2 corrupted, 1 incomplete and 1 invalid lines");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("(a)\n", &Language::puzzle()).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_stream(lines in prop::collection::vec("[\\[\\](){}<>]{0,20}", 1..20)) {
+            // streams are normalized while reading, like the inputs of `solve`
+            let input = Input::new(&Lines(&lines).to_string());
+            let input = input.as_str();
+            prop_assert_eq!(get().part1_stream(&mut input.as_bytes()), part1(input));
+            prop_assert_eq!(get().part2_stream(&mut input.as_bytes()), part2(input));
+        }
+
+        #[test]
+        fn test_roundtrip(lines in prop::collection::vec("[\\[\\](){}<>]{0,40}", 0..20)) {
+            let serialized = Lines(&lines).to_string();
+            prop_assert_eq!(parse(&serialized, &Language::puzzle()).map(|status| status.len()), Ok(lines.len()));
+        }
+
+        #[test]
+        fn test_garbage(input in "([\\[\\](){}<>a ]{0,10}\n){0,4}") {
+            let language = Language::puzzle();
+            if let Ok(lines) = parse(&input, &language) {
+                lines.iter().for_each(|status| {language.error_score(status); language.completion_score(status);});
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
 use crate::AOCDay;
+use crate::utils::ParseError;
+use crate::utils::grid::{Coord, Grid};
+use crate::visualize::{Colour, Frame, Visualize};
 
 /*
  * Template for a implementing a day
@@ -6,30 +9,61 @@ use crate::AOCDay;
 
 pub struct Day11();
 
-type Grid = Vec<Vec<u32>>;
-
 impl AOCDay for Day11 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 11 }
 }
 
+impl Visualize for Day11 {
+    /// Energy levels of the octopuses, until all of them flash simultaneously
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError> {
+        let mut squids = parser(input)?;
+        let total_squids = squids.len();
+        let mut synchronized = false;
+        let mut first = true;
+        Ok(Box::new(std::iter::from_fn(move || {
+            if synchronized { return None; }
+            if first {
+                first = false;
+            } else {
+                synchronized = simulate_day(&mut squids) as usize == total_squids;
+            }
+            Some(frame(&squids))
+        })))
+    }
+}
+
 pub fn get() -> Day11 {Day11()}
 
-fn part1(input: &str) -> String {
-    let mut squids = parser(&input);
+// Flashing octopuses are bright, the others darken with their energy level
+const PALETTE: [Colour; 10] = [
+    (255, 255, 200), (10, 20, 60), (15, 30, 80), (20, 40, 100), (25, 50, 120),
+    (30, 60, 140), (35, 70, 160), (40, 80, 180), (45, 90, 200), (50, 100, 220),
+];
+
+fn frame(grid: &Grid<u32>) -> Frame {
+    let mut frame = Frame::new(grid.width(), grid.height(), &PALETTE);
+    for (x, y) in grid.coords() {
+        frame.set(x, y, grid[(x, y)] as u8);
+    }
+    frame
+}
+
+fn part1(input: &str) -> Result<String, ParseError> {
+    let mut squids = parser(input)?;
     let mut ftotal = 0;
     for _ in 0..100 {
         let f = simulate_day(&mut squids);
         ftotal += f;
     }
-    String::from(format!("{}", ftotal))
+    Ok(format!("{}", ftotal))
 }
 
 
-fn part2(input: &str) -> String {
-    let mut squids = parser(&input);
-    let total_squids = squids.len() * squids[0].len();
+fn part2(input: &str) -> Result<String, ParseError> {
+    let mut squids = parser(input)?;
+    let total_squids = squids.len();
     let mut days = 0;
     loop {
         days += 1;
@@ -38,7 +72,7 @@ fn part2(input: &str) -> String {
             break;
         }
     }
-    String::from(format!("{}", days))
+    Ok(format!("{}", days))
 }
 
 fn test_input() -> String {
@@ -54,46 +88,62 @@ fn test_input() -> String {
 5283751526")
 }
 
-fn simulate_day(grid: &mut Grid) -> u32 {
+fn simulate_day(grid: &mut Grid<u32>) -> u32 {
     let mut flashes = 0;
-    for y in 0..grid.len() { // Step 1: increase all indices by 1
-        for x in 0..grid[0].len() {
-            grid[y][x] += 1;
-        }
+    for n in grid.iter_mut() { // Step 1: increase all indices by 1
+        *n += 1;
     }
-    for y in 0..grid.len() { // Step 1: increase all indices by 1
-        for x in 0..grid[0].len() {
-            if grid[y][x] > 9 {
-                flash(x, y, grid,&mut flashes);
-            }
+    for coord in grid.coords() { // Step 2: flash octopuses with enough energy
+        if grid[coord] > 9 {
+            flash(coord, grid, &mut flashes);
         }
     }
     flashes
 }
 
-/// Octopus at (x,y) flashes and might trigger neighbours
-fn flash(x: usize, y: usize, grid: &mut Grid, flashes: &mut u32) {
+/// Octopus at coord flashes and might trigger neighbours
+fn flash(coord: Coord, grid: &mut Grid<u32>, flashes: &mut u32) {
     *flashes = *flashes + 1;
-    grid[y][x] = 0;
-    let ns = get_adjacent_inidces(x, y, &grid);
-    for (x2, y2) in ns {
-        let n = grid[y2][x2];
+    grid[coord] = 0;
+    for c2 in grid.neighbours8(coord) {
+        let n = grid[c2];
         if n >= 9 {
-            flash(x2,y2,grid,flashes);
+            flash(c2,grid,flashes);
         } else if n > 0 {
-            grid[y2][x2] += 1;
+            grid[c2] += 1;
         }
     }
 }
 
-/// Returns a list of indices of the neighbours of grid[y][x].
-fn get_adjacent_inidces(x: usize, y: usize, grid: &Grid) -> Vec<(usize, usize)> {
-    let (row_size, col_size) = (grid.len() as i32, grid[0].len() as i32);
-    let (x, y) = (x as i32, y as i32);
-    let indices: Vec<(i32, i32)> = vec![(x-1,y-1), (x-1,y), (x-1,y+1),(x,y-1),(x,y+1),(x+1,y-1),(x+1,y),(x+1,y+1)];
-    indices.into_iter().filter(|(x,y)| *x >= 0 && *x < col_size && *y >= 0 && *y < row_size).map(|(x,y)| (x as usize, y as usize)).collect()
+fn parser(input: &str) -> Result<Grid<u32>, ParseError> {
+    Grid::parse_digits(input)
 }
 
-fn parser(input: &str) -> Grid {
-    input.lines().map(|l| l.chars().map(|s| s.to_digit(10).unwrap()).collect()).collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn grid() -> impl Strategy<Value = Grid<u32>> {
+        (1..20usize).prop_flat_map(|width| prop::collection::vec(prop::collection::vec(0..10u32, width), 1..20))
+            .prop_map(|rows| Grid::from_rows(rows).unwrap())
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parser("12\n3\n").is_err());
+        assert!(parser("1a\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(grid in grid()) {
+            prop_assert_eq!(parser(&grid.to_string()), Ok(grid));
+        }
+
+        #[test]
+        fn test_garbage(input in "([0-9a ]{0,6}\\n){0,4}") {
+            let _ = parser(&input);
+        }
+    }
 }
@@ -1,7 +1,11 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::bits::BitSet;
+use crate::utils::memo::memoize;
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
 
 /*
  * Template for a implementing a day
@@ -10,99 +14,75 @@ use std::collections::HashSet;
 pub struct Day12();
 
 impl AOCDay for Day12 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 12 }
 }
 
-type Edge<'a> = (&'a str, &'a str);
-type Graph<'a> = HashMap<&'a str, HashSet<&'a str>>;
-type Path<'a> = Vec<&'a str>;
-
-pub fn get() -> Day12 {Day12()}
+/// Connection between two caves
+#[derive(Debug, PartialEq, Eq)]
+struct Edge<'a>(&'a str, &'a str);
 
-fn part1(input: &str) -> String {
-    let graph = graph(parser(&input));
-    let paths = paths(&graph);
-    String::from(format!("{}", paths))
+impl fmt::Display for Edge<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.0, self.1)
+    }
 }
 
-fn part2(input: &str) -> String {
-    let graph = graph(parser(&input));
-    let paths = paths2(&graph);
-    String::from(format!("{}", paths))
-}
+type Graph<'a> = HashMap<&'a str, HashSet<&'a str>>;
 
-fn paths<'a>(graph: &'a Graph<'a>) -> u32 {
-    let mut visited = HashSet::new();
-    visited.insert("start");
-    visit_node("start", graph, &visited)
-}
+pub fn get() -> Day12 {Day12()}
 
-fn paths2<'a>(graph: &'a Graph<'a>) -> u32 {
-    let mut visited = HashSet::new();
-    visited.insert("start");
-    visit_node2("start", graph, &visited, false)
+fn part1(input: &str) -> Result<String, ParseError> {
+    let graph = graph(parser(input)?);
+    let paths = paths(&graph, false)?;
+    Ok(format!("{}", paths))
 }
 
-fn visit_node<'a>(node: &'a str, graph: &'a Graph, visited: &HashSet<&'a str>) -> u32 {
-    if node == "end" { // reached end node
-        return 1;
-    }
-
-    let neighbours = graph.get(node).unwrap();
-    let mut paths = 0;
-
-    for n in neighbours.iter() {
-        if big(n) { // visit cave unconditionally
-                let ps = visit_node(n, graph, visited);
-                paths += ps;
-        }else { // only visit cave if not visited already
-            if !visited.contains(n) { // unvisited
-                let mut visited = visited.clone();
-                visited.insert(n);
-                let ps = visit_node(n, graph, &visited);
-                paths += ps;
-            }
-        }
-    }
-    paths
+fn part2(input: &str) -> Result<String, ParseError> {
+    let graph = graph(parser(input)?);
+    let paths = paths(&graph, true)?;
+    Ok(format!("{}", paths))
 }
 
-fn visit_node2<'a>(node: &'a str, graph: &'a Graph, visited: &HashSet<&'a str>, visited_node_twice: bool) -> u32 {
-    if node == "end" { // reached end node
-        return 1;
+/// Position of a path through the caves: the current cave, the small caves visited so far and
+/// whether a small cave may still be visited a second time.
+type Visit<'a> = (&'a str, BitSet, bool);
+
+/// Number of paths from start to end, visiting small caves at most once.
+/// With `revisit` a single small cave other than start may be visited twice.
+fn paths(graph: &Graph, revisit: bool) -> Result<u64, ParseError> {
+    // small caves are tracked as bits, numbered in order of their names
+    let mut small: Vec<&str> = graph.keys().copied().filter(|c| !big(c)).collect();
+    small.sort_unstable();
+    if small.len() > BitSet::<1>::CAPACITY {
+        return Err(ParseError::new(format!("too many small caves ({})", small.len())));
     }
+    let index: HashMap<&str, usize> = small.into_iter().enumerate().map(|(i, c)| (c, i)).collect();
 
-    let neighbours = graph.get(node).unwrap();
-    let mut paths = 0;
-
-    for n in neighbours.iter() {
-        if n == &"start" {continue;} // we do not visit start twice
-        if big(n) { // visit cave unconditionally
-                let ps = visit_node2(n, graph, visited, visited_node_twice);
-                paths += ps;
-        }else { // only visit cave if not visited already
-            if visited_node_twice {
-                // we only want to visit nodes, not visited already
-                if !visited.contains(n) { // unvisited
-                    let mut visited = visited.clone();
-                    visited.insert(n);
-                    let ps = visit_node2(n, graph, &visited, visited_node_twice);
-                    paths += ps;
-                }
-
+    let start = index.get("start").ok_or_else(|| ParseError::new("missing start cave"))?;
+    let start = ("start", std::iter::once(*start).collect(), revisit);
+    let count = memoize(start, |rec, &(node, visited, revisit): &Visit| {
+        if node == "end" { // reached end node
+            return 1;
+        }
+        let mut paths = 0;
+        for n in graph.get(node).into_iter().flatten() {
+            if *n == "start" {continue;} // we do not visit start twice
+            if big(n) { // visit cave unconditionally
+                paths += rec.get((n, visited, revisit));
             }else {
-                // we do not care about visiting a node twice
-                let mut visited = visited.clone();
-                let visited_already = visited.contains(n);
-                visited.insert(n);
-                let ps = visit_node2(n, graph, &visited, visited_already);
-                paths += ps;
+                let mut visited = visited;
+                if visited.insert(index[n]) { // unvisited
+                    paths += rec.get((n, visited, revisit));
+                }else if revisit { // use up the second visit
+                    paths += rec.get((n, visited, false));
+                }
             }
         }
-    }
-    paths
+        paths
+    });
+    Ok(count)
 }
 
 fn big(cave: &str) -> bool {
@@ -134,7 +114,7 @@ kj-dc")
 
 fn graph<'a>(edges: Vec<Edge<'a>>) -> Graph<'a> {
     let mut m: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
-    for (n1, n2) in edges {
+    for Edge(n1, n2) in edges {
         if m.contains_key(n1) {
             let hs = m.get_mut(n1).unwrap();
             hs.insert(n2);
@@ -155,11 +135,50 @@ fn graph<'a>(edges: Vec<Edge<'a>>) -> Graph<'a> {
     m
 }
 
-fn parser<'a>(input: &'a str) -> Vec<Edge<'a>> {
-    input.lines().map(|l| {
-        let mut ns = l.split("-");
-        let n1 = ns.next().unwrap();
-        let n2 = ns.next().unwrap();
-        (n1, n2)
-    }).collect()
+fn parser(input: &str) -> Result<Vec<Edge<'_>>, ParseError> {
+    parse::lines(input, |l| {
+        let mut ns = l.split('-');
+        match (ns.next(), ns.next(), ns.next()) {
+            (Some(n1), Some(n2), None) if valid_cave(n1) && valid_cave(n2) => Ok(Edge(n1, n2)),
+            _ => Err(ParseError::new(format!("invalid connection {:?}", l))),
+        }
+    })
+}
+
+fn valid_cave(cave: &str) -> bool {
+    !cave.is_empty() && cave.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input1()), Ok(String::from("10")));
+        assert_eq!(part2(&test_input1()), Ok(String::from("36")));
+        assert_eq!(part1(&test_input2()), Ok(String::from("19")));
+        assert_eq!(part2(&test_input2()), Ok(String::from("103")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parser("start-A\nA\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(edges in prop::collection::vec(("[a-zA-Z]{1,5}", "[a-zA-Z]{1,5}"), 0..20)) {
+            let edges: Vec<Edge> = edges.iter().map(|(n1, n2)| Edge(n1, n2)).collect();
+            let serialized = Lines(&edges).to_string();
+            prop_assert_eq!(parser(&serialized), Ok(edges));
+        }
+
+        #[test]
+        fn test_garbage(input in "([a-zA-Z]{0,3}-?[a-zA-Z1 ]{0,3}\n){0,4}") {
+            let _ = parser(&input);
+        }
+    }
 }
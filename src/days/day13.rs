@@ -1,5 +1,8 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::visualize::{Colour, Frame, Visualize};
 use std::collections::HashSet;
+use std::fmt;
 
 /*
  * Day 12
@@ -12,12 +15,12 @@ use std::collections::HashSet;
 pub struct Day13();
 
 impl AOCDay for Day13 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 13 }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 struct Paper {
     dots: Dots,
     xlen: usize,
@@ -43,26 +46,82 @@ impl ToString for Paper {
 
 type Dots = HashSet<(usize, usize)>;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum Fold {
     Y(usize),
     X(usize),
 }
 
+impl fmt::Display for Fold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fold::Y(n) => write!(f, "fold along y={}", n),
+            Fold::X(n) => write!(f, "fold along x={}", n),
+        }
+    }
+}
+
+/// The dots on the transparent paper and the fold instructions
+#[derive(Debug, PartialEq, Eq)]
+struct Manual {
+    paper: Paper,
+    folds: Vec<Fold>,
+}
+
+impl fmt::Display for Manual {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dots: Vec<&(usize, usize)> = self.paper.dots.iter().collect();
+        dots.sort();
+        for (x, y) in dots {
+            writeln!(f, "{},{}", x, y)?;
+        }
+        writeln!(f)?;
+        for fold in &self.folds {
+            writeln!(f, "{}", fold)?;
+        }
+        Ok(())
+    }
+}
+
+impl Visualize for Day13 {
+    /// The paper before and after every fold
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError> {
+        let Manual {mut paper, folds} = parsing(input)?;
+        let mut frames = vec![paper.frame()];
+        for f in folds {
+            fold_paper(&mut paper, &f);
+            frames.push(paper.frame());
+        }
+        Ok(Box::new(frames.into_iter()))
+    }
+}
+
+const PALETTE: [Colour; 2] = [(0, 0, 0), (255, 255, 255)];
+
+impl Paper {
+    fn frame(&self) -> Frame {
+        let mut frame = Frame::new(self.xlen, self.ylen, &PALETTE);
+        for (x, y) in self.dots.iter() {
+            frame.set(*x, *y, 1);
+        }
+        frame
+    }
+}
+
 pub fn get() -> Day13 {Day13()}
 
-fn part1(input: &str) -> String {
-    let (mut paper, folds) = parsing(&input);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let Manual {mut paper, folds} = parsing(input)?;
     fold_paper(&mut paper, &folds[0]);
-    String::from(format!("{}", paper.dots.len()))
+    Ok(format!("{}", paper.dots.len()))
 }
 
-fn part2(input: &str) -> String {
-    let (mut paper, folds) = parsing(&input);
+fn part2(input: &str) -> Result<String, ParseError> {
+    let Manual {mut paper, folds} = parsing(input)?;
     for f in folds {
         fold_paper(&mut paper, &f);
     }
-    String::from(format!("\n{}", paper.to_string()))
+    Ok(format!("\n{}", paper.to_string()))
 }
 
 fn fold_paper(paper: &mut Paper, fold: &Fold) {
@@ -139,36 +198,65 @@ fold along x=5")
 }
 
 
-fn parsing(input: &str) -> (Paper, Vec<Fold>) {
-    let mut parts = input.split("\n\n");
-    let coords = parts.next().unwrap();
-    let folds = parts.next().unwrap();
+fn parsing(input: &str) -> Result<Manual, ParseError> {
+    let mut parts = parse::sections(input).into_iter();
+    let (coords, folds) = match (parts.next(), parts.next()) {
+        (Some(coords), Some(folds)) => (coords, folds),
+        _ => return Err(ParseError::new("missing fold instructions")),
+    };
 
-    let mut dots: HashSet<(usize, usize)> = HashSet::new();
-    let mut max_x = 0;
-    let mut max_y = 0;
-    for coord in coords.lines() {
-        let mut n = coord.split(',');
-        let x: usize = n.next().unwrap().parse().unwrap();
-        let y: usize = n.next().unwrap().parse().unwrap();
-        max_x = usize::max(max_x, x);
-        max_y = usize::max(max_y, y);
-        dots.insert((x,y));
-    }
+    let dots: HashSet<(usize, usize)> = coords.parse(|text| parse::lines(text, |coord| {
+        let (x, y) = coord.split_once(',').ok_or_else(|| ParseError::new(format!("invalid dot {:?}", coord)))?;
+        Ok((parse::number(coord, x)?, parse::number(coord, y)?))
+    }))?.into_iter().collect();
+    let max_x = dots.iter().map(|d| d.0).max().unwrap_or(0);
+    let max_y = dots.iter().map(|d| d.1).max().unwrap_or(0);
 
-    let mut fold_instrs = Vec::new();
-    for fold in folds.lines() {
-        let mut split = fold.split('=');
-        let tag = split.next().unwrap();
-        let num: usize = split.next().unwrap().parse().unwrap();
-        if tag == "fold along y" {
-            fold_instrs.push(Fold::Y(num));
-        }else if tag == "fold along x"{
-            fold_instrs.push(Fold::X(num));
-        }else {
-            panic!("Unrecognized tag: {}", tag);
+    let fold_instrs = folds.parse(|text| parse::lines(text, |fold| {
+        let (tag, num) = fold.split_once('=').ok_or_else(|| ParseError::new(format!("invalid fold {:?}", fold)))?;
+        let num = parse::number(fold, num)?;
+        match tag {
+            "fold along y" => Ok(Fold::Y(num)),
+            "fold along x" => Ok(Fold::X(num)),
+            _ => Err(ParseError::new(format!("unrecognized tag {:?}", tag)).at(1, 1)),
         }
+    }))?;
+    if fold_instrs.is_empty() {
+        return Err(ParseError::new("missing fold instructions"));
+    }
+
+    Ok(Manual {paper: Paper{dots, xlen: max_x+1, ylen: max_y+1}, folds: fold_instrs})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn manual() -> impl Strategy<Value = Manual> {
+        let fold = prop_oneof![(0..100usize).prop_map(Fold::X), (0..100usize).prop_map(Fold::Y)];
+        (prop::collection::hash_set((0..100usize, 0..100usize), 1..50), prop::collection::vec(fold, 1..10)).prop_map(|(dots, folds)| {
+            let xlen = dots.iter().map(|d| d.0).max().unwrap_or(0) + 1;
+            let ylen = dots.iter().map(|d| d.1).max().unwrap_or(0) + 1;
+            Manual {paper: Paper {dots, xlen, ylen}, folds}
+        })
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parsing("6,10\n").is_err());
+        assert!(parsing("6,10\n\nfold along z=3\n").is_err());
     }
 
-    (Paper{dots, xlen: max_x+1, ylen: max_y+1}, fold_instrs)
+    proptest! {
+        #[test]
+        fn test_roundtrip(manual in manual()) {
+            prop_assert_eq!(parsing(&manual.to_string()), Ok(manual));
+        }
+
+        #[test]
+        fn test_garbage(input in "([0-9]{0,2},?[0-9a]{0,2}\n){0,3}\n?(fold along [xyz]=?[0-9]{0,2}\n){0,2}") {
+            let _ = parsing(&input);
+        }
+    }
 }
@@ -1,6 +1,13 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::bigint::Natural;
+use crate::utils::linalg::{self, Matrix, Ring};
+use crate::utils::memo::Counter;
 
 use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
 
 /*
  * Template for a implementing a day
@@ -9,8 +16,8 @@ use std::collections::HashMap;
 pub struct Day14();
 
 impl AOCDay for Day14 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(solve(_input, 10)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(solve(_input, 40)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { solve(_input, 10) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { solve(_input, 40) }
     fn get_num(&self) -> u32 { 14 }
 }
 
@@ -18,88 +25,98 @@ pub fn get() -> Day14 {Day14()}
 
 type RuleSet = HashMap<(char, char), char>;
 
-fn solve(input: &str, steps: usize) -> String {
-    let (template, ruleset) = parsing(&input);
-    let last_char = template.chars().rev().next().unwrap();
-    let pair_counts = process_polymer(&template, &ruleset, steps);
-    let char_list = char_list(&ruleset);
-    let (min, max) = compute_char_occurence(&pair_counts, &char_list, last_char);
-    String::from(format!("{}", max - min))
+/// Polymer template and pair insertion rules
+#[derive(Debug, PartialEq, Eq)]
+struct Instructions {
+    template: String,
+    ruleset: RuleSet,
 }
 
-fn char_list(ruleset: &RuleSet) -> Vec<char> { // A vector containing all possible chars in the polymer chain
-    let mut v: Vec<char> = Vec::new();
-    for (c1,c2) in ruleset.keys() {
-        v.push(*c1);
-        v.push(*c2);
+impl fmt::Display for Instructions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.template)?;
+        writeln!(f)?;
+        let mut rules: Vec<_> = self.ruleset.iter().collect();
+        rules.sort();
+        for ((c1, c2), c) in rules {
+            writeln!(f, "{}{} -> {}", c1, c2, c)?;
+        }
+        Ok(())
     }
-    v.sort();
-    v.dedup();
-    v
 }
 
-fn process_polymer(template: &str, ruleset: &RuleSet, steps: usize) -> HashMap<(char,char), u64> {
-    /*
-     * Processes the polymer
-     * Keeps track of the number of pairs present after updating the polymer chain `steps` times.
-     */
-    let mut pair_counts: HashMap<(char, char), u64> = HashMap::new();
+fn solve(input: &str, steps: usize) -> Result<String, ParseError> {
+    let difference: u64 = polymer(input, steps)?;
+    Ok(format!("{}", difference))
+}
 
-    // create pair counts
-    for (c1, c2) in ruleset.keys() {
-        pair_counts.insert((*c1,*c2), 0);
-    }
-    let mut chars = template.chars();
-    let mut c1 = chars.next().unwrap();
-    while let Some(c2) = chars.next() {
-        let cur = *pair_counts.get(&(c1,c2)).unwrap();
-        pair_counts.insert((c1,c2), cur + 1);
-        c1 = c2;
-    }
+/// Difference between the most and the least common element after `steps` steps
+pub fn polymer<N: Natural>(input: &str, steps: usize) -> Result<N, ParseError> {
+    let Instructions {template, ruleset} = parsing(input)?;
+    let counts: Counter<char, N> = element_counts(&template, &ruleset, steps);
+    let (min, max) = counts.into_iter().map(|(_, n)| n).minmax().into_option()
+        .ok_or_else(|| ParseError::new("empty template"))?;
+    Ok(max - min)
+}
 
+/// Occurrences of every element in the polymer after `steps` pair insertions.
+/// Steps the count of every pair of neighbours, such that no step depends on the ones after it.
+/// Memoized recursion over (pair, steps left) recursed once per step and overflowed the stack for
+/// the thousands of steps of the `polymer` variant.
+fn element_counts<N: Natural>(template: &str, ruleset: &RuleSet, steps: usize) -> Counter<char, N> {
+    let mut pairs: Counter<(char, char), N> = template.chars().tuple_windows().collect();
     for _ in 0..steps {
-        // update pair counts according to ruleset
-        let mut new_pair_counts: HashMap<(char,char), u64> = HashMap::new();
-
-        for (c1, c2) in pair_counts.keys() {
-            let c = ruleset.get(&(*c1,*c2)).unwrap();
-            let p1 = (*c1, *c); // newly created pair
-            let p2 = (*c, *c2); // newly created pair
-
-            let count = pair_counts.get(&(*c1,*c2)).unwrap();
-
-            let cp1 = new_pair_counts.get_mut(&p1); // &mut u64
-            if cp1.is_some() {
-                cp1.map(|s| *s = *s + count);
-            }else {
-                new_pair_counts.insert(p1, *count);
-            }
-
-            let cp2 = new_pair_counts.get_mut(&p2);
-            if cp2.is_some() {
-                cp2.map(|s| *s = *s + count);
-            }else {
-                new_pair_counts.insert(p2, *count);
-            }
-        }
-        pair_counts = new_pair_counts;
+        // every pair (c1, c2) with insertion c turns into the pairs (c1, c) and (c, c2)
+        pairs = pairs.step(|&(c1, c2)| {
+            let c = ruleset[&(c1, c2)];
+            [((c1, c), N::one()), ((c, c2), N::one())]
+        });
     }
-
-    pair_counts
+    // every element but the first one ends a pair
+    let mut counts: Counter<char, N> = template.chars().take(1).collect();
+    for ((_, c2), n) in pairs {
+        counts.add(c2, n);
+    }
+    counts
 }
 
-fn compute_char_occurence(pair_counts: &HashMap<(char,char), u64>, char_list: &Vec<char>, last_char: char) -> (u64, u64) { // (min, max)
-    let mut map = HashMap::new();
-    for c in char_list {
-        map.insert(c, 0);
+/// Occurrences of every element after `steps` steps, sorted by element.
+/// Counts the pairs with a matrix power, such that the number of steps is only limited by the ring.
+pub fn elements_after<R: Ring>(input: &str, steps: u64) -> Result<Vec<(char, R)>, ParseError> {
+    let Instructions {template, ruleset} = parsing(input)?;
+    let elements = char_list(&ruleset);
+    let index = |c: char| elements.binary_search(&c).unwrap();
+    let pair = |c1: char, c2: char| index(c1) * elements.len() + index(c2);
+
+    // every pair (c1, c2) with insertion c turns into the pairs (c1, c) and (c, c2)
+    let mut step: Matrix<R> = Matrix::zero(elements.len() * elements.len());
+    for (&(c1, c2), &c) in ruleset.iter() {
+        for next in [pair(c1, c), pair(c, c2)] {
+            step[(next, pair(c1, c2))] = step[(next, pair(c1, c2))].clone() + R::from(1);
+        }
+    }
+    let mut pairs = vec![0; step.size()];
+    for (c1, c2) in template.chars().tuple_windows() {
+        pairs[pair(c1, c2)] += 1;
     }
-    for ((c1,_), count) in pair_counts {
-        map.get_mut(c1).map(|c| *c=*c+count);
+    let pairs = linalg::pow(&step, steps).apply(&pairs.into_iter().map(R::from).collect::<Vec<_>>());
+
+    // every element but the first one ends a pair
+    let mut counts: Vec<R> = elements.iter().map(|&c| R::from(template.starts_with(c) as u64)).collect();
+    for (p, n) in pairs.into_iter().enumerate() {
+        let last = p % elements.len();
+        counts[last] = counts[last].clone() + n;
     }
-    map.get_mut(&last_char).map(|c| *c=*c+1);
-    (*map.values().min().unwrap(), *map.values().max().unwrap())
+    Ok(elements.into_iter().zip(counts).collect())
 }
 
+/// All elements occurring in the rules, sorted
+fn char_list(ruleset: &RuleSet) -> Vec<char> {
+    let mut v: Vec<char> = ruleset.iter().flat_map(|((c1, c2), c)| [*c1, *c2, *c]).collect();
+    v.sort();
+    v.dedup();
+    v
+}
 
 /// Test Input
 /// Solutions: Part1 => 1588, Part2 => 2188189693529
@@ -124,18 +141,103 @@ CC -> N
 CN -> C")
 }
 
-fn parsing(input: &str) -> (String, RuleSet) {
-    let mut parts = input.split("\n\n");
-    let template = parts.next().unwrap();
-    let rules = parts.next().unwrap();
-
-    let mut ruleset = HashMap::new();
-    for rule in rules.lines() {
-        let mut s = rule.split(" -> ");
-        let mut pair = s.next().unwrap().chars();
-        let c = s.next().unwrap().chars().next().unwrap();
-        let pair = (pair.next().unwrap(), pair.next().unwrap());
-        ruleset.insert(pair, c);
+fn parsing(input: &str) -> Result<Instructions, ParseError> {
+    let mut parts = parse::sections(input).into_iter();
+    let (template, rules) = match (parts.next(), parts.next()) {
+        (Some(template), Some(rules)) => (template, rules),
+        _ => return Err(ParseError::new("missing pair insertion rules")),
+    };
+    let template = template.text;
+    if template.contains('\n') || !template.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(ParseError::new(format!("invalid template {:?}", template)).at(1, 1));
+    }
+
+    let ruleset: HashMap<(char, char), char> = rules.parse(|text| parse::lines(text, |rule| {
+        let chars: Vec<char> = rule.chars().collect();
+        match chars[..] {
+            [c1, c2, ' ', '-', '>', ' ', c] if [c1, c2, c].iter().all(|c| c.is_ascii_uppercase()) => Ok(((c1, c2), c)),
+            _ => Err(ParseError::new(format!("invalid rule {:?}", rule))),
+        }
+    }))?.into_iter().collect();
+
+    // every pair of elements has to be covered, as the polymer grows
+    let elements: Vec<char> = template.chars().chain(ruleset.iter().flat_map(|((c1, c2), c)| [*c1, *c2, *c])).collect();
+    for c1 in elements.iter() {
+        for c2 in elements.iter() {
+            if !ruleset.contains_key(&(*c1, *c2)) {
+                return Err(ParseError::new(format!("missing rule for pair {}{}", c1, c2)));
+            }
+        }
+    }
+    Ok(Instructions {template: String::from(template), ruleset})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bigint::BigUint;
+    use proptest::prelude::*;
+
+    fn instructions() -> impl Strategy<Value = Instructions> {
+        (1..6usize).prop_flat_map(|n| {
+            let elements: Vec<char> = ('A'..='Z').take(n).collect();
+            let element = prop::sample::select(elements.clone());
+            let pairs: Vec<(char, char)> = elements.iter().flat_map(|c1| elements.iter().map(move |c2| (*c1, *c2))).collect();
+            (prop::collection::vec(element.clone(), 1..20), prop::collection::vec(element, pairs.len()), Just(pairs))
+        }).prop_map(|(template, insertions, pairs)| Instructions {
+            template: template.into_iter().collect(),
+            ruleset: pairs.into_iter().zip(insertions).collect(),
+        })
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(solve(&test_input(), 10), Ok(String::from("1588")));
+        assert_eq!(solve(&test_input(), 40), Ok(String::from("2188189693529")));
+        let difference: BigUint = polymer(&test_input(), 40).unwrap();
+        assert_eq!(difference.to_string(), "2188189693529");
+        // the polymer doubles in length every step
+        assert!(polymer::<BigUint>(&test_input(), 100).unwrap() > BigUint::from(u64::MAX));
+    }
+
+    #[test]
+    fn test_elements_after() {
+        // NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB
+        let elements: Vec<(char, u64)> = elements_after(&test_input(), 4).unwrap();
+        assert_eq!(elements, vec![('B', 23), ('C', 10), ('H', 5), ('N', 11)]);
+
+        let Instructions {template, ruleset} = parsing(&test_input()).unwrap();
+        let stepped: Counter<char, BigUint> = element_counts(&template, &ruleset, 100);
+        let mut stepped: Vec<(char, BigUint)> = stepped.into_iter().collect();
+        stepped.sort();
+        assert_eq!(stepped, elements_after::<BigUint>(&test_input(), 100).unwrap());
+    }
+
+    #[test]
+    fn test_many_steps() {
+        // far beyond any recursion depth, the difference has hundreds of digits
+        let difference = polymer::<BigUint>(&test_input(), 1000).unwrap().to_string();
+        assert_eq!(difference.len(), 302);
+        let elements = elements_after::<BigUint>(&test_input(), 1000).unwrap();
+        let (min, max) = elements.into_iter().map(|(_, n)| n).minmax().into_option().unwrap();
+        assert_eq!(difference, (max - min).to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parsing("NNCB\n").is_err());
+        assert!(parsing("NNCB\n\nCH => B\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(instructions in instructions()) {
+            prop_assert_eq!(parsing(&instructions.to_string()), Ok(instructions));
+        }
+
+        #[test]
+        fn test_garbage(input in "[A-C]{0,4}\n\n([A-Ca]{0,2} -> [A-C]{0,2}\n){0,9}") {
+            let _ = parsing(&input);
+        }
     }
-    (String::from(template), ruleset)
 }
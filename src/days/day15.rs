@@ -1,98 +1,51 @@
 use crate::AOCDay;
-use std::collections::BinaryHeap;
-use std::cmp::Ordering;
+use crate::utils::ParseError;
+use crate::utils::geom::Point2;
+use crate::utils::grid::{Coord, Grid};
+use crate::utils::search;
 
 /*
  * Day 15: Chiton
  * Path finding algorithm, Find shortest path.
  *
- * Optimization: use A* instead of Dijkstra
+ * A* search, where the heuristic is the manhattan distance towards the goal times the lowest risk level.
  */
 
 pub struct Day15();
 
 impl AOCDay for Day15 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 15 }
 }
 pub fn get() -> Day15 {Day15()}
 
-type Grid = Vec<Vec<u32>>;
-type Coord = (usize, usize);
-
-#[derive(Eq,PartialEq)]
-struct Node(Coord, u32);
-
-// Create reversed Ord implementation for use with BinaryHeap
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.1.cmp(&other.1).reverse()
-    }
-}
-
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-fn part1(input: &str) -> String {
-    let grid = parser(&input);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let grid = parser(input)?;
     let cost = shortest_path(&grid);
-    format!("{}", cost)
+    Ok(format!("{}", cost))
 }
 
-fn part2(input: &str) -> String {
-    let grid = parser(&input);
+fn part2(input: &str) -> Result<String, ParseError> {
+    let grid = parser(input)?;
     let grid = expand_map(&grid);
     let cost = shortest_path(&grid);
-    format!("{}", cost)
+    Ok(format!("{}", cost))
 
 }
 
-fn shortest_path(grid: &Grid) -> u32 { // (Path, Cost)
-    // Dijkstra Algorithm
-    let xlen = grid[0].len();
-    let ylen = grid.len();
-    let goal = (xlen-1, ylen-1);
-    
-    let mut queue: BinaryHeap<Node> = BinaryHeap::new();
-    let mut dist: Vec<Vec<u32>> = (0..ylen).map(|_| (0..xlen).map(|_| u32::MAX).collect()).collect();
-
-    dist[0][0] = 0;
-    queue.push(Node((0,0), 0));
-
-    while let Some(Node((x,y),c)) = queue.pop() {
-        if (x,y) == goal { return c; }
-        if c > dist[y][x] { continue; }
-
-        let ns = get_adjacent_inidces(x,y,grid);
-        for (x2,y2) in ns {
-            let node = Node((x2,y2), dist[y][x] + grid[y2][x2]); // node should contain coordinate and distance to it
-            if node.1 < dist[y2][x2] { // found better path
-                dist[y2][x2] = node.1;
-                queue.push(node);
-            }
-        }
-    }
-    0
+fn shortest_path(grid: &Grid<u32>) -> u32 {
+    let goal = (grid.width()-1, grid.height()-1);
+    let min_risk = grid.iter().min().copied().unwrap_or(0);
+    let neighbours = |&c: &Coord| grid.neighbours4(c).map(|n| (n, grid[n]));
+    let heuristic = |&(x, y): &Coord| Point2::new(x, y).manhattan(&Point2::new(goal.0, goal.1)) as u32 * min_risk;
+    let mut dist = Grid::filled(grid.width(), grid.height(), None);
+    dist[(0, 0)] = Some(0);
+    search::astar_cost((0, 0), &mut dist, neighbours, heuristic, |c| *c == goal).unwrap_or(0)
 }
 
-fn expand_map(grid: &Grid) -> Grid {
-    let mut new_grid = Vec::new();
-    for yi in 0..5 {
-        for y in 0..grid.len() {
-            let mut row = Vec::new();
-            for xi in 0..5 {
-                for x in 0..grid[0].len() {
-                    row.push(wrap(grid[y][x]+yi+xi));
-                }
-            }
-            new_grid.push(row);
-        }
-    }
-    new_grid
+fn expand_map(grid: &Grid<u32>) -> Grid<u32> {
+    grid.tile(5, 5, |n, tx, ty| wrap(n + (tx + ty) as u32))
 }
 
 fn wrap(n: u32) -> u32 { // safe, because a number can't reach 18
@@ -100,16 +53,6 @@ fn wrap(n: u32) -> u32 { // safe, because a number can't reach 18
     else {n % 9}
 }
 
-
-
-/// Returns a list of indices of the neighbours of grid[y][x].
-fn get_adjacent_inidces(x: usize, y: usize, grid: &Grid) -> Vec<(usize, usize)> {
-    let (row_size, col_size) = (grid.len() as i32, grid[0].len() as i32);
-    let (x, y) = (x as i32, y as i32);
-    let indices: Vec<(i32, i32)> = vec![(x-1,y), (x,y-1),(x,y+1),(x+1,y)];
-    indices.into_iter().filter(|(x,y)| *x >= 0 && *x < col_size && *y >= 0 && *y < row_size).map(|(x,y)| (x as usize, y as usize)).collect()
-}
-
 fn test_input() -> String {
     String::from("1163751742
 1381373672
@@ -123,11 +66,48 @@ fn test_input() -> String {
 2311944581")
 }
 
-fn parser(input: &str) -> Grid {
-        input.lines().map(|line| {
-            line.trim()
-                .chars()
-                .map(|c| c.to_digit(10).unwrap())
-                .collect()
-        }).collect()
+fn parser(input: &str) -> Result<Grid<u32>, ParseError> {
+    Grid::parse_digits(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn grid() -> impl Strategy<Value = Grid<u32>> {
+        (1..20usize).prop_flat_map(|width| prop::collection::vec(prop::collection::vec(0..10u32, width), 1..20))
+            .prop_map(|rows| Grid::from_rows(rows).unwrap())
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("40")));
+        assert_eq!(part2(&test_input()), Ok(String::from("315")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parser("12\n3\n").is_err());
+        assert!(parser("1a\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(grid in grid()) {
+            prop_assert_eq!(parser(&grid.to_string()), Ok(grid));
+        }
+
+        #[test]
+        fn test_heuristic(grid in grid()) {
+            let goal = (grid.width()-1, grid.height()-1);
+            let dijkstra = search::dijkstra((0, 0), |&c: &Coord| grid.neighbours4(c).map(|n| (n, grid[n])), |c| *c == goal);
+            prop_assert_eq!(Some(shortest_path(&grid)), dijkstra.map(|s| s.cost));
+        }
+
+        #[test]
+        fn test_garbage(input in "([0-9a ]{0,6}\\n){0,4}") {
+            let _ = parser(&input);
+        }
+    }
 }
@@ -1,4 +1,7 @@
 use crate::AOCDay;
+use crate::utils::ParseError;
+
+use std::fmt;
 
 /*
  * Day 16: Packet Decoder
@@ -9,20 +12,20 @@ use crate::AOCDay;
 pub struct Day16();
 
 impl AOCDay for Day16 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 16 }
 }
 
 pub fn get() -> Day16 {Day16()}
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum PType {
     Literal(u64, u64, u64), // version, type ID, value
     Operator(u64, u64, bool, u64) //version, type ID, length type ID, length
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 struct Packet {
     ptype: PType,
     subpackets: Vec<Packet> // empty for Literal
@@ -38,18 +41,53 @@ impl Packet {
             },
         }
     }
+
+    /// Appends the binary encoding of the packet
+    fn encode(&self, bits: &mut String) {
+        match self.ptype {
+            PType::Literal(v, t_id, val) => {
+                bits.push_str(&format!("{:03b}{:03b}", v, t_id));
+                let groups = usize::max(1, (64 - val.leading_zeros() as usize).div_ceil(4));
+                for g in (0..groups).rev() {
+                    let more = if g > 0 {'1'} else {'0'};
+                    bits.push_str(&format!("{}{:04b}", more, (val >> (4 * g)) & 0xF));
+                }
+            },
+            PType::Operator(v, t_id, length_type, length) => {
+                bits.push_str(&format!("{:03b}{:03b}", v, t_id));
+                if length_type {
+                    bits.push_str(&format!("1{:011b}", length));
+                } else {
+                    bits.push_str(&format!("0{:015b}", length));
+                }
+                self.subpackets.iter().for_each(|p| p.encode(bits));
+            },
+        }
+    }
+}
+
+/// Serializes the packet as hexadecimal transmission, padded with zeroes to whole bytes
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bits = String::new();
+        self.encode(&mut bits);
+        while !bits.len().is_multiple_of(8) { bits.push('0'); }
+        for nibble in bits.as_bytes().chunks(4) {
+            let n = nibble.iter().fold(0, |n, b| (n << 1) | (b - b'0') as u32);
+            write!(f, "{:X}", n)?;
+        }
+        writeln!(f)
+    }
 }
 
-fn part1(input: &str) -> String {
-    let binary = hex_to_binary(&input);
-    let (_, p) = parse_packet(&binary);
-    p.version_sum().to_string()
+fn part1(input: &str) -> Result<String, ParseError> {
+    let p = parse(input)?;
+    Ok(p.version_sum().to_string())
 }
 
-fn part2(input: &str) -> String {
-    let binary = hex_to_binary(&input);
-    let (_, p) = parse_packet(&binary);
-    evaluate_packet(&p).to_string()
+fn part2(input: &str) -> Result<String, ParseError> {
+    let p = parse(input)?;
+    Ok(evaluate_packet(&p).to_string())
 }
 
 fn evaluate_packet(p: &Packet) -> u64 {
@@ -70,121 +108,114 @@ fn evaluate_packet(p: &Packet) -> u64 {
     }
 }
 
-fn parse_literal(input: &str) -> (&str, Packet) {
-    let version_s: &str = &input[0..3];
-    let type_id_s: &str = &input[3..6];
-    let version = u64::from_str_radix(version_s, 2).unwrap();
-    let type_id = u64::from_str_radix(type_id_s, 2).unwrap();
-    assert_eq!(type_id, 4, "Packet is not of type_id 4, in parsing literal");
+/// Takes `n` bits from the input as a number
+fn take_bits(input: &str, n: usize) -> Result<(&str, u64), ParseError> {
+    if input.len() < n {
+        return Err(ParseError::new("unexpected end of transmission"));
+    }
+    Ok((&input[n..], u64::from_str_radix(&input[..n], 2)?))
+}
+
+fn parse_literal(input: &str) -> Result<(&str, Packet), ParseError> {
+    let (rest, version) = take_bits(input, 3)?;
+    let (mut rest, type_id) = take_bits(rest, 3)?;
 
     let mut num: u64 = 0;
-    let chars = &input[6..];
-    let mut char_iter = chars.chars();
-    while let Some(b) = char_iter.next() {
-        let num_s: String = char_iter.clone().take(4).collect();
-        char_iter.nth(3); // skip 4 elements
-        num = (num << 4) | u64::from_str_radix(&num_s, 2).unwrap();
-        match b {
-            '1' => {continue;},
-            '0' => {break;},
-            _ => panic!("Invalid binary in parse literal")
+    loop {
+        let (r, more) = take_bits(rest, 1)?;
+        let (r, group) = take_bits(r, 4)?;
+        rest = r;
+        if num >> 60 != 0 {
+            return Err(ParseError::new("literal value exceeds 64 bits"));
         }
+        num = (num << 4) | group;
+        if more == 0 { break; }
     }
 
     let packet = Packet {
         ptype: PType::Literal(version, type_id, num),
         subpackets: Vec::new()
     };
-    let result = (char_iter.as_str(), packet);
-    result
+    Ok((rest, packet))
 }
 
-fn parse_operator(input: &str) -> (&str, Packet) {
-    let type_id_s: &str = &input[3..6];
-    let type_id = u64::from_str_radix(type_id_s, 2).unwrap();
-    assert!(type_id != 4, "Packet is of type_id 4, in parsing operator");
-    let length_type = &input[6..7].chars().next().unwrap();
+fn parse_operator(input: &str) -> Result<(&str, Packet), ParseError> {
+    let (rest, version) = take_bits(input, 3)?;
+    let (rest, type_id) = take_bits(rest, 3)?;
+    let (rest, length_type) = take_bits(rest, 1)?;
 
-    match length_type {
-        '1' => {
-            return parse_subpackets_type1(&input);
-        },
-        '0' => {
-            return parse_subpackets_type0(&input);
-        },
-        _ => panic!("Invalid length type: {}", length_type),
+    let (rest, packet) = if length_type == 1 {
+        parse_subpackets_type1(rest, version, type_id)?
+    } else {
+        parse_subpackets_type0(rest, version, type_id)?
+    };
+    let expected = match type_id {
+        5..=7 => packet.subpackets.len() == 2,
+        _ => !packet.subpackets.is_empty(),
+    };
+    if !expected {
+        return Err(ParseError::new(format!("operator {} with {} subpackets", type_id, packet.subpackets.len())));
     }
+    Ok((rest, packet))
 }
 
-fn parse_subpackets_type0(input: &str) -> (&str, Packet) {
-    let version_s: &str = &input[0..3];
-    let version = u64::from_str_radix(version_s, 2).unwrap();
-    let type_id_s: &str = &input[3..6];
-    let type_id = u64::from_str_radix(type_id_s, 2).unwrap();
-    let length_s = &input[7..22];
-    let length = u64::from_str_radix(length_s, 2).unwrap();
+fn parse_subpackets_type0(input: &str, version: u64, type_id: u64) -> Result<(&str, Packet), ParseError> {
+    let (rest, length) = take_bits(input, 15)?;
     // parse subpackets
-    let rest = &input[22..];
     let mut r = rest;
     let mut subpackets: Vec<Packet> = Vec::new();
-    loop {
-        let (rr, p) = parse_packet(r);
+    while ((rest.len() - r.len()) as u64) < length {
+        let (rr, p) = parse_packet(r)?;
         r = rr;
         subpackets.push(p);
-        let diff = (rest.len() - r.len()) as u64;
-        if diff < length {
-            continue;
-        }else if diff == length {
-            break; // we have parsed the length in packets
-        }else {
-            panic!("Parsed more bits than specified");
-        }
+    }
+    if (rest.len() - r.len()) as u64 != length {
+        return Err(ParseError::new("parsed more bits than specified"));
     }
 
-    (r, Packet {
+    Ok((r, Packet {
         ptype: PType::Operator(version, type_id, false, length),
         subpackets
-    })
+    }))
 }
 
-fn parse_subpackets_type1(input: &str) -> (&str, Packet) {
-    let version_s: &str = &input[0..3];
-    let version = u64::from_str_radix(version_s, 2).unwrap();
-    let type_id_s: &str = &input[3..6];
-    let type_id = u64::from_str_radix(type_id_s, 2).unwrap();
-    let length_s = &input[7..18];
-    let length = u64::from_str_radix(length_s, 2).unwrap();
+fn parse_subpackets_type1(input: &str, version: u64, type_id: u64) -> Result<(&str, Packet), ParseError> {
+    let (mut r, length) = take_bits(input, 11)?;
     // parse subpackets
-    let mut r = &input[18..];
     let mut subpackets: Vec<Packet> = Vec::new();
     for _ in 0..length {
-        let (rr, p) = parse_packet(r);
+        let (rr, p) = parse_packet(r)?;
         r = rr;
         subpackets.push(p);
     }
 
-    (r, Packet {
+    Ok((r, Packet {
         ptype: PType::Operator(version, type_id, true, length),
         subpackets
-    })
+    }))
 }
 
-fn parse_packet(input: &str) -> (&str, Packet) {
-    let type_id_s = &input[3..6];
-    let type_id = u64::from_str_radix(type_id_s, 2).unwrap();
+fn parse_packet(input: &str) -> Result<(&str, Packet), ParseError> {
+    let (_, type_id) = take_bits(input.get(3..).unwrap_or_default(), 3)?;
     match type_id {
-        4 => {return parse_literal(input);},
-        _ => {return parse_operator(input);},
+        4 => parse_literal(input),
+        _ => parse_operator(input),
     }
 }
 
-pub fn hex_to_binary(input: &str) -> String {
+/// Parses the outermost packet of a hexadecimal transmission
+fn parse(input: &str) -> Result<Packet, ParseError> {
+    let binary = hex_to_binary(input)?;
+    Ok(parse_packet(&binary)?.1)
+}
+
+pub fn hex_to_binary(input: &str) -> Result<String, ParseError> {
     let mut result = String::new();
     for c in input.trim().chars() {
-        let n = u32::from_str_radix(&c.to_string(), 16).unwrap();
+        let n = c.to_digit(16).ok_or_else(|| ParseError::new(format!("invalid hexadecimal digit {:?}", c)))?;
         result.push_str(&format!("{:04b}", n));
     }
-    result
+    Ok(result)
 }
 
 fn test_input_literal() -> String {
@@ -214,3 +245,56 @@ fn test_input_nested3() -> String {
 fn test_input_nested4() -> String {
     String::from("A0016C880162017C3686B18A3D4780")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Creates an operator, deriving the length from its subpackets
+    fn operator(version: u64, type_id: u64, length_type: bool, subpackets: Vec<Packet>) -> Packet {
+        let length = if length_type {
+            subpackets.len() as u64
+        } else {
+            let mut bits = String::new();
+            subpackets.iter().for_each(|p| p.encode(&mut bits));
+            bits.len() as u64
+        };
+        Packet {ptype: PType::Operator(version, type_id, length_type, length), subpackets}
+    }
+
+    fn packet() -> impl Strategy<Value = Packet> {
+        let literal = (0..8u64, any::<u64>()).prop_map(|(v, val)| Packet {ptype: PType::Literal(v, 4, val), subpackets: Vec::new()});
+        literal.prop_recursive(4, 32, 4, |inner| prop_oneof![
+            (0..8u64, 0..4u64, any::<bool>(), prop::collection::vec(inner.clone(), 1..4))
+                .prop_map(|(v, t, l, subs)| operator(v, t, l, subs)),
+            (0..8u64, 5..8u64, any::<bool>(), prop::collection::vec(inner, 2))
+                .prop_map(|(v, t, l, subs)| operator(v, t, l, subs)),
+        ])
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(parse(&test_input_literal()).unwrap().ptype, PType::Literal(6, 4, 2021));
+        assert_eq!(part1(&test_input_nested4()), Ok(String::from("31")));
+        assert_eq!(parse(&test_input_nested4()).unwrap().to_string(), test_input_nested4() + "\n");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("D2FEG\n").is_err());
+        assert!(parse("").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(packet in packet()) {
+            prop_assert_eq!(parse(&packet.to_string()), Ok(packet));
+        }
+
+        #[test]
+        fn test_garbage(input in "[0-9A-Fa-g]{0,30}\n?") {
+            let _ = parse(&input);
+        }
+    }
+}
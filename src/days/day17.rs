@@ -1,4 +1,9 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::geom::{Point2, Rect};
+use crate::visualize::{Colour, Frame, Visualize};
+
+use std::fmt;
 
 /*
  * Day 17: Trick Shot
@@ -9,23 +14,43 @@ use crate::AOCDay;
 pub struct Day17();
 
 impl AOCDay for Day17 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 17 }
 }
 
+impl Visualize for Day17 {
+    /// The probe following the highest trajectory, scaled down to fit a terminal
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError> {
+        let area = parse(input)?;
+        let velocity = highest_trajectory(&area).ok_or_else(|| ParseError::new("target area can not be hit"))?;
+        let mut probe = Probe {velocity, position: Point2::default()};
+        let mut trail = vec![probe.position];
+        while !past_area(probe.position, &area).0 {
+            simulate_step(&mut probe);
+            trail.push(probe.position);
+        }
+        let frames: Vec<Frame> = (1..trail.len()+1).map(|n| trajectory_frame(&area, &trail[..n])).collect();
+        Ok(Box::new(frames.into_iter()))
+    }
+}
+
 pub fn get() -> Day17 {Day17()}
 
-#[derive(Debug)]
-struct Area {
-    xlim: (i32, i32), // (xmin, xmax)
-    ylim: (i32, i32), // (ymin, ymax)
+#[derive(Debug, PartialEq, Eq)]
+struct Area(Rect);
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Area(Rect {min, max}) = self;
+        writeln!(f, "target area: x={}..{}, y={}..{}", min.x, max.x, min.y, max.y)
+    }
 }
 
 #[derive(Debug)]
 struct Probe {
-    velocity: (i32, i32),
-    position: (i32, i32)
+    velocity: Point2,
+    position: Point2,
 }
 
 #[derive(Debug)]
@@ -36,27 +61,27 @@ enum RelPos {
 }
 
 
-fn part1(input: &str) -> String {
-    let area = parse(&input);
-    let (_,y) = highest_trajectory(&area).unwrap();
+fn part1(input: &str) -> Result<String, ParseError> {
+    let area = parse(input)?;
+    let y = highest_trajectory(&area).ok_or_else(|| ParseError::new("target area can not be hit"))?.y;
     let n = (y * (y+1)) / 2;
-    n.to_string()
+    Ok(n.to_string())
 }
 
-fn part2(input: &str) -> String {
-    let area = parse(&input);
+fn part2(input: &str) -> Result<String, ParseError> {
+    let area = parse(input)?;
     let n = number_of_hits(&area);
-    n.to_string()
+    Ok(n.to_string())
 }
 
-fn highest_trajectory(area: &Area) -> Option<(i32, i32)> {
+fn highest_trajectory(area: &Area) -> Option<Point2> {
     let y_max = max_velocity_y(&area);
     let x_start = min_velocity_x(&area);
     let x_end = max_velocity_x(&area);
     for y in (0..y_max+1).into_iter().rev() {
         for x in x_start..x_end+1 {
             if simulate(x, y, &area) {
-                return Some((x,y));
+                return Some(Point2::new(x, y));
             };
         }
     }
@@ -81,26 +106,22 @@ fn number_of_hits(area: &Area) -> u32 {
 }
 
 fn simulate_step(probe: &mut Probe) {
-    probe.position = (probe.position.0 + probe.velocity.0, probe.position.1 + probe.velocity.1); // update position
-    let (x_v, y_v) = probe.velocity;
-    let dx = if x_v == 0 {0} else {-x_v / x_v};
-    probe.velocity = (x_v + dx, y_v - 1);
+    probe.position += probe.velocity; // update position
+    probe.velocity -= Point2::new(probe.velocity.x.signum(), 1); // drag and gravity
 }
 
 fn simulate(x_v: i32, y_v: i32, target: &Area) -> bool {
-    let mut p = Probe {velocity: (x_v, y_v), position: (0, 0)};
-    while !past_area(p.position.0, p.position.1, target).0 {
-        if within_area(p.position.0, p.position.1, target) {return true;}
+    let mut p = Probe {velocity: Point2::new(x_v, y_v), position: Point2::default()};
+    while !past_area(p.position, target).0 {
+        if target.0.contains(&p.position) {return true;}
         simulate_step(&mut p);
     }
     return false;
 }
 
-fn within_area(x: i32, y: i32, area: &Area) -> bool {x >= area.xlim.0 && x <= area.xlim.1 && y >= area.ylim.0 && y <= area.ylim.1}
-
-fn past_area(x: i32, y: i32, area: &Area) -> (bool, RelPos) {
-    let x_past = x > area.xlim.1;
-    let y_past = y < area.ylim.0;
+fn past_area(p: Point2, area: &Area) -> (bool, RelPos) {
+    let x_past = p.x > area.0.max.x;
+    let y_past = p.y < area.0.min.y;
     match (x_past, y_past) {
         (true, true) => {(true, RelPos::RightDown)},
         (true, false) => {(true, RelPos::Right)},
@@ -113,35 +134,102 @@ fn min_velocity_x(area: &Area) -> i32 {
     let mut i = 0;
     loop {
         let n = (i * (i+1)) / 2;
-        if n >= area.xlim.0 {return i;}
+        if n >= area.0.min.x {return i;}
         i += 1;
     }
 }
-fn max_velocity_x(area: &Area) -> i32 { area.xlim.1 }
+fn max_velocity_x(area: &Area) -> i32 { area.0.max.x }
+
+fn min_velocity_y(area: &Area) -> i32 { area.0.min.y }
+fn max_velocity_y(area: &Area) -> i32 { i32::abs(area.0.min.y) }
+
+
+
+// Maximum frame size in cells
+const FRAME_WIDTH: i32 = 160;
+const FRAME_HEIGHT: i32 = 100;
+const PALETTE: [Colour; 4] = [(0, 0, 30), (40, 120, 40), (120, 120, 120), (255, 60, 60)];
+
+/// Frame showing the target area, the trail of the probe and its current position.
+fn trajectory_frame(area: &Area, trail: &[Point2]) -> Frame {
+    let Area(target) = area;
+    let (xmin, xmax) = (0, target.max.x);
+    let ymin = target.min.y;
+    let ymax = trail.iter().map(|p| p.y).max().unwrap().max(0);
+    // cells per frame pixel
+    let sx = (xmax - xmin) / FRAME_WIDTH + 1;
+    let sy = (ymax - ymin) / FRAME_HEIGHT + 1;
+    let (width, height) = ((xmax - xmin) / sx + 1, (ymax - ymin) / sy + 1);
+    let to_pixel = |x: i32, y: i32| (((x - xmin) / sx) as usize, ((ymax - y) / sy) as usize);
+
+    let mut frame = Frame::new(width as usize, height as usize, &PALETTE);
+    for y in target.min.y..target.max.y+1 {
+        for x in target.min.x..target.max.x+1 {
+            let (px, py) = to_pixel(x, y);
+            frame.set(px, py, 1);
+        }
+    }
+    let (last, trail) = trail.split_last().unwrap();
+    for p in trail.iter().filter(|p| p.x <= xmax && p.y >= ymin) {
+        let (px, py) = to_pixel(p.x, p.y);
+        frame.set(px, py, 2);
+    }
+    if last.x <= xmax && last.y >= ymin {
+        let (px, py) = to_pixel(last.x, last.y);
+        frame.set(px, py, 3);
+    }
+    frame
+}
 
-fn min_velocity_y(area: &Area) -> i32 { area.ylim.0 }
-fn max_velocity_y(area: &Area) -> i32 { i32::abs(area.ylim.0) }
+fn parse(input: &str) -> Result<Area, ParseError> {
+    let line = input.trim_end();
+    let area = line.strip_prefix("target area: ").ok_or_else(|| ParseError::new("expected \"target area: \"").at(1, 1))?;
+    let (x, y) = area.split_once(", ").ok_or_else(|| ParseError::new("expected x and y range").locate(line, area))?;
+    let (x1, x2) = parse::range(line, x, "x")?;
+    let (y1, y2) = parse::range(line, y, "y")?;
+    let target = Rect::from_corners(Point2::new(x1, y1), Point2::new(x2, y2));
+    if target.min.x <= 0 || target.max.y >= 0 {
+        return Err(ParseError::new("target area must be to the right of and below the launcher"));
+    }
+    Ok(Area(target))
+}
 
+fn test_input() -> String {
+    String::from("target area: x=20..30, y=-10..-5")
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
 
-fn parse(input: &str) -> Area {
-    let input = &input[13..].trim_end();
-    let mut s = input.split(", ");
-    let x_s = &s.next().unwrap()[2..]; 
-    let y_s = &s.next().unwrap()[2..]; 
+    fn area() -> impl Strategy<Value = Area> {
+        (1..10000i32, 0..1000i32, -10000..0i32, 0..1000i32).prop_map(|(xmin, w, ymax, h)| Area(Rect {min: Point2::new(xmin, ymax - h), max: Point2::new(xmin + w, ymax)}))
+    }
 
-    let mut x_nums = x_s.split("..");
-    let x1 = i32::from_str_radix(x_nums.next().unwrap(), 10).unwrap();
-    let x2 = i32::from_str_radix(x_nums.next().unwrap(), 10).unwrap();
-    let (xmin, xmax) = (i32::min(x1,x2), i32::max(x1,x2));
+    #[test]
+    fn test_frames() {
+        assert!(get().frames("target area: x=20..30, y=-10..-5").unwrap().count() > 0);
+        // only odd positions are reached when dropping to y=-1
+        assert!(get().frames("target area: x=4..4, y=-1..-1").is_err());
+        assert!(get().frames("target area: x=4..4").is_err());
+    }
 
-    let mut y_nums = y_s.split("..");
-    let y1 = i32::from_str_radix(y_nums.next().unwrap(), 10).unwrap();
-    let y2 = i32::from_str_radix(y_nums.next().unwrap(), 10).unwrap();
-    let (ymin, ymax) = (i32::min(y1,y2), i32::max(y1,y2));
-    Area {xlim: (xmin,xmax), ylim: (ymin,ymax)}
-}
+    #[test]
+    fn test_invalid() {
+        assert!(parse("target area: x=20..30\n").is_err());
+        assert!(parse("x=20..30, y=-10..-5\n").is_err());
+    }
 
-fn test_input() -> String {
-    String::from("target area: x=20..30, y=-10..-5")
+    proptest! {
+        #[test]
+        fn test_roundtrip(area in area()) {
+            prop_assert_eq!(parse(&area.to_string()), Ok(area));
+        }
+
+        #[test]
+        fn test_garbage(input in "(target area: )?x=-?[0-9]{0,3}(\\.\\.)?-?[0-9]{0,3},? ?y=?-?[0-9]{0,3}\\.?\\.?-?[0-9]{0,12}\n?") {
+            let _ = parse(&input);
+        }
+    }
 }
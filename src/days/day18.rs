@@ -1,4 +1,9 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
 
 use itertools::Itertools;
 
@@ -11,14 +16,14 @@ use itertools::Itertools;
 pub struct Day18();
 
 impl AOCDay for Day18 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 18 }
 }
 
 pub fn get() -> Day18 {Day18()}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Snail {
     LB,
     RB,
@@ -27,6 +32,27 @@ enum Snail {
 
 type Number = Vec<Snail>;
 
+/// Serializes a snailfish number in its written form, e.g. `[[1,2],3]`
+struct Snailfish<'a>(&'a Number);
+
+impl fmt::Display for Snailfish<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut prev: Option<&Snail> = None;
+        for snail in self.0 {
+            // elements of a pair are separated by a comma
+            let separated = matches!(prev, Some(Snail::Num(_)) | Some(Snail::RB)) && !matches!(snail, Snail::RB);
+            if separated { write!(f, ",")?; }
+            match snail {
+                Snail::Num(n) => write!(f, "{}", n)?,
+                Snail::RB => write!(f, "]")?,
+                Snail::LB => write!(f, "[")?,
+            }
+            prev = Some(snail);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 enum Reduction {
     Explode(usize),
@@ -34,19 +60,19 @@ enum Reduction {
     Nothing,
 }
 
-fn part1(input: &str) -> String {
-    let numbers = parse(&input);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let numbers = parse(input)?;
     let mut iter = numbers.into_iter();
     let mut num = iter.next().unwrap();
     while let Some(mut snail) = iter.next() {
         addition(&mut num, &mut snail);
         reduce(&mut num);
     }
-    magnitude(&num).to_string()
+    Ok(magnitude(&num).to_string())
 }
 
-fn part2(input: &str) -> String {
-    let numbers = parse(&input);
+fn part2(input: &str) -> Result<String, ParseError> {
+    let numbers = parse(input)?;
     let perms = numbers.into_iter().permutations(2);
     let mut max = 0;
     for perm in perms {
@@ -57,7 +83,7 @@ fn part2(input: &str) -> String {
         reduce(&mut n1);
         max = u32::max(max, magnitude(&n1));
     }
-    max.to_string()
+    Ok(max.to_string())
 }
 
 fn magnitude(num: &Number) -> u32 {
@@ -179,40 +205,46 @@ fn addition(s1: &mut Number, s2: &mut Number) {
     s1.push(Snail::RB);
 }
 
-fn parse_line(input: &str) -> Number {
-    let mut chars = input.trim().chars();
-    let mut snails = Vec::new();
-    while let Some(c) = chars.next() {
-        match c {
-            '[' => {snails.push(Snail::LB)},
-            ']' => {snails.push(Snail::RB)},
-            ',' => {},
-            _ => {snails.push(Snail::Num(u32::from_str_radix(&c.to_string(), 10).unwrap()))},
-        }
+/// Parses a pair or a regular number
+fn parse_element(chars: &mut Peekable<Chars>, snails: &mut Number) -> Result<(), ParseError> {
+    match chars.next() {
+        Some('[') => {
+            snails.push(Snail::LB);
+            parse_element(chars, snails)?;
+            if chars.next() != Some(',') {
+                return Err(ParseError::new("expected ',' between elements of a pair"));
+            }
+            parse_element(chars, snails)?;
+            if chars.next() != Some(']') {
+                return Err(ParseError::new("expected ']' after a pair"));
+            }
+            snails.push(Snail::RB);
+        },
+        Some(c) if c.is_ascii_digit() => {snails.push(Snail::Num(c.to_digit(10).unwrap()))},
+        c => {return Err(ParseError::new(format!("unexpected {:?}", c)))},
     }
-    snails
+    Ok(())
 }
 
-fn parse(input: &str) -> Vec<Number> {
-    let mut v = Vec::new();
-    for line in input.lines() {
-        let n = parse_line(line);
-        v.push(n)
+fn parse_line(input: &str) -> Result<Number, ParseError> {
+    let mut chars = input.trim().chars().peekable();
+    if chars.peek() != Some(&'[') {
+        return Err(ParseError::new("snailfish number must be a pair"));
+    }
+    let mut snails = Vec::new();
+    parse_element(&mut chars, &mut snails)?;
+    if chars.next().is_some() {
+        return Err(ParseError::new("trailing characters after snailfish number"));
     }
-    v
+    Ok(snails)
 }
 
-fn to_string(snail: &Number) -> String {
-    let mut s = String::with_capacity(snail.len());
-    let mut iter = snail.iter();
-    while let Some(snail) = iter.next() {
-        match snail {
-            Snail::Num(n) => {s.push_str(&format!("({})", n));},
-            Snail::RB => {s.push_str("]")},
-            Snail::LB => {s.push_str("[")},
-        }
+fn parse(input: &str) -> Result<Vec<Number>, ParseError> {
+    let numbers = parse::lines(input, parse_line)?;
+    if numbers.is_empty() {
+        return Err(ParseError::new("no snailfish numbers"));
     }
-    s
+    Ok(numbers)
 }
 
 fn test_input1() -> String {
@@ -284,3 +316,48 @@ fn test_magnitude2() -> String {
 fn test_magnitude3() -> String {
     String::from("[[[[1,1],[2,2]],[3,3]],[4,4]]")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pair() -> impl Strategy<Value = Number> {
+        let leaf = (0..10u32).prop_map(|n| vec![Snail::Num(n)]);
+        let element = leaf.prop_recursive(4, 32, 2, |inner| (inner.clone(), inner).prop_map(|(a, b)| wrap(a, b)));
+        (element.clone(), element).prop_map(|(a, b)| wrap(a, b))
+    }
+
+    fn wrap(mut a: Number, mut b: Number) -> Number {
+        a.insert(0, Snail::LB);
+        a.append(&mut b);
+        a.push(Snail::RB);
+        a
+    }
+
+    #[test]
+    fn test_examples() {
+        let numbers = parse(&test_input2()).unwrap();
+        assert_eq!(Snailfish(&numbers[0]).to_string(), "[[[0,[4,5]],[0,0]],[[[4,5],[2,6]],[9,5]]]");
+        assert_eq!(part1(&test_input2()), Ok(String::from("3488")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("[1,2\n").is_err());
+        assert!(parse("[1,2]x\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(numbers in prop::collection::vec(pair(), 1..10)) {
+            let serialized: String = numbers.iter().map(|n| format!("{}\n", Snailfish(n))).collect();
+            prop_assert_eq!(parse(&serialized), Ok(numbers));
+        }
+
+        #[test]
+        fn test_garbage(input in "([\\[\\],0-9a]{0,20}\\n){0,3}") {
+            let _ = parse(&input);
+        }
+    }
+}
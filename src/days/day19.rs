@@ -1,19 +1,17 @@
 use crate::AOCDay;
+use crate::stats;
+use crate::utils::{parse, ParseError};
+use crate::utils::geom::{Point3, Rotation};
 
 use std::collections::HashMap;
-use std::cmp::Ordering;
-
-use nalgebra::base::{Vector3, Matrix3};
+use std::fmt;
 
 use nom::{
     bytes::complete::tag,
     sequence::{preceded, terminated},
     character::complete,
-    error::Error,
 };
 
-use itertools::Itertools;
-
 /*
  * Day 19: Beacon Scanner
  *
@@ -24,17 +22,27 @@ use itertools::Itertools;
 pub struct Day19();
 
 impl AOCDay for Day19 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 19 }
 }
 
 pub fn get() -> Day19 {Day19()}
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 struct Scanner {
     id: u32,
-    beacons: Vec<Vector3<i32>>,
+    beacons: Vec<Point3>,
+}
+
+impl fmt::Display for Scanner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- scanner {} ---", self.id)?;
+        for b in &self.beacons {
+            writeln!(f, "{},{},{}", b.x, b.y, b.z)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -42,8 +50,8 @@ struct Scanner {
 struct ScannerInfo {
     scanner: Scanner,
     inner_distances: Vec<Distance>, // needs to be sorted by dist for optimal performance
-    position: Option<Vector3<i32>>,
-    orientation: Option<Matrix3<i32>>,
+    position: Option<Point3>,
+    orientation: Option<Rotation>,
 }
 
 #[derive(Debug, Clone)]
@@ -57,22 +65,22 @@ struct Distance {
 const ALIGNMENT_THRESHOLD: u32 = 12;
 const EDGE_THRESHOLD: u32 = ALIGNMENT_THRESHOLD * (ALIGNMENT_THRESHOLD - 1) / 2;
 
-fn part1(input: &str) -> String {
-    let scanners: Vec<ScannerInfo> = parse(&input).into_iter().map(analyze_scanner).collect();
+fn part1(input: &str) -> Result<String, ParseError> {
+    let scanners: Vec<ScannerInfo> = parse(input)?.into_iter().map(analyze_scanner).collect();
     let aligned = align_scanners(scanners);
     // Check Number of Beacons
     let mut beacons = Vec::new();
     for scanner_info in aligned.values() {
-        let mut bs: Vec<Vector3<i32>> = scanner_info.scanner.beacons.iter().map(|v| scanner_info.orientation.unwrap() * v + scanner_info.position.unwrap()).collect();
+        let mut bs: Vec<Point3> = scanner_info.scanner.beacons.iter().map(|v| scanner_info.orientation.unwrap() * *v + scanner_info.position.unwrap()).collect();
         beacons.append(&mut bs);
     }
-    beacons.sort_by(|x,y|compare_vector(&x,&y));
-    beacons.dedup_by(|x,y|compare_vector(&x,&y) == Ordering::Equal);
-    beacons.len().to_string()
+    beacons.sort();
+    beacons.dedup();
+    Ok(beacons.len().to_string())
 }
 
-fn part2(input: &str) -> String {
-    let scanners: Vec<ScannerInfo> = parse(&input).into_iter().map(analyze_scanner).collect();
+fn part2(input: &str) -> Result<String, ParseError> {
+    let scanners: Vec<ScannerInfo> = parse(input)?.into_iter().map(analyze_scanner).collect();
     let aligned = align_scanners(scanners);
 
     let mut scanner_positions = Vec::new();
@@ -83,30 +91,29 @@ fn part2(input: &str) -> String {
     let mut max_dist = 0;
     while let Some(pos) = scanner_positions.pop() {
         for p in scanner_positions.iter() {
-            max_dist = i32::max(max_dist, manhattan_distance(&pos, &p));
+            max_dist = i32::max(max_dist, pos.manhattan(p));
         }
     }
-    max_dist.to_string()
+    Ok(max_dist.to_string())
 }
 
 /// Aligns `s1` with neighbour `s2`, requires that `s2` has a known position and orientation.
 fn align_scanner(s1: &mut ScannerInfo, s2: &ScannerInfo) -> bool {
     assert!(s2.position.is_some() && s2.orientation.is_some(), "'s2' must have a known orientation and position");
 
-    let mut s2_diffs: Vec<Vector3<i32>> = position_differences(&s2.scanner.beacons);
-    s2_diffs.sort_by(|v1,v2| compare_vector(&v1,v2));
+    let s2_diffs: Vec<Point3> = position_differences(&s2.scanner.beacons);
 
     // Step 1. Find correct configuration
     let mut rotational_alignment = false;
     let mut orientation = None;
 
-    for rotation in possible_orientations() {
+    for rotation in Rotation::all() {
+        stats::count("rotation attempts", 1);
         // Apply rotation to beacon locations
-        let mut beacons: Vec<Vector3<i32>> = s1.scanner.beacons.clone().into_iter().map(|v| rotation * v).collect();
-        beacons.sort_by(|v1,v2| compare_vector(&v1,v2));
+        let mut beacons: Vec<Point3> = s1.scanner.beacons.iter().map(|v| rotation * *v).collect();
+        beacons.sort();
         // Compute new differences between beacons
-        let mut diffs = position_differences(&beacons);
-        diffs.sort_by(|v1,v2| compare_vector(&v1,v2));
+        let diffs = position_differences(&beacons);
 
         let eq_diffs = equal_vector_count(&diffs, &s2_diffs);
 
@@ -122,22 +129,22 @@ fn align_scanner(s1: &mut ScannerInfo, s2: &ScannerInfo) -> bool {
 
     // Step 2. Find offset, which causes probes to overlap
     let mut positional_alignment = false;
-    let mut position: Option<Vector3<i32>> = None;
+    let mut position: Option<Point3> = None;
 
-    let mut s2_beacons: Vec<Vector3<i32>> = s2.scanner.beacons.clone().into_iter().map(|v| s2.orientation.unwrap() * v).collect();
-    s2_beacons.sort_by(|v1,v2|compare_vector(&v1,v2));
+    let mut s2_beacons: Vec<Point3> = s2.scanner.beacons.iter().map(|v| s2.orientation.unwrap() * *v).collect();
+    s2_beacons.sort();
 
-    let mut s1_beacons: Vec<Vector3<i32>> = s1.scanner.beacons.clone().into_iter().map(|v| orientation.unwrap() * v).collect();
-    s1_beacons.sort_by(|v1,v2|compare_vector(&v1,v2));
+    let mut s1_beacons: Vec<Point3> = s1.scanner.beacons.iter().map(|v| orientation.unwrap() * *v).collect();
+    s1_beacons.sort();
 
     let mut stack = s2_beacons.clone();
     'outer: while let Some(s2_beacon) = stack.pop() {
         // Use s1_beacon as reference point
         for s1_beacon in s1_beacons.iter() {
             // align s2_beacon with s1_beacon and check whether alignment is correct
-            let offset = s2_beacon - s1_beacon; // offset + x1 = x2 (if correct) 
-            let mut aligned_beacons: Vec<Vector3<i32>> = s1_beacons.clone().into_iter().map(|v| offset + v).collect();
-            aligned_beacons.sort_by(|x,y|compare_vector(&x,&y));
+            stats::count("offset attempts", 1);
+            let offset = s2_beacon - *s1_beacon; // offset + x1 = x2 (if correct) 
+            let aligned_beacons: Vec<Point3> = s1_beacons.iter().map(|v| offset + *v).collect(); // still sorted
 
             let eq = equal_vector_count(&aligned_beacons, &s2_beacons);
             if eq >= ALIGNMENT_THRESHOLD {
@@ -156,6 +163,7 @@ fn align_scanner(s1: &mut ScannerInfo, s2: &ScannerInfo) -> bool {
 
 /// Aligns all scanners
 fn align_scanners(scanners: Vec<ScannerInfo>) -> HashMap<u32, ScannerInfo> {
+    let _span = stats::span("alignment");
     let mut unaligned = HashMap::new();
     let mut visited = HashMap::new();
 
@@ -165,8 +173,8 @@ fn align_scanners(scanners: Vec<ScannerInfo>) -> HashMap<u32, ScannerInfo> {
 
     // Remove first scanner and make it the base reference frame
     let mut s0 = unaligned.remove(&0).unwrap();
-    s0.position = Some(Vector3::from_element(0));
-    s0.orientation = Some(Matrix3::identity());
+    s0.position = Some(Point3::default());
+    s0.orientation = Some(Rotation::IDENTITY);
 
     let mut queue = Vec::new();
     queue.push(s0);
@@ -178,6 +186,7 @@ fn align_scanners(scanners: Vec<ScannerInfo>) -> HashMap<u32, ScannerInfo> {
         // Remove potential candidates
         for id in ids {
             let mut s = unaligned.remove(&id).unwrap(); // temporariliy take ownership of scanner
+            stats::count("alignment attempts", 1);
             let success = align_scanner(&mut s, &scanner_info);
 
             // Check whether scanner was aligned successfully
@@ -222,26 +231,15 @@ fn analyze_scanner(scanner: Scanner) -> ScannerInfo {
     }
 }
 
-/// Manhattan distance between two vectors
-fn manhattan_distance(v1: &Vector3<i32>, v2: &Vector3<i32>) -> i32 {
-    v1.zip_map(v2, |a,b| i32::abs(a-b)).fold(0,|a,e| a+e)
-}
-
-/// Euclidean distance between two vectors
-fn euclidean_distance(v1: &Vector3<i32>, v2: &Vector3<i32>) -> f32 {
-    let f = v1.zip_map(v2, |a,b| i32::pow(a-b, 2)).fold(0,|a,e| a+e) as f32;
-    f32::sqrt(f)
-}
-
 /// Computes a list of all distances between the beacons
 fn manhattan_distances(scanner: &Scanner) -> Vec<Distance> {
     let mut distances = Vec::with_capacity(((scanner.beacons.len()-1) * scanner.beacons.len()) / 2);
-    let mut stack: Vec<(usize, &Vector3<i32>)> = scanner.beacons.iter().enumerate().collect();
+    let mut stack: Vec<(usize, &Point3)> = scanner.beacons.iter().enumerate().collect();
     while stack.len() > 1 {
         let (i1, beacon1) = stack.pop().unwrap();
         // compare element with elements left in stack
         for (i2, beacon2) in stack.iter() {
-            let dist = manhattan_distance(beacon1, beacon2) as u32;
+            let dist = beacon1.manhattan(beacon2) as u32;
             distances.push(Distance{src: i1, target: *i2, dist});
         }
     }
@@ -250,17 +248,17 @@ fn manhattan_distances(scanner: &Scanner) -> Vec<Distance> {
 }
 
 /// Calculates the differences between the beacon locations
-fn position_differences(beacons: &Vec<Vector3<i32>>) -> Vec<Vector3<i32>> {
+fn position_differences(beacons: &[Point3]) -> Vec<Point3> {
     let mut differences = Vec::with_capacity(((beacons.len()-1) * beacons.len()) / 2);
-    let mut stack: Vec<&Vector3<i32>> = beacons.iter().collect();
+    let mut stack: Vec<&Point3> = beacons.iter().collect();
     while stack.len() > 1 {
         let beacon1 = stack.pop().unwrap();
         // compare element with elements left in stack
         for beacon2 in stack.iter() {
-            differences.push(beacon1 - *beacon2);
+            differences.push(*beacon1 - **beacon2);
         }
     }
-    differences.sort_by(|v1,v2| compare_vector(&v1, &v2));
+    differences.sort();
     differences
 }
 
@@ -288,19 +286,19 @@ fn equal_distance_count(s1: &ScannerInfo, s2: &ScannerInfo) -> u32 {
 }
 
 /// Requires `s1` and `s2` are sorted
-fn equal_vector_count(s1: &Vec<Vector3<i32>>, s2: &Vec<Vector3<i32>>) -> u32 {
+fn equal_vector_count(s1: &[Point3], s2: &[Point3]) -> u32 {
     let d1 = s1; // inner_distances are sorted
     let d2 = s2;
     let mut count = 0;
     let (mut i1, mut i2) = (0, 0);
     loop {
         if i1 >= d1.len() || i2 >= d2.len() {break;} // loop guard
-        if compare_vector(&d1[i1], &d2[i2]) == Ordering::Equal { // found an equal distance
+        if d1[i1] == d2[i2] { // found an equal distance
             count+=1;
             i1 += 1;
             i2 += 1;
         }
-        else if compare_vector(&d1[i1], &d2[i2]) == Ordering::Greater {
+        else if d1[i1] > d2[i2] {
             i2 += 1;
         }else {
             i1 += 1;
@@ -309,83 +307,33 @@ fn equal_vector_count(s1: &Vec<Vector3<i32>>, s2: &Vec<Vector3<i32>>) -> u32 {
     count
 }
 
-/// Returns all possible orientations that the scanner could be in the form of rotation matrices
-fn possible_orientations() -> Vec<Matrix3<i32>> {
-    let matrices: Vec<Matrix3<i32>> = vec![vec![1,0,0],vec![0,1,0],vec![0,0,1]].into_iter().permutations(3).map(|e| {
-        Matrix3::from_iterator(e.concat().into_iter())
-    }).map(|m| {
-        let mut m2 = m.clone();
-        multiply_row(&mut m2, 0, -1);
-        vec![m, m2]
-    }).flatten().map(|m| {
-        let mut m2 = m.clone();
-        multiply_row(&mut m2, 1, -1);
-        vec![m, m2]
-    }).flatten().map(|m| {
-        let mut m2 = m.clone();
-        multiply_row(&mut m2, 2, -1);
-        vec![m, m2]
-    }).flatten().filter(|m| det(&m) == 1).collect();
-    matrices
-}
-
-fn multiply_row(matrix: &mut Matrix3<i32>, index: usize, scalar: i32) {
-    for i in 0..3 {matrix[i*3+index] = matrix[i*3+index] * scalar;} // column major matrix
-}
-
-/// Determinant of 3x3 Matrix 
-fn det(m: &Matrix3<i32>) -> i32 {
-    let mut d = m[0] * (m[3*1+1]*m[3*2+2]-m[3*2+1]*m[3*1+2]);
-    d = d - m[3]*(m[1]*m[8]-m[7]*m[2]);
-    d = d + m[6]*(m[1]*m[5]-m[4]*m[2]);
-    d
-}
-
-/// Utility function for comparing vectors (useful for sorting)
-fn compare_vector(v1: &Vector3<i32>, v2: &Vector3<i32>) -> Ordering {
-    if v1[0] == v2[0] {
-        if v1[1] == v2[1] {
-            if v1[2] == v2[2] { Ordering::Equal
-            }else if v1[2] > v2[2] { Ordering::Greater
-            }else { Ordering::Less}
-        }else if v1[1] > v2[1] {Ordering::Greater
-        }else {Ordering::Less}
-    }else if v1[0] > v2[0] {Ordering::Greater
-    }else {Ordering::Less}
-}
-
-
 // --- PARSING ---
-fn parse_scanner_id(input: &str) -> u32 {
-    let mut parser = preceded::<&str, _, _, Error<&str>, _, _>(tag("--- scanner "), terminated(complete::u32, tag(" ---")));
-    parser(input).unwrap().1
+fn parse_scanner_id(input: &str) -> Result<u32, ParseError> {
+    parse::complete(input, preceded(tag("--- scanner "), terminated(complete::u32, tag(" ---"))))
 }
 
-fn parse_beacon(input: &str) -> Vector3<i32> {
-    let mut coords = input.trim().split(',');
-    let x = i32::from_str_radix(coords.next().unwrap(), 10).unwrap();
-    let y = i32::from_str_radix(coords.next().unwrap(), 10).unwrap();
-    let z = i32::from_str_radix(coords.next().unwrap(), 10).unwrap();
-    Vector3::from_iterator(vec![x,y,z].into_iter())
+fn parse_beacon(input: &str) -> Result<Point3, ParseError> {
+    match parse::ints(input)?[..] {
+        [x, y, z] => Ok(Point3::new(x, y, z)),
+        _ => Err(ParseError::new(format!("beacon {:?} does not have three coordinates", input)).at(1, 1)),
+    }
 }
 
 
-fn parse(input: &str) -> Vec<Scanner> {
-    let scans = input.split("\n\n");
+fn parse(input: &str) -> Result<Vec<Scanner>, ParseError> {
     let mut scanners = Vec::new();
 
-    for scan in scans {
-        let mut lines = scan.lines();
-        let heading = lines.next().unwrap();
-        let id = parse_scanner_id(heading);
-        let mut beacons = Vec::new();
-        for beacon in lines {
-            beacons.push(parse_beacon(beacon));
-        }
-        beacons.sort_by(|v1,v2| compare_vector(&v1, &v2));
+    for scan in parse::sections(input) {
+        let (heading, beacons) = scan.text.split_once('\n').unwrap_or((scan.text, ""));
+        let id = scan.parse(|_| parse_scanner_id(heading))?;
+        let mut beacons = scan.parse(|_| parse::lines(beacons, parse_beacon).map_err(|e| e.offset(2)))?;
+        beacons.sort();
         scanners.push(Scanner{id,beacons});
     }
-    scanners
+    if scanners.is_empty() {
+        return Err(ParseError::new("no scanners"));
+    }
+    Ok(scanners)
 }
 
 // --- Test Inputs ---
@@ -577,30 +525,41 @@ fn test_input_simple() -> String {
 mod tests {
 
     use super::*;
-   
-    #[test] 
-    fn dist() {
-        let v1 = Vector3::from_iterator(vec![0,2,-2].into_iter());
-        let v2 = Vector3::from_iterator(vec![0,-2,1].into_iter());
-        assert_eq!(manhattan_distance(&v1, &v2), 7);
-        assert_eq!(euclidean_distance(&v1, &v2), 5.0);
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    fn scanner() -> impl Strategy<Value = Scanner> {
+        (any::<u32>(), prop::collection::vec(any::<[i32; 3]>(), 0..20)).prop_map(|(id, beacons)| {
+            let mut beacons: Vec<Point3> = beacons.into_iter().map(|[x, y, z]| Point3::new(x, y, z)).collect();
+            beacons.sort();
+            Scanner {id, beacons}
+        })
     }
 
-    #[test] 
-    fn matrix_ops() {
-        let mut m1 = Matrix3::identity();
-        multiply_row(&mut m1, 1, -1);
-        println!("{:?}", m1);
-        assert_eq!(det(&m1), -1, "Invalid Determinant");
-        assert_eq!(possible_orientations().len(), 24, "Invalid number of orientations");
+    #[test]
+    fn test_invalid() {
+        assert!(parse("--- scanner 0 ---\n1,2\n").is_err());
+        assert!(parse("").is_err());
     }
 
+    proptest! {
+        #[test]
+        fn test_roundtrip(scanners in prop::collection::vec(scanner(), 1..5)) {
+            prop_assert_eq!(parse(&Lines(&scanners).to_string()), Ok(scanners));
+        }
+
+        #[test]
+        fn test_garbage(input in "(--- scanner [0-9a]{0,2} ---\n(-?[0-9]{0,3},?-?[0-9]{0,3},?[0-9]{0,3}\n){0,3}\n){0,3}") {
+            let _ = parse(&input);
+        }
+    }
+   
     #[test] 
     fn utilities() {
-        let v1 = Vector3::from_iterator(vec![0,2,-2].into_iter());
-        let v2 = Vector3::from_iterator(vec![0,2,-2].into_iter());
-        let v3 = Vector3::from_iterator(vec![-1,2,-2].into_iter());
-        let vec1 = vec![v3.clone(), v2.clone()];
+        let v1 = Point3::new(0,2,-2);
+        let v2 = Point3::new(0,2,-2);
+        let v3 = Point3::new(-1,2,-2);
+        let vec1 = vec![v3, v2];
         let vec2 = vec![v1];
         assert_eq!(equal_vector_count(&vec1, &vec2), 1);
     }
@@ -1,4 +1,9 @@
-use crate::AOCDay;
+use crate::{AOCDay, StreamingDay};
+use crate::utils::{parse, ParseError};
+
+use std::io::BufRead;
+
+use parsing::Command;
 
 /*
  * Template for a implementing a day
@@ -7,107 +12,302 @@ use crate::AOCDay;
 pub struct Day2();
 
 impl AOCDay for Day2 {
-    fn part1(&self, input: &str) -> Option<String> { Some(calculate_position(input)) }
-    fn part2(&self, input: &str) -> Option<String> { Some(calculate_position2(input)) }
+    fn part1(&self, input: &str) -> Result<String, ParseError> { calculate_position(input) }
+    fn part2(&self, input: &str) -> Result<String, ParseError> { calculate_position2(input) }
     fn get_num(&self) -> u32 { 2 }
 }
 
+impl StreamingDay for Day2 {
+    fn part1_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> { stream_position(input, &Plain) }
+    fn part2_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> { stream_position(input, &Aimed) }
+}
+
 pub fn get() -> Day2 {Day2()}
 
-fn calculate_position(input: &str) -> String {
-    //TODO: PARSE INPUT
-    let dirs = parsing::parse(input);
-    let mut x = 0;
-    let mut d = 0;
-    for dir in dirs {
-        match dir {
-            parsing::Dir::Up(n) => {d -= n;},
-            parsing::Dir::Down(n) => {d += n;},
-            parsing::Dir::Forward(n) => {x += n},
+/// Horizontal position, depth and aim of the submarine
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Submarine {
+    pub x: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+impl Submarine {
+    /// Horizontal position times depth, the answer of both parts
+    fn product(&self) -> Result<i64, ParseError> {
+        checked(self.x.checked_mul(self.depth))
+    }
+}
+
+/// The value of a checked operation, an error if it overflowed
+fn checked(value: Option<i64>) -> Result<i64, ParseError> {
+    value.ok_or_else(|| ParseError::new("the submarine leaves the range of 64 bit integers"))
+}
+
+/// Rules by which the commands move the submarine, failing if a value overflows
+pub trait Model {
+    fn apply(&self, sub: &mut Submarine, command: &Command) -> Result<(), ParseError>;
+}
+
+/// Up and down change the depth directly (part 1), the aim is kept but does not steer.
+pub struct Plain;
+
+/// Up and down change the aim, moving changes the depth by the aim per unit (part 2)
+pub struct Aimed;
+
+impl Model for Plain {
+    fn apply(&self, sub: &mut Submarine, command: &Command) -> Result<(), ParseError> {
+        match *command {
+            Command::Forward(n) => sub.x = checked(sub.x.checked_add(n as i64))?,
+            Command::Back(n) => sub.x = checked(sub.x.checked_sub(n as i64))?,
+            Command::Down(n) => sub.depth = checked(sub.depth.checked_add(n as i64))?,
+            Command::Up(n) => sub.depth = checked(sub.depth.checked_sub(n as i64))?,
+            Command::Aim(aim) => sub.aim = aim,
+        }
+        Ok(())
+    }
+}
+
+impl Model for Aimed {
+    fn apply(&self, sub: &mut Submarine, command: &Command) -> Result<(), ParseError> {
+        match *command {
+            Command::Forward(n) => {
+                let dive = checked((n as i64).checked_mul(sub.aim))?;
+                sub.x = checked(sub.x.checked_add(n as i64))?;
+                sub.depth = checked(sub.depth.checked_add(dive))?;
+            }
+            Command::Back(n) => {
+                let dive = checked((n as i64).checked_mul(sub.aim))?;
+                sub.x = checked(sub.x.checked_sub(n as i64))?;
+                sub.depth = checked(sub.depth.checked_sub(dive))?;
+            }
+            Command::Down(n) => sub.aim = checked(sub.aim.checked_add(n as i64))?,
+            Command::Up(n) => sub.aim = checked(sub.aim.checked_sub(n as i64))?,
+            Command::Aim(aim) => sub.aim = aim,
         }
+        Ok(())
+    }
+}
+
+/// The rules of part 2 if `aimed` is set, those of part 1 otherwise
+pub fn model(aimed: bool) -> &'static dyn Model {
+    if aimed { &Aimed } else { &Plain }
+}
+
+/// Applies the command of a line, if it has one, and tells whether it had one
+fn follow(sub: &mut Submarine, line: &str, model: &dyn Model) -> Result<bool, ParseError> {
+    match parsing::parse_command(line)? {
+        Some(command) => model.apply(sub, &command).map(|_| true),
+        None => Ok(false),
     }
-    String::from(format!("{}", x * d))
 }
 
-fn calculate_position2(input: &str) -> String {
-    let dirs = parsing::parse(input);
-    let mut x = 0;
-    let mut d = 0;
-    let mut aim = 0;
-    for dir in dirs {
-        match dir {
-            parsing::Dir::Up(n) => {aim -= n;},
-            parsing::Dir::Down(n) => {aim += n;},
-            parsing::Dir::Forward(n) => {x += n; d += n * aim},
+fn navigate(input: &str, model: &dyn Model) -> Result<String, ParseError> {
+    let mut sub = Submarine::default();
+    parse::lines(input, |line| follow(&mut sub, line, model))?;
+    Ok(format!("{}", sub.product()?))
+}
+
+fn calculate_position(input: &str) -> Result<String, ParseError> {
+    navigate(input, &Plain)
+}
+
+fn calculate_position2(input: &str) -> Result<String, ParseError> {
+    navigate(input, &Aimed)
+}
+
+fn stream_position(input: &mut dyn BufRead, model: &dyn Model) -> Result<String, ParseError> {
+    let mut sub = Submarine::default();
+    parse::stream_lines(input, |line| follow(&mut sub, line, model).map(|_| ()))?;
+    Ok(format!("{}", sub.product()?))
+}
+
+/// The submarine at the start and after every command, errors point at the line of the command
+pub fn trajectory(input: &str, model: &dyn Model) -> Result<Vec<Submarine>, ParseError> {
+    let mut sub = Submarine::default();
+    let mut steps = vec![sub];
+    parse::lines(input, |line| {
+        if follow(&mut sub, line, model)? {
+            steps.push(sub);
         }
+        Ok(())
+    })?;
+    Ok(steps)
+}
+
+/// The trajectory as CSV with one row per step, step 0 being the start
+pub fn trajectory_csv(input: &str, aimed: bool) -> Result<String, ParseError> {
+    let steps = trajectory(input, model(aimed))?;
+    let commands = parsing::parse(input)?;
+    let mut csv = String::from("step,command,x,depth,aim\n");
+    for (i, sub) in steps.iter().enumerate() {
+        let command = if i == 0 { String::new() } else { commands[i - 1].to_string() };
+        csv.push_str(&format!("{},{},{},{},{}\n", i, command, sub.x, sub.depth, sub.aim));
     }
-    String::from(format!("{}", x * d))
+    Ok(csv)
 }
 
-mod parsing {
+pub mod parsing {
+    use std::fmt;
+
     use nom::bytes::complete::tag;
     use nom::{IResult};
-    use nom::combinator::{recognize};
+    use nom::combinator::{map};
     use nom::branch::{alt};
-    use nom::sequence::{separated_pair};
-    use nom::error::Error;
+    use nom::sequence::{preceded};
     use nom::character;
 
-    #[derive(Debug)]
-    pub enum Dir {
+    use crate::utils::{parse, ParseError};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Command {
         Up(u32),
         Down(u32),
-        Forward(u32)
-    }
-
-    pub fn parse(input: &str) -> Vec<Dir> {
-        let lines = input.lines();
-        let mut v = Vec::new();
-        for line in lines {
-            let l = parse_line(line);
-            if l.is_ok() {
-                v.push(l.unwrap().1);
-            }else {
-                println!("{}", l.unwrap_err());
-                panic!("PARSE ERROR");
+        Forward(u32),
+        Back(u32),
+        Aim(i64), // sets the aim
+    }
+
+    impl fmt::Display for Command {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Command::Up(n) => write!(f, "up {}", n),
+                Command::Down(n) => write!(f, "down {}", n),
+                Command::Forward(n) => write!(f, "forward {}", n),
+                Command::Back(n) => write!(f, "back {}", n),
+                Command::Aim(n) => write!(f, "aim {}", n),
             }
         }
-        v
     }
 
-    fn parse_line(input: &str) -> IResult<&str, Dir> {
-        let space = character::complete::char::<_, (&str, _)>(' ');
-        let foward = recognize(tag("forward"));
-        let down = recognize(tag("down"));
-        let up = recognize(tag("up"));
-        let dir = alt((foward, up, down));
-        let number = character::complete::u32;
+    /// The commands of all lines, lines may end in a `#` comment and lines without a command are skipped.
+    pub fn parse(input: &str) -> Result<Vec<Command>, ParseError> {
+        let commands = parse::lines(input, parse_command)?;
+        Ok(commands.into_iter().flatten().collect())
+    }
 
-        let mut parser= separated_pair(dir, space, number);
-        let res = parser(input);
-        let result = res.map_err(|e| {// we have to convert to the default error type
-            e.map(|err| {
-                Error {input: err.0, code: err.1}
-            })
-        });
-        result.map(|(rem, (d, num))| {
-            match d {
-                "forward" => (rem, Dir::Forward(num)),
-                "down" => (rem, Dir::Down(num)),
-                "up" => (rem, Dir::Up(num)),
-                _ => panic!("PARSE ERROR"),
-            }
-        })
+    /// The command of a line, if it has one
+    pub fn parse_command(line: &str) -> Result<Option<Command>, ParseError> {
+        let code = line.split('#').next().unwrap_or_default().trim_end();
+        if code.is_empty() {
+            return Ok(None);
+        }
+        parse::complete(code, parse_line).map(Some)
+    }
+
+    fn parse_line(input: &str) -> IResult<&str, Command> {
+        let number = character::complete::u32;
+        alt((
+            map(preceded(tag("forward "), number), Command::Forward),
+            map(preceded(tag("down "), number), Command::Down),
+            map(preceded(tag("up "), number), Command::Up),
+            map(preceded(tag("back "), number), Command::Back),
+            map(preceded(tag("aim "), character::complete::i64), Command::Aim),
+        ))(input)
     }
 
     #[cfg(test)]
     pub mod test {
         use super::*;
+        use crate::utils::Lines;
+        use proptest::prelude::*;
 
         #[test]
         fn test_parser() {
             let res = parse_line("forward 8");
-            println!("{:?}", res);
+            assert_eq!(res, Ok(("", Command::Forward(8))));
+            assert_eq!(parse_line("aim -3"), Ok(("", Command::Aim(-3))));
+        }
+
+        #[test]
+        fn test_comments() {
+            let input = "# course\nforward 5 # full speed\n\nback 2\n#\naim 7";
+            assert_eq!(parse(input), Ok(vec![Command::Forward(5), Command::Back(2), Command::Aim(7)]));
+
+            let e = parse("forward 5\n# ok\nsideways 3 # no\n").unwrap_err();
+            assert_eq!((e.line(), e.col()), (Some(3), Some(1)));
+            let e = parse("back 1\nup -4\n").unwrap_err();
+            assert_eq!((e.line(), e.col()), (Some(2), Some(1)));
+        }
+
+        fn command() -> impl Strategy<Value = Command> {
+            prop_oneof![
+                any::<u32>().prop_map(Command::Up),
+                any::<u32>().prop_map(Command::Down),
+                any::<u32>().prop_map(Command::Forward),
+                any::<u32>().prop_map(Command::Back),
+                any::<i64>().prop_map(Command::Aim),
+            ]
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert!(parse("forward x\n").is_err());
+            assert!(parse("sideways 3\n").is_err());
+        }
+
+        proptest! {
+            #[test]
+            fn test_roundtrip(commands in prop::collection::vec(command(), 0..50)) {
+                prop_assert_eq!(parse(&Lines(&commands).to_string()), Ok(commands));
+            }
+
+            #[test]
+            fn test_garbage(input in "((forward|down|up|back|aim)? ?[0-9a-z# -]{0,12}\n){0,5}") {
+                let _ = parse(&input);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        let input = "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2\n";
+        assert_eq!(calculate_position(input), Ok(String::from("150")));
+        assert_eq!(calculate_position2(input), Ok(String::from("900")));
+    }
+
+    #[test]
+    fn test_models() {
+        let input = "down 3\nforward 4\nback 1\naim -2\nforward 5\n";
+        let plain = trajectory(input, &Plain).unwrap();
+        assert_eq!(plain.last(), Some(&Submarine {x: 8, depth: 3, aim: -2}));
+        let aimed = trajectory(input, &Aimed).unwrap();
+        assert_eq!(aimed.len(), 6);
+        assert_eq!(aimed[3], Submarine {x: 3, depth: 9, aim: 3});
+        assert_eq!(aimed[5], Submarine {x: 8, depth: -1, aim: -2});
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = trajectory_csv("forward 5\ndown 5 # dive\nforward 8\n", true).unwrap();
+        assert_eq!(csv, "step,command,x,depth,aim\n0,,0,0,0\n1,forward 5,5,0,0\n2,down 5,5,0,5\n3,forward 8,13,40,5\n");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = "down 1\naim 9223372036854775807\nforward 2\n";
+        let e = calculate_position2(input).unwrap_err();
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(trajectory(input, &Aimed).unwrap_err().line(), Some(3));
+        assert_eq!(get().part2_stream(&mut input.as_bytes()).unwrap_err().line(), Some(3));
+        assert_eq!(calculate_position(input), Ok(String::from("2")));
+
+        let input = "forward 4294967295\ndown 4294967295\n";
+        assert_eq!(calculate_position(input).unwrap_err().line(), None);
+        assert_eq!(calculate_position("forward 3037000499\ndown 3037000499\n"), Ok(String::from("9223372030926249001")));
+    }
+
+    proptest! {
+        #[test]
+        fn test_stream(input in "((forward|down|up|back) [0-9]{1,3}( #.*)?\n){0,20}(up 1x\n)?") {
+            prop_assert_eq!(get().part1_stream(&mut input.as_bytes()), calculate_position(&input));
+            prop_assert_eq!(get().part2_stream(&mut input.as_bytes()), calculate_position2(&input));
         }
     }
 }
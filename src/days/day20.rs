@@ -1,6 +1,10 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::bits::BitBoard;
+use crate::utils::grid::Grid;
+use crate::visualize::{Colour, Frame, Visualize};
 
-use std::mem;
+use std::fmt;
 
 /*
  * Day 20: Trench Map
@@ -11,129 +15,121 @@ use std::mem;
 pub struct Day20();
 
 impl AOCDay for Day20 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 20 }
 }
 
+impl Visualize for Day20 {
+    /// The image after every one of the 50 enhancement steps
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError> {
+        let TrenchMap {mut img, alg} = parse(input)?;
+        let mut inf_tile = false;
+        let mut step = 0;
+        Ok(Box::new(std::iter::from_fn(move || {
+            if step > 50 { return None; }
+            if step > 0 {
+                (img, inf_tile) = enhance(&img, &alg, inf_tile);
+            }
+            step += 1;
+            Some(img_to_frame(&img))
+        })))
+    }
+}
+
 pub fn get() -> Day20 {Day20()}
 
-type Image = Vec<Vec<bool>>;
+type Image = BitBoard;
 type EAlg = Vec<bool>;
 
-fn part1(input: &str) -> String {
-    let (mut img, alg) = parse(&input);
-    iterate(&mut img, &alg, 2);
-    let lit = img.into_iter().flatten().filter(|c| *c == true);
-    format!("{}", lit.count())
+/// The image enhancement algorithm and the input image
+#[derive(Debug, PartialEq, Eq)]
+struct TrenchMap {
+    alg: EAlg,
+    img: Image,
 }
 
-fn part2(input: &str) -> String {
-    let (mut img, alg) = parse(&input);
-    iterate(&mut img, &alg, 50);
-    let lit = img.into_iter().flatten().filter(|c| *c == true);
-    format!("{}", lit.count())
+impl fmt::Display for TrenchMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alg: String = self.alg.iter().map(|p| if *p {'#'} else {'.'}).collect();
+        write!(f, "{}\n\n{}", alg, img_to_string(&self.img))
+    }
 }
 
-fn iterate(img: &mut Image, alg: &EAlg, steps: u32) {
-    let mut inf_tile = false; // models tile color at infinity
-    add_padding(img, inf_tile);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let TrenchMap {img, alg} = parse(input)?;
+    let img = iterate(img, &alg, 2);
+    Ok(format!("{}", img.count_ones()))
+}
+
+fn part2(input: &str) -> Result<String, ParseError> {
+    let TrenchMap {img, alg} = parse(input)?;
+    let img = iterate(img, &alg, 50);
+    Ok(format!("{}", img.count_ones()))
+}
 
-    let mut buffer1 = img.clone();
-    let mut buffer2 = img.clone();
+fn iterate(mut img: Image, alg: &EAlg, steps: u32) -> Image {
+    let mut inf_tile = false; // models tile color at infinity
     for _ in 0..steps {
-        add_padding(&mut buffer1, inf_tile); // increase both buffers
-        add_padding(&mut buffer2, inf_tile); 
-        for iy in 1..buffer2.len()-1 {
-            for ix in 1..buffer2.len()-1 {
-                let w = get_window(ix,iy,&mut buffer1);
-                buffer2[iy][ix] = alg[w as usize]; 
-            }
-        }
-        // update infinity tile
-        let w = if inf_tile {0b0111111111} else {0x01};
-        inf_tile = alg[w];
-        update_border(&mut buffer2, inf_tile);
-        // swap buffers
-        mem::swap(&mut buffer1, &mut buffer2);     
+        (img, inf_tile) = enhance(&img, alg, inf_tile);
     }
-    mem::swap(img, &mut buffer1);
+    img
 }
 
-fn get_window(x: usize, y: usize, img: &Image) -> u32 {
-    let mut val = 0;
-    for iy in y-1..y+2 {
-        for ix in x-1..x+2 {
-            if img[iy][ix] {
-                val = (val << 1) | 0x01;
-            }else {
-                val = val << 1;
+/// Applies the enhancement algorithm once, the image grows by one pixel on every side.
+/// Returns the enhanced image and the new tile color at infinity.
+fn enhance(img: &Image, alg: &EAlg, inf_tile: bool) -> (Image, bool) {
+    let mut enhanced = BitBoard::new(img.width()+2, img.height()+2);
+    for y in 0..enhanced.height() {
+        // the enhanced pixel (x, y) is centered at (x-1, y-1) of the original image
+        for (x, window) in img.row_windows3(y as isize - 1, inf_tile).enumerate() {
+            if alg[window] {
+                enhanced.set((x, y), true);
             }
         }
     }
-    val
+    // update infinity tile
+    let w = if inf_tile {0b111111111} else {0};
+    (enhanced, alg[w])
 }
 
-fn add_padding(img: &mut Image, dark: bool) {
-    for row in img.iter_mut() {
-        row.insert(0, dark);
-        row.insert(0, dark);
-        row.append(&mut vec![dark, dark]);
-    }
-    let n = vec![dark; img.len()+4];
-    img.insert(0, n.clone());
-    img.insert(0, n.clone());
-    img.push(n.clone());
-    img.push(n)
+fn img_to_string(img: &Image) -> String {
+    Grid::from_fn(img.width(), img.height(), |c| if img.get(c) {'#'} else {'.'}).to_string()
 }
 
-fn update_border(img: &mut Image, dark: bool) {
-    let z = img.len();
-    for i in 0..z {
-        img[i][0] = dark;
-        img[i][z-1] = dark;
-        img[0][i] = dark;
-        img[z-1][i] = dark;
+const PALETTE: [Colour; 2] = [(0, 0, 0), (255, 255, 255)];
+
+fn img_to_frame(img: &Image) -> Frame {
+    let mut frame = Frame::new(img.width(), img.height(), &PALETTE);
+    for (x, y) in img.ones() {
+        frame.set(x, y, 1);
     }
+    frame
 }
 
-fn img_to_string(img: &Image) -> String {
-    let mut s = String::with_capacity(img.len() * (img.len()+1));
-    let z = img.len();
-    for y in 0..z {
-        for x in 0..z {
-            if img[y][x] {s.push('#')} else {s.push('.')}
-        }
-        s.push('\n');
+fn parse_pixel(c: char) -> Result<bool, ParseError> {
+    match c {
+        '.' => Ok(false),
+        '#' => Ok(true),
+        _ => Err(ParseError::new(format!("invalid pixel {:?}", c))),
     }
-    s
 }
 
-fn parse(input: &str) -> (Image, EAlg) {
-    let mut parts = input.split("\n\n");
-    let alg_s = parts.next().unwrap();
-    let mut enhancement_alg = Vec::new();
-    for c in alg_s.chars() {
-        match c{
-            '.' => {enhancement_alg.push(false)},
-            '#' => {enhancement_alg.push(true)},
-            _ => {},
-        }
+fn parse(input: &str) -> Result<TrenchMap, ParseError> {
+    let mut parts = parse::sections(input).into_iter();
+    let (alg_s, img_s) = match (parts.next(), parts.next()) {
+        (Some(alg_s), Some(img_s)) => (alg_s, img_s),
+        _ => return Err(ParseError::new("missing input image")),
+    };
+    // the algorithm may be wrapped over several lines
+    let alg = alg_s.parse(|text| parse::lines(text, |line| {
+        line.char_indices().map(|(i, c)| parse_pixel(c).map_err(|e| e.locate(line, &line[i..]))).collect::<Result<EAlg, _>>()
+    }))?.concat();
+    if alg.len() != 512 {
+        return Err(ParseError::new(format!("enhancement algorithm has {} instead of 512 pixels", alg.len())).offset(alg_s.line));
     }
-    let img_s = parts.next().unwrap();
-    let mut img = Vec::new();
-    for line in img_s.lines() {
-        let mut r = Vec::new();
-        for c in line.trim_end().chars() {
-            match c{
-                '.' => {r.push(false)},
-                '#' => {r.push(true)},
-                _ => {},
-            }
-        }
-        img.push(r);
-    }
-    (img, enhancement_alg)
+    let img = Image::from(&img_s.parse(|text| parse::grid(text, parse_pixel))?);
+    Ok(TrenchMap {alg, img})
 }
 
 fn test_input() -> String {
@@ -151,3 +147,42 @@ fn test_input() -> String {
 ..#..
 ..###")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn trench_map() -> impl Strategy<Value = TrenchMap> {
+        let img = (1..20usize).prop_flat_map(|width| prop::collection::vec(prop::collection::vec(any::<bool>(), width), 1..20))
+            .prop_map(|rows| Image::from(&Grid::from_rows(rows).unwrap()));
+        (prop::collection::vec(any::<bool>(), 512), img).prop_map(|(alg, img)| TrenchMap {alg, img})
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("35")));
+        assert_eq!(part2(&test_input()), Ok(String::from("3351")));
+        // images do not have to be square
+        let wide = test_input().replace("#..#.\n#....\n##..#\n..#..\n..###", "#..#.\n#....");
+        assert!(part1(&wide).is_ok());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("#.#\n\n#.\n").is_err());
+        assert!(parse(&format!("{}\n\n#x\n", "#".repeat(512))).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(map in trench_map()) {
+            prop_assert_eq!(parse(&map.to_string()), Ok(map));
+        }
+
+        #[test]
+        fn test_garbage(input in "[.#]{0,520}\\n\\n([.#a]{0,5}\\n){0,4}") {
+            let _ = parse(&input);
+        }
+    }
+}
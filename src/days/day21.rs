@@ -1,7 +1,9 @@
 use crate::AOCDay;
+use crate::utils::ParseError;
+use crate::utils::bigint::Natural;
+use crate::utils::memo::Counter;
 
-use std::collections::HashMap;
-use std::hash::{Hash};
+use std::fmt;
 
 use itertools::Itertools;
 
@@ -14,8 +16,8 @@ use itertools::Itertools;
 pub struct Day21();
 
 impl AOCDay for Day21 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 21 }
 }
 
@@ -27,10 +29,15 @@ struct DetDice(u32, u32); //prev, rolls
 #[derive(Debug, Hash, Clone, Eq, PartialEq)]
 struct Player(u32, u32); //pos, score
 
-#[derive(Debug, Hash, Clone, Eq, PartialEq)]
-struct Universe {
-    player1: Player,
-    player2: Player
+/// Starting positions of both players
+#[derive(Debug, PartialEq, Eq)]
+struct StartingPositions(u32, u32);
+
+impl fmt::Display for StartingPositions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Player 1 starting position: {}", self.0)?;
+        writeln!(f, "Player 2 starting position: {}", self.1)
+    }
 }
 
 impl DetDice {
@@ -49,8 +56,9 @@ impl Iterator for DetDice {
     }
 }
 
-fn part1(input: &str) -> String {
-    let (mut p1, mut p2) = parse(&input);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let StartingPositions(p1, p2) = parse(input)?;
+    let (mut p1, mut p2) = (Player(p1, 0), Player(p2, 0));
     let mut dice = DetDice::new();
     let mut player_one_turn = true;
 
@@ -70,59 +78,74 @@ fn part1(input: &str) -> String {
     }else {
         loser = p1;
     }
-    format!("{}", dice.1 * loser.1)
+    Ok(format!("{}", dice.1 * loser.1))
 }
 
-fn part2(input: &str) -> String {
-    let (p1, p2) = parse(&input);
-
-    let mut ended_games = HashMap::new();
-    let mut universes = HashMap::new();
-    let universe = Universe{player1: p1, player2: p2};
-    universes.insert(universe, 1);
+fn part2(input: &str) -> Result<String, ParseError> {
+    let wins: u64 = dirac(input, 21)?;
+    Ok(format!("{}", wins))
+}
 
-    let mut p1_turn = true;
-    while universes.len() > 0 {
-        universes = play_turn_quantum(&universes, p1_turn, &mut ended_games);
-        p1_turn = !p1_turn;
-    }
-    format!("{}", ended_games.values().max().unwrap())
+/// Number of universes in which the player winning most often wins, playing up to `score` points
+pub fn dirac<N: Natural>(input: &str, score: u32) -> Result<N, ParseError> {
+    let StartingPositions(p1, p2) = parse(input)?;
+    let (wins1, wins2): (N, N) = quantum_wins(Player(p1, 0), Player(p2, 0), score);
+    Ok(wins1.max(wins2))
 }
 
-/// Plays a non-deterministic turn with Dirac Dice, keeps track of the number of universes, which
-/// are the same.
-fn play_turn_quantum(universes: &HashMap<Universe, u64>, p1_turn: bool, ended_games: &mut HashMap<bool, u64>) -> HashMap<Universe, u64> {
-    let mut new_universes = HashMap::new();
-    for (universe, count) in universes.into_iter() {
-        // Every universe branches into 27 new universes.
-        let rolls: Vec<u32> = dirac_throw().into_iter()
-            .cartesian_product(dirac_throw())
-            .cartesian_product(dirac_throw())
-            .map(|((t1,t2),t3)| t1+t2+t3).collect();
-
-        for roll in rolls {
-            let mut new_universe = universe.clone();
-            let player = if p1_turn {&mut new_universe.player1} else {&mut new_universe.player2};
-            play_turn_det(player, roll);
-            if player.1 >= 21 {
-                insert_or_increment(ended_games, p1_turn, *count);
-            }else {
-                insert_or_increment(&mut new_universes, new_universe, *count);
-            }
+/// Number of universes in which either player reaches `score` first with Dirac Dice,
+/// the first player moves next.
+///
+/// The players only interact by ending the game, so every player is played on its own: the first
+/// player wins at its turn `t` in all universes where it reaches the score at turn `t`, times those
+/// where the second player did not within `t - 1` turns.
+fn quantum_wins<N: Natural>(p1: Player, p2: Player, score: u32) -> (N, N) {
+    let (turns1, turns2) = (solo_turns::<N>(p1, score), solo_turns::<N>(p2, score));
+    let playing = |turns: &[(N, N)], t: usize| turns.get(t).map_or(N::zero(), |(_, playing)| playing.clone());
+    let (mut wins1, mut wins2) = (N::zero(), N::zero());
+    for t in 1..turns1.len().max(turns2.len()) {
+        if let Some((won, _)) = turns1.get(t) {
+            wins1 += won.clone() * playing(&turns2, t - 1);
+        }
+        if let Some((won, _)) = turns2.get(t) {
+            wins2 += won.clone() * playing(&turns1, t);
         }
     }
-    new_universes
+    (wins1, wins2)
 }
 
-/// Insert `val` into the hashmap, or increment existing entry with `val`
-fn insert_or_increment<K: Eq + Hash>(hashmap: &mut HashMap<K, u64>, key: K, val: u64) {
-    let n = hashmap.get_mut(&key);
-    if n.is_some() {
-        let n = n.unwrap();
-        *n = *n + val;
-    }else {
-        hashmap.insert(key, val);
+/// Universes of a single player after every turn, starting at turn 0: those in which it reaches
+/// `score` at that turn and those in which it is still below. Played forward turn by turn, as memoized
+/// recursion over both players recursed once per turn and overflowed the stack for the high scores
+/// of the `dirac` variant.
+fn solo_turns<N: Natural>(player: Player, score: u32) -> Vec<(N, N)> {
+    // the sums of three throws, every universe branches into 27 new universes
+    let rolls: Counter<u32, N> = dirac_throw().into_iter()
+        .cartesian_product(dirac_throw())
+        .cartesian_product(dirac_throw())
+        .map(|((t1,t2),t3)| t1+t2+t3).collect();
+
+    // universes per position and score below `score`, scores only increase within a turn
+    let index = |Player(pos, points): &Player| (points * 10 + pos - 1) as usize;
+    let states = 10 * score.max(1) as usize;
+    let mut playing = vec![N::zero(); states];
+    playing[index(&player)] = N::one();
+    let mut turns = vec![(N::zero(), N::one())];
+    while playing.iter().any(|n| *n != N::zero()) {
+        let (mut won, mut next) = (N::zero(), vec![N::zero(); states]);
+        for (i, count) in playing.iter().enumerate().filter(|(_, n)| **n != N::zero()) {
+            for (roll, n) in rolls.iter() {
+                let mut player = Player(i as u32 % 10 + 1, i as u32 / 10);
+                play_turn_det(&mut player, *roll);
+                let universes = count.clone() * n.clone();
+                if player.1 >= score { won += universes } else { next[index(&player)] += universes }
+            }
+        }
+        let total = next.iter().fold(N::zero(), |total, n| total + n.clone());
+        turns.push((won, total));
+        playing = next;
     }
+    turns
 }
 
 /// The possible outcomes of the Dirac Die
@@ -140,13 +163,22 @@ fn play_turn_det(player: &mut Player, roll: u32) {
 }
 
 // --- PARSING ---
-fn parse(input: &str) -> (Player, Player) {
+fn parse_position(line: Option<&str>, player: u32) -> Result<u32, ParseError> {
+    let prefix = format!("Player {} starting position: ", player);
+    let pos: u32 = line.and_then(|l| l.trim_end().strip_prefix(&prefix))
+        .ok_or_else(|| ParseError::new(format!("missing starting position of player {}", player)))?
+        .parse()?;
+    if !(1..=10).contains(&pos) {
+        return Err(ParseError::new(format!("starting position {} is not on the board", pos)));
+    }
+    Ok(pos)
+}
+
+fn parse(input: &str) -> Result<StartingPositions, ParseError> {
     let mut lines = input.lines();
-    let l1 = lines.next().unwrap();
-    let l2 = lines.next().unwrap();
-    let p1 = u32::from_str_radix(&l1.trim_end().chars().rev().next().unwrap().to_string(), 10).unwrap();
-    let p2 = u32::from_str_radix(&l2.trim_end().chars().rev().next().unwrap().to_string(), 10).unwrap();
-    (Player(p1, 0), Player(p2, 0))
+    let p1 = parse_position(lines.next(), 1)?;
+    let p2 = parse_position(lines.next(), 2)?;
+    Ok(StartingPositions(p1, p2))
 }
 
 // --- TEST INPUTS ---
@@ -154,3 +186,81 @@ fn test_input() -> String {
     String::from("Player 1 starting position: 4
 Player 2 starting position: 8")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bigint::BigUint;
+    use crate::utils::memo::memoize;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("739785")));
+        assert_eq!(part2(&test_input()), Ok(String::from("444356092776315")));
+        let wins: BigUint = dirac(&test_input(), 21).unwrap();
+        assert_eq!(wins.to_string(), "444356092776315");
+        assert!(dirac::<BigUint>(&test_input(), 40).unwrap() > BigUint::from(u64::MAX));
+    }
+
+    /// State of a game, `player1` moves next
+    #[derive(Debug, Hash, Clone, Eq, PartialEq)]
+    struct Universe {
+        player1: Player,
+        player2: Player
+    }
+
+    /// Plays every universe recursively, caching the outcome of every state
+    fn reference(p1: Player, p2: Player, score: u32) -> (u64, u64) {
+        memoize(Universe {player1: p1, player2: p2}, |rec, universe| {
+            let (mut wins, mut losses) = (0, 0);
+            for ((t1, t2), t3) in dirac_throw().into_iter().cartesian_product(dirac_throw()).cartesian_product(dirac_throw()) {
+                let mut player = universe.player1.clone();
+                play_turn_det(&mut player, t1 + t2 + t3);
+                if player.1 >= score {
+                    wins += 1;
+                } else {
+                    let (w, l) = rec.get(Universe {player1: universe.player2.clone(), player2: player});
+                    wins += l;
+                    losses += w;
+                }
+            }
+            (wins, losses)
+        })
+    }
+
+    #[test]
+    fn test_reference() {
+        for (p1, p2) in (1..=10).cartesian_product(1..=10) {
+            for score in [0, 1, 12] {
+                assert_eq!(quantum_wins(Player(p1, 0), Player(p2, 0), score), reference(Player(p1, 0), Player(p2, 0), score));
+            }
+        }
+    }
+
+    #[test]
+    fn test_high_score() {
+        // far beyond the reach of recursion, the number of universes has hundreds of digits
+        let wins: BigUint = dirac(&test_input(), 300).unwrap();
+        assert_eq!(wins.to_string().len(), 203);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("Player 1 starting position: 11\nPlayer 2 starting position: 8\n").is_err());
+        assert!(parse("Player 1 starting position: 4\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(p1 in 1..=10u32, p2 in 1..=10u32) {
+            let start = StartingPositions(p1, p2);
+            prop_assert_eq!(parse(&start.to_string()), Ok(start));
+        }
+
+        #[test]
+        fn test_garbage(input in "(Player [0-3] starting position: -?[0-9a]{0,3}\n){0,2}") {
+            let _ = parse(&input);
+        }
+    }
+}
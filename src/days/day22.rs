@@ -1,14 +1,9 @@
 use crate::AOCDay;
+use crate::stats;
+use crate::utils::{parse, ParseError};
+use crate::utils::geom::{Cuboid, Point3};
 
-use nom::{
-    IResult,
-    Parser,
-    bytes::complete::{tag, take_till},
-    branch::alt,
-    sequence::{separated_pair, preceded, tuple},
-    character::{complete, is_digit},
-
-};
+use std::fmt;
 
 /*
  * Day 22: Reactor Reboot
@@ -19,55 +14,30 @@ use nom::{
 pub struct Day22();
 
 impl AOCDay for Day22 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 22 }
 }
 
 pub fn get() -> Day22 {Day22()}
 
-#[derive(Debug)]
-struct Cuboid{
-    xlim: (i32, i32), // (min, max)
-    ylim: (i32, i32),
-    zlim: (i32, i32),
-}
-
-impl Cuboid {
-    /// Volume of cuboid in number of cubes
-    fn volume(&self) -> u64 {
-        let zlim = self.zlim;
-        let ylim = self.ylim;
-        let xlim = self.xlim;
-        let z_width = (zlim.1 - zlim.0 + 1) as i64;
-        let y_width = (ylim.1 - ylim.0 + 1) as i64;
-        let x_width = (xlim.1 - xlim.0 + 1) as i64;
-        (z_width * y_width * x_width) as u64
-    }
+#[derive(Debug, PartialEq, Eq)]
+struct Instr(Cuboid, bool); // (cuboid, turn off/on)
 
-    /// Intersection between two cuboids
-    fn intersection(&self, other: &Self) -> Option<Self> {
-        if self.zlim.0 > other.zlim.1 || other.zlim.0 > self.zlim.1 {return None}; // no intersection
-        if self.ylim.0 > other.ylim.1 || other.ylim.0 > self.ylim.1 {return None};
-        if self.xlim.0 > other.xlim.1 || other.xlim.0 > self.xlim.1 {return None};
-        // Cube must intersect
-        let zlim_int = (i32::max(self.zlim.0, other.zlim.0), i32::min(self.zlim.1, other.zlim.1));
-        let ylim_int = (i32::max(self.ylim.0, other.ylim.0), i32::min(self.ylim.1, other.ylim.1));
-        let xlim_int = (i32::max(self.xlim.0, other.xlim.0), i32::min(self.xlim.1, other.xlim.1));
-        Some(Cuboid {
-            xlim: xlim_int,
-            ylim: ylim_int,
-            zlim: zlim_int,
-        })
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Instr(c, on) = self;
+        write!(f, "{} x={}..{},y={}..{},z={}..{}", if *on {"on"} else {"off"},
+            c.min.x, c.max.x, c.min.y, c.max.y, c.min.z, c.max.z)
     }
 }
 
-#[derive(Debug)]
-struct Instr(Cuboid, bool); // (cuboid, turn off/on)
+/// Region of the reactor which is rebooted in part 1
+const INIT_REGION: Cuboid = Cuboid {min: Point3::new(-50, -50, -50), max: Point3::new(50, 50, 50)};
 
 /// Naive solution, quite ugly
-fn part1(input: &str) -> String {
-    let instructions = parse(&input);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let instructions = parse(input)?;
     let mut space = Vec::new();
     for _ in -50..51 {
         let mut grid = Vec::new();
@@ -80,14 +50,10 @@ fn part1(input: &str) -> String {
     // follow instructions
     for instr in instructions {
         let (cuboid, on) = (instr.0, instr.1);
-        let within_limit = within_limit(cuboid.zlim.0, -50, 50) && within_limit(cuboid.zlim.1, -50, 50) && within_limit(cuboid.ylim.0, -50, 50) && within_limit(cuboid.ylim.1, -50, 50) && within_limit(cuboid.xlim.0, -50, 50) && within_limit(cuboid.xlim.1, -50, 50);
-        if within_limit {
-            let (zmin, zmax) = (clamp(cuboid.zlim.0, -50, 50), clamp(cuboid.zlim.1, -50, 50));
-            let (ymin, ymax) = (clamp(cuboid.ylim.0, -50, 50), clamp(cuboid.ylim.1, -50, 50));
-            let (xmin, xmax) = (clamp(cuboid.xlim.0, -50, 50), clamp(cuboid.xlim.1, -50, 50));
-            for z in (zmin+50)..(zmax+51) {
-                for y in (ymin+50)..(ymax+51) {
-                    for x in (xmin+50)..(xmax+51) {
+        if INIT_REGION.contains_cuboid(&cuboid) {
+            for z in (cuboid.min.z+50)..(cuboid.max.z+51) {
+                for y in (cuboid.min.y+50)..(cuboid.max.y+51) {
+                    for x in (cuboid.min.x+50)..(cuboid.max.x+51) {
                         space[z as usize][y as usize][x as usize] = on;
                     }
                 }
@@ -95,10 +61,10 @@ fn part1(input: &str) -> String {
         }
     }
     let count: u64 = space.into_iter().flatten().flatten().map(|on| if on {1} else {0}).sum();
-    format!("{}", count)
+    Ok(format!("{}", count))
 }
 
-fn part2(input: &str) -> String {
+fn part2(input: &str) -> Result<String, ParseError> {
     /*
      * Based on inclusion-exclusion principle. https://en.wikipedia.org/wiki/Inclusion%E2%80%93exclusion_principle
      */
@@ -107,7 +73,7 @@ fn part2(input: &str) -> String {
         add: bool,
     }
 
-    let instructions = parse(&input);
+    let instructions = parse(input)?;
     // Based
     let mut reactor: Vec<CuboidState> = Vec::new();
     for instr in instructions {
@@ -130,21 +96,62 @@ fn part2(input: &str) -> String {
         }
         reactor.append(&mut extra_cuboids);
     }
+    stats::count("signed cuboids", reactor.len() as u64);
     // Count the number of on cubes
     let mut count: i64 = 0;
     for s in reactor {
         let sign = if s.add {1} else {-1};
         count += sign * (s.cuboid.volume() as i64);
     }
-    count.to_string()
+    Ok(count.to_string())
+}
+
+/// Naive reference: reboots a reactor compressed to the coordinates where cuboids start or end
+pub fn reference_part1(input: &str) -> Option<String> {
+    let instructions = parse(input).ok()?.into_iter().filter(|Instr(c, _)| {
+        INIT_REGION.contains_cuboid(c)
+    }).collect();
+    Some(reference_reboot(instructions).to_string())
 }
 
-fn within_limit(i: i32, min: i32, max: i32) -> bool {
-    i >= min && i <= max
+/// Naive reference: reboots a reactor compressed to the coordinates where cuboids start or end
+pub fn reference_part2(input: &str) -> Option<String> {
+    Some(reference_reboot(parse(input).ok()?).to_string())
 }
 
-fn clamp(i: i32, min: i32, max: i32) -> i32 {
-    if i < min {min} else if i > max {max} else {i}
+fn reference_reboot(instructions: Vec<Instr>) -> u64 {
+    // every cell of the compressed reactor spans the cubes between two consecutive boundaries
+    let boundaries = |lim: fn(&Cuboid) -> (i32, i32)| {
+        let mut b: Vec<i64> = instructions.iter().flat_map(|Instr(c, _)| [lim(c).0 as i64, lim(c).1 as i64 + 1]).collect();
+        b.sort();
+        b.dedup();
+        b
+    };
+    let (xs, ys, zs) = (boundaries(|c| (c.min.x, c.max.x)), boundaries(|c| (c.min.y, c.max.y)), boundaries(|c| (c.min.z, c.max.z)));
+    let index = |b: &Vec<i64>, v: i32| b.binary_search(&(v as i64)).unwrap();
+
+    let cells = |b: &Vec<i64>| b.len().saturating_sub(1);
+    let mut reactor = vec![vec![vec![false; cells(&zs)]; cells(&ys)]; cells(&xs)];
+    for Instr(c, on) in instructions.iter() {
+        for plane in reactor.iter_mut().take(index(&xs, c.max.x + 1)).skip(index(&xs, c.min.x)) {
+            for row in plane.iter_mut().take(index(&ys, c.max.y + 1)).skip(index(&ys, c.min.y)) {
+                for cell in row.iter_mut().take(index(&zs, c.max.z + 1)).skip(index(&zs, c.min.z)) {
+                    *cell = *on;
+                }
+            }
+        }
+    }
+    let mut count = 0;
+    for (x, plane) in reactor.iter().enumerate() {
+        for (y, row) in plane.iter().enumerate() {
+            for (z, on) in row.iter().enumerate() {
+                if *on {
+                    count += ((xs[x+1] - xs[x]) * (ys[y+1] - ys[y]) * (zs[z+1] - zs[z])) as u64;
+                }
+            }
+        }
+    }
+    count
 }
 
 fn in_range(elem: i32, min: i32, max: i32) -> bool {
@@ -153,36 +160,33 @@ fn in_range(elem: i32, min: i32, max: i32) -> bool {
 
 
 // --- Parsing ---
-fn parse_on_off(input: &str) -> IResult<&str, bool> {
-    let on = tag("on").map(|_| true);
-    let off = tag("off").map(|_| false);
-    alt((on, off))(input)
+fn parse_instr(line: &str) -> Result<Instr, ParseError> {
+    let (state, cuboid) = line.split_once(' ').ok_or_else(|| ParseError::new("expected on or off and a cuboid").at(1, 1))?;
+    let on = match state {
+        "on" => true,
+        "off" => false,
+        _ => return Err(ParseError::new(format!("expected on or off, found {:?}", state)).at(1, 1)),
+    };
+    let ranges: Vec<&str> = cuboid.split(',').collect();
+    let (x, y, z) = match ranges[..] {
+        [x, y, z] => (x, y, z),
+        _ => return Err(ParseError::new("expected x, y and z range").locate(line, cuboid)),
+    };
+    let (x1, x2) = parse::range(line, x, "x")?;
+    let (y1, y2) = parse::range(line, y, "y")?;
+    let (z1, z2) = parse::range(line, z, "z")?;
+    let cuboid = Cuboid {min: Point3::new(x1, y1, z1), max: Point3::new(x2, y2, z2)};
+    Ok(Instr(cuboid, on))
 }
 
-fn parse_range(input: &str) -> IResult<&str, (i32, i32)> {
-    let coord_bs = take_till(|c| (c == '-') || (is_digit(c as u8)));
-    preceded(coord_bs, separated_pair(complete::i32, tag(".."), complete::i32))(input)
-}
-
-fn parse_instr(input: &str) -> IResult<&str, Instr> {
-    let trimmed = input.trim();
-    let mut parser = tuple((parse_on_off, parse_range, parse_range, parse_range)).map(|(on, (x1,x2), (y1,y2), (z1,z2))| {
-        let cuboid = Cuboid {
-            xlim: (x1, x2),
-            ylim: (y1, y2),
-            zlim: (z1, z2),
-        };
-        Instr(cuboid, on)
-    });
-    parser.parse(trimmed)
-}
-
-fn parse(input: &str) -> Vec<Instr> {
-    let mut instrs = Vec::new();
-    for line in input.lines() {
-        instrs.push(parse_instr(line).unwrap().1);
-    }
-    instrs
+fn parse(input: &str) -> Result<Vec<Instr>, ParseError> {
+    parse::lines(input, |line| {
+        let instr = parse_instr(line)?;
+        if instr.0.is_empty() {
+            return Err(ParseError::new(format!("empty cuboid {:?}", line)));
+        }
+        Ok(instr)
+    })
 }
 
 fn test_input() -> String {
@@ -277,27 +281,42 @@ off x=-93533..-4276,y=-16170..68771,z=-104985..-24507")
 mod tests {
     use super::*;
     
-    #[test]
-    fn test_cuboid() {
-        let c1 = Cuboid {
-            zlim: (-2,2),
-            ylim: (-2,2),
-            xlim: (-2,2),
-        };
-        let c2 = Cuboid {
-            zlim: (2,5),
-            ylim: (-4,-4),
-            xlim: (-4,-2),
-        };
-        assert_eq!(c1.volume(), 125);
-        assert_eq!(c2.volume(), 12);
-        let c3 = Cuboid {
-            xlim: (-2,2),
-            ylim: (-1,3),
-            zlim: (-1,3),
-        };
-        let intersection = c1.intersection(&c3);
-        assert_eq!(intersection.unwrap().volume(), 80);
+    mod parsing {
+        use super::*;
+        use crate::utils::Lines;
+        use proptest::prelude::*;
+
+        fn range() -> impl Strategy<Value = (i32, i32)> {
+            (any::<i32>(), any::<i32>()).prop_map(|(a, b)| (a.min(b), a.max(b)))
+        }
+
+        fn instr() -> impl Strategy<Value = Instr> {
+            (range(), range(), range(), any::<bool>()).prop_map(|((x1, x2), (y1, y2), (z1, z2), on)| {
+                Instr(Cuboid {min: Point3::new(x1, y1, z1), max: Point3::new(x2, y2, z2)}, on)
+            })
+        }
 
+        #[test]
+        fn test_example() {
+            assert_eq!(part1(&test_input()), Ok(String::from("590784")));
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert!(parse("on x=1..2,y=1..2\n").is_err());
+            assert!(parse("toggle x=1..2,y=1..2,z=1..2\n").is_err());
+        }
+
+        proptest! {
+            #[test]
+            fn test_roundtrip(instructions in prop::collection::vec(instr(), 0..20)) {
+                prop_assert_eq!(parse(&Lines(&instructions).to_string()), Ok(instructions));
+            }
+
+            #[test]
+            fn test_garbage(input in "((on|off|of)? ?x?=?-?[0-9]{0,3}\\.{0,2}-?[0-9]{0,3},?y?=?-?[0-9]{0,3}\\.{0,2}[0-9]{0,3}(,z=)?[0-9]{0,3}\\.{0,2}[0-9]{0,3}\n){0,4}") {
+                let _ = parse(&input);
+            }
+        }
     }
 }
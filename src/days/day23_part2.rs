@@ -1,7 +1,7 @@
+use crate::utils::ParseError;
+use crate::utils::search;
+
 use std::fmt;
-use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::collections::BinaryHeap;
 
 /*
  * Day 23: Amphipod
@@ -11,93 +11,13 @@ use std::collections::BinaryHeap;
  * Implementation: Copy and paste from part1, with some minor adjustments to allow for bigger rooms.
  */
 
-struct DistanceMap(HashMap<State, u32>);
-impl DistanceMap {
-    fn new() -> Self {
-        DistanceMap(HashMap::new())
-    }
-    fn update(&mut self, node: &State, distance: u32) {
-        let entry = self.0.get_mut(&node);
-        if entry.is_some() {
-            let n = entry.unwrap();
-            *n = *n + distance;
-        }else {
-            self.0.insert(node.clone(), distance);
-        }
-    }
-    fn get(&mut self, node: &State) -> u32 {
-        *self.0.get(node).unwrap_or(&u32::MAX)
-    }
-}
-
-#[derive(Debug,Clone,Eq,PartialEq,Hash)]
-struct Node{
-    state: State,
-    g: u32,
-    h: u32,
-}
-
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-// This way we can turn BinaryHeap into a min-heap
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let val1 = self.g + self.h;
-        let val2 = other.g + other.h;
-        val1.cmp(&val2).reverse()
-    }
-}
-
-
-pub fn part2(input: &str) -> String {
-    let start_state = parse(&input);
+pub fn part2(input: &str) -> Result<String, ParseError> {
+    let start_state = parse(input)?;
     // A* algorithm to find shortest path to goal state
     let goal = State::goal_state();
-    let start_node = Node{state: start_state.clone(), g: 0, h: State::heuristic(&start_state)};
-
-    let mut dist = DistanceMap::new();
-    dist.update(&start_node.state, 0);
-
-    let mut queue = BinaryHeap::new();
-    queue.push(start_node);
-    //let mut prev: HashMap<State, State> = HashMap::new(); // bookkeeping for retracing the path
-
-    let mut result = None; 
-    while let Some(n) = queue.pop() {
-        if n.state == goal { result = Some((n.state, n.g)); break;}
-        if n.g > dist.get(&n.state) {continue;} // there is a shorter way to reach the node
-        
-        let ns = n.state.neighbours();
-        for (state, cost) in ns {
-            let h = State::heuristic(&state);
-            let node = Node{state,g: cost+n.g,h};
-            if node.g < dist.get(&node.state) {
-                //prev.insert(node.0.clone(), n.0.clone());
-                dist.update(&node.state, node.g);
-                queue.push(node);
-            }
-        }
-    }
-    // assume it worked
-    let (_, energy) = result.unwrap();
-    //// print path
-    //let mut path = Vec::new();
-    //path.push(state.clone());
-    //let mut cur_state = state;
-    //let prev_mut = &mut prev;
-    //while let Some(s) = prev_mut.remove(&cur_state) {
-    //    path.push(s.clone());
-    //    cur_state = s;
-    //}
-    //println!("PATH");
-    //for s in path.into_iter().rev() {
-    //    println!("{}\n", s);
-    //}
-
-    format!("{}", energy)
+    let solution = search::astar(start_state, State::neighbours, State::heuristic, |s| *s == goal)
+        .ok_or_else(|| ParseError::new("amphipods can not be organized"))?;
+    Ok(format!("{}", solution.cost))
 }
 
 #[derive(Debug,Hash,Eq,PartialEq,Copy,Clone)]
@@ -394,18 +314,23 @@ impl fmt::Display for State {
 }
 
 // --- PARSING ---
-fn parse(input: &str) -> State {
+fn parse_amphipod(c: Option<char>) -> Result<M, ParseError> {
+    match c {
+        Some('A') => Ok(M::A),
+        Some('B') => Ok(M::B),
+        Some('C') => Ok(M::C),
+        Some('D') => Ok(M::D),
+        Some(a) => Err(ParseError::new(format!("unrecognized character {:?}", a))),
+        None => Err(ParseError::new("incomplete burrow")),
+    }
+}
+
+fn parse(input: &str) -> Result<State, ParseError> {
     let mut lines = input.lines().skip(2);
-    let mut row1 = lines.next().unwrap().chars().skip(3).step_by(2);
     let mut state = State::new();
+    let mut row1 = lines.next().unwrap_or("").chars().skip(3).step_by(2);
     for i in 0..4 {
-        match row1.next().unwrap() {
-            'A' => {state.burrow[11+i*4] = M::A},
-            'B' => {state.burrow[11+i*4] = M::B},
-            'C' => {state.burrow[11+i*4] = M::C},
-            'D' => {state.burrow[11+i*4] = M::D},
-            a => {panic!("Unrecognized Character: {}", a)},
-        }
+        state.burrow[11+i*4] = parse_amphipod(row1.next())?;
     }
 
     // Insert extra part between top and bottom row.
@@ -419,17 +344,16 @@ fn parse(input: &str) -> State {
     burrow[24] = M::A;
     burrow[25] = M::C;
 
-    let mut row2 = lines.next().unwrap().chars().skip(3).step_by(2);
+    let mut row2 = lines.next().unwrap_or("").chars().skip(3).step_by(2);
     for i in 0..4 {
-        match row2.next().unwrap() {
-            'A' => {state.burrow[14+i*4] = M::A},
-            'B' => {state.burrow[14+i*4] = M::B},
-            'C' => {state.burrow[14+i*4] = M::C},
-            'D' => {state.burrow[14+i*4] = M::D},
-            a => {panic!("Unrecognized Character: {}", a)},
+        state.burrow[14+i*4] = parse_amphipod(row2.next())?;
+    }
+    for m in [M::A, M::B, M::C, M::D] {
+        if state.burrow.iter().filter(|&&b| b == m).count() != 4 {
+            return Err(ParseError::new(format!("expected two amphipods of type {:?}", m)));
         }
     }
-    return state;
+    Ok(state)
 }
 
 // --- TEST INPUTS ---
@@ -515,4 +439,27 @@ mod tests {
         }
         assert!(goal == state);
     }
+
+    mod parsing {
+        use super::*;
+        use proptest::prelude::*;
+
+        #[test]
+        fn test_example() {
+            assert_eq!(part2(&test_input()), Ok(String::from("44169")));
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert!(parse("#############\n#...........#\n###B#C#B#D###\n").is_err());
+            assert!(parse("#############\n#...........#\n###B#C#B#E###\n  #A#D#C#A#\n").is_err());
+        }
+
+        proptest! {
+            #[test]
+            fn test_garbage(input in "(#{0,13}\n)?#?[.A-DE]{0,11}#?\n(#{0,3}[A-D.]#[A-D.]#[A-D.]#[A-D.]#{0,3}\n){0,2}") {
+                let _ = parse(&input);
+            }
+        }
+    }
 }
@@ -1,9 +1,11 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+
+use std::fmt;
 
 use nom::{
-    character,
-    error,
-    bytes::complete::take_till,
+    character::complete,
+    bytes::complete::tag,
     sequence::preceded,
 };
 
@@ -18,8 +20,8 @@ use nom::{
 pub struct Day24();
 
 impl AOCDay for Day24 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 24 }
 }
 
@@ -110,71 +112,181 @@ SOLUTION BY HAND: 18116121134117
 
 
 /// Chunk contains all neccesary information about a chunk (18 lines of instructions) in the input file
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 struct Chunk {
     popped_stack: bool, // Variable 1
     eql_offset: i32, // Variable 2
     stack_offset: u32, // Variable 3
 }
 
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let div = if self.popped_stack {26} else {1};
+        write!(f, "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z {}\nadd x {}\neql x w\neql x 0\nmul y 0\nadd y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y {}\nmul y x\nadd z y",
+            div, self.eql_offset, self.stack_offset)
+    }
+}
+
 #[derive(Debug)]
 struct Constraint(usize, i32, usize); // c.0+c.1 == c.2
 
 /// Solve automatically
-fn part1(input: &str) -> String {
-    let chunks = parse(&input);
-    let constraints = get_constraints(&chunks);
-    solve(&constraints, false, 14)
+fn part1(input: &str) -> Result<String, ParseError> {
+    let chunks = parse(input)?;
+    let constraints = get_constraints(&chunks)?;
+    solve(&constraints, false, chunks.len())
 }
 
-fn part2(input: &str) -> String {
-    let chunks = parse(&input);
-    let constraints = get_constraints(&chunks);
-    solve(&constraints, true, 14)
+fn part2(input: &str) -> Result<String, ParseError> {
+    let chunks = parse(input)?;
+    let constraints = get_constraints(&chunks)?;
+    solve(&constraints, true, chunks.len())
 }
 
 /// Derives constraints from the input
-fn get_constraints(chunks: &Vec<Chunk>) -> Vec<Constraint> {
+fn get_constraints(chunks: &[Chunk]) -> Result<Vec<Constraint>, ParseError> {
     let mut constraints = Vec::new();
-    let mut stack = vec![(999,999)];
+    let mut stack = Vec::new();
     for i in 0..chunks.len() {
         let chunk = &chunks[i];
-        let &(d, offset) = &stack[stack.len() - 1];
+        let top = stack.last().copied();
         if chunk.popped_stack {stack.pop();}
         if chunk.eql_offset > 9 { // condition will always be false and value is put on stack
             stack.push((i, chunk.stack_offset));
         }else { // assume that the condition must be true
+            let (d, offset) = top.ok_or_else(|| ParseError::new(format!("chunk {} compares with an empty stack", i)))?;
             constraints.push(Constraint(d,offset as i32+chunk.eql_offset,i));
         }
     }
-    constraints
+    Ok(constraints)
 }
 
 /// Solves the constraints
-fn solve(constraints: &Vec<Constraint>, min: bool, digit_length: usize) -> String {
+fn solve(constraints: &Vec<Constraint>, min: bool, digit_length: usize) -> Result<String, ParseError> {
     let mut digits = vec![0;digit_length];
-    constraints.into_iter().for_each(|c| {
-        let iter = (1..10).into_iter().filter(|n| n+c.1 > 0 && n+c.1 < 10);
-        let left = if min {iter.min().unwrap()} else {iter.max().unwrap()};
+    for c in constraints {
+        let mut iter = (1..10).filter(|n| n+c.1 > 0 && n+c.1 < 10);
+        let left = if min {iter.next()} else {iter.next_back()};
+        let left = left.ok_or_else(|| ParseError::new(format!("digits {} and {} can not differ by {}", c.0, c.2, c.1)))?;
         let right = left + c.1;
         digits[c.0] = left;
         digits[c.2] = right;
-    });
-    digits.into_iter().map(|d| d.to_string()).collect::<String>()
+    }
+    Ok(digits.into_iter().map(|d| d.to_string()).collect::<String>())
+}
+
+
+// --- REFERENCE ---
+/// Naive reference: runs MONAD on every model number, starting at the highest number.
+/// Only feasible for programs reading a few digits.
+pub fn reference_part1(input: &str) -> Option<String> {
+    search_model_number(input, false)
 }
 
+/// Naive reference: runs MONAD on every model number, starting at the lowest number.
+pub fn reference_part2(input: &str) -> Option<String> {
+    search_model_number(input, true)
+}
+
+fn search_model_number(input: &str, min: bool) -> Option<String> {
+    let program: Vec<Vec<&str>> = input.lines().map(|l| l.split_whitespace().collect()).collect();
+    let digit_count = program.iter().filter(|i| i[0] == "inp").count();
+    if digit_count > 8 { return None; } // would take too long
+    let mut digits = vec![if min {1} else {9}; digit_count];
+    loop {
+        if run_alu(&program, &digits)? == 0 {
+            return Some(digits.iter().map(|d| d.to_string()).collect());
+        }
+        // next model number, digits never contain zeroes
+        let mut i = digit_count;
+        loop {
+            if i == 0 { return None; }
+            i -= 1;
+            if min && digits[i] < 9 { digits[i] += 1; break; }
+            if !min && digits[i] > 1 { digits[i] -= 1; break; }
+            digits[i] = if min {1} else {9};
+        }
+    }
+}
+
+/// Executes the program and returns the value of `z`, None for invalid operations.
+fn run_alu(program: &[Vec<&str>], digits: &[i64]) -> Option<i64> {
+    let mut regs = [0i64; 4]; // w, x, y, z
+    let reg = |r: &str| "wxyz".find(r);
+    let mut digits = digits.iter();
+    for instr in program {
+        let a = reg(instr[1])?;
+        let b = if instr[0] == "inp" { *digits.next()? } else {
+            match reg(instr[2]) { Some(r) => regs[r], None => instr[2].parse().ok()? }
+        };
+        regs[a] = match instr[0] {
+            "inp" => b,
+            "add" => regs[a] + b,
+            "mul" => regs[a] * b,
+            "div" if b != 0 => regs[a] / b,
+            "mod" if regs[a] >= 0 && b > 0 => regs[a] % b,
+            "eql" => (regs[a] == b) as i64,
+            _ => return None,
+        };
+    }
+    Some(regs[3])
+}
 
 // --- PARSING ---
-fn parse(input: &str) -> Vec<Chunk> {
+/// Parses the variable of a single instruction, e.g. `add x -7`
+fn parse_variable(line: &str, instr: &str) -> Result<i32, ParseError> {
+    parse::complete(line, preceded(tag(instr), complete::i32))
+}
+
+/// Every chunk must match the MONAD template, apart from its three variables
+fn parse_chunk(lines: &[&str]) -> Result<Chunk, ParseError> {
+    let div = parse_variable(lines[4], "div z ")?;
+    if div != 1 && div != 26 {
+        return Err(ParseError::new(format!("unexpected {:?}", lines[4])));
+    }
+    let eql_offset = parse_variable(lines[5], "add x ")?;
+    let stack_offset = u32::try_from(parse_variable(lines[15], "add y ")?)
+        .map_err(|_| ParseError::new(format!("negative offset {:?}", lines[15])))?;
+    let chunk = Chunk{popped_stack: div == 26, eql_offset, stack_offset};
+    if let Some((expected, line)) = chunk.to_string().lines().zip(lines).find(|(e, l)| *e != l.trim()) {
+        return Err(ParseError::new(format!("expected {:?}, found {:?}", expected, line)));
+    }
+    Ok(chunk)
+}
+
+fn parse(input: &str) -> Result<Vec<Chunk>, ParseError> {
     let lines: Vec<&str> = input.lines().collect();
-    let mut chunks = Vec::new();
-    let mut instr_parser = preceded::<&str,_,i32,error::Error<&str>,_,_>(take_till(|c: char| c.is_ascii_digit() || c == '-'), character::complete::i32);
-    for i in 0..14 {
-        let popped_stack = instr_parser(lines[4+18*i]).unwrap().1 == 26;
-        let eql_offset = instr_parser(lines[5+18*i]).unwrap().1;
-        let stack_offset = instr_parser(lines[15+18*i]).unwrap().1 as u32;
-        chunks.push(Chunk{popped_stack,eql_offset,stack_offset});
+    if lines.is_empty() || !lines.len().is_multiple_of(18) {
+        return Err(ParseError::new("program does not consist of chunks of 18 instructions"));
     }
-    chunks
+    lines.chunks(18).map(parse_chunk).collect()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    fn chunk() -> impl Strategy<Value = Chunk> {
+        (any::<bool>(), any::<i32>(), 0..=i32::MAX as u32).prop_map(|(popped_stack, eql_offset, stack_offset)| Chunk {popped_stack, eql_offset, stack_offset})
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("inp w\nadd x 1\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(chunks in prop::collection::vec(chunk(), 1..14)) {
+            prop_assert_eq!(parse(&Lines(&chunks).to_string()), Ok(chunks));
+        }
+
+        #[test]
+        fn test_garbage(input in "((inp|add|mul|div|mod|eql) [wxyz] ?[wxyz0-9-]{0,3}\n){0,40}") {
+            let _ = parse(&input);
+            let _ = part1(&input);
+        }
+    }
+}
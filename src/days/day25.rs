@@ -1,4 +1,10 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::bits::BitBoard;
+use crate::utils::grid::Grid;
+use crate::visualize::{Colour, Frame, Visualize};
+
+use std::fmt;
 
 /*
  * Template for a implementing a day
@@ -7,11 +13,29 @@ use crate::AOCDay;
 pub struct Day25();
 
 impl AOCDay for Day25 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(String::from("🌟 Happy Christmas!!!🎅")) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { Ok(String::from("🌟 Happy Christmas!!!🎅")) }
     fn get_num(&self) -> u32 { 25 }
 }
 
+impl Visualize for Day25 {
+    /// The herds of sea cucumbers, until none of them can move
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError> {
+        let mut sea = Herds::from(&parse(input)?);
+        let mut moving = true;
+        let mut first = true;
+        Ok(Box::new(std::iter::from_fn(move || {
+            if !moving { return None; }
+            if first {
+                first = false;
+            } else {
+                moving = iterate(&mut sea);
+            }
+            Some(sea_to_frame(&sea))
+        })))
+    }
+}
+
 pub fn get() -> Day25 {Day25()}
 
 // --- STRUCTURES ---
@@ -22,54 +46,69 @@ enum S {
     East,
 }
 
-fn part1(input: &str) -> String {
-    let mut sea = parse(&input);
+impl fmt::Display for S {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            S::Empty => write!(f, "."),
+            S::South => write!(f, "v"),
+            S::East => write!(f, ">"),
+        }
+    }
+}
+
+/// Positions of both herds
+struct Herds {
+    east: BitBoard,
+    south: BitBoard,
+}
+
+impl From<&Grid<S>> for Herds {
+    fn from(sea: &Grid<S>) -> Self {
+        Herds {east: BitBoard::from(&sea.map(|s| *s == S::East)), south: BitBoard::from(&sea.map(|s| *s == S::South))}
+    }
+}
+
+fn part1(input: &str) -> Result<String, ParseError> {
+    let mut sea = Herds::from(&parse(input)?);
     let mut i = 0;
     while iterate(&mut sea) {
         i+=1;
     }
-    format!("{}", i+1)
+    Ok(format!("{}", i+1))
 }
 
-fn iterate(sea: &mut Vec<Vec<S>>) -> bool {
-    let mut updated_sea = sea.clone();
-    // First East Seacucumbers move
-    let mut updated = false;
-    for y in 0..sea.len() {
-        for x in 0..sea[0].len()-1 {
-            if sea[y][x] == S::East && sea[y][x+1] == S::Empty{
-                updated_sea[y][x] = S::Empty;
-                updated_sea[y][x+1] = S::East;
-                updated = true;
-            }
-        }
-        if sea[y][sea[0].len()-1] == S::East && sea[y][0] == S::Empty { // handle edge case
-                updated_sea[y][sea[0].len()-1] = S::Empty;
-                updated_sea[y][0] = S::East;
-                updated = true;
-        }
+fn iterate(sea: &mut Herds) -> bool {
+    // First East Seacucumbers move, then South Seacucumbers move
+    let east = move_herd(&mut sea.east, &sea.south, (1, 0));
+    let south = move_herd(&mut sea.south, &sea.east, (0, 1));
+    east || south
+}
+
+/// Simultaneously moves every sea cucumber of a herd, which is facing an empty spot.
+/// Sea cucumbers moving off an edge reappear on the opposite edge.
+fn move_herd(herd: &mut BitBoard, other: &BitBoard, (dx, dy): (isize, isize)) -> bool {
+    let empty = !&(&*herd | other);
+    // shifting the empty spots back onto the cucumbers facing them
+    let moving = &*herd & &empty.wrapping_shift(-dx, -dy);
+    if !moving.any() {
+        return false;
     }
-    *sea = updated_sea;
-    updated_sea = sea.clone();
-    // Second South Seacucumbers move
-    for y in 0..sea.len()-1 {
-        for x in 0..sea[0].len() {
-            if sea[y][x] == S::South && sea[y+1][x] == S::Empty{
-                updated_sea[y][x] = S::Empty;
-                updated_sea[y+1][x] = S::South;
-                updated = true;
-            }
-        }
+    *herd = &(&*herd ^ &moving) | &moving.wrapping_shift(dx, dy);
+    true
+}
+
+// --- VISUALIZATION ---
+const PALETTE: [Colour; 3] = [(0, 20, 60), (230, 120, 40), (60, 200, 90)];
+
+fn sea_to_frame(sea: &Herds) -> Frame {
+    let mut frame = Frame::new(sea.east.width(), sea.east.height(), &PALETTE);
+    for (x, y) in sea.east.ones() {
+        frame.set(x, y, 1);
     }
-    for x in 0..sea[0].len() { // handle edge case
-        if sea[sea.len()-1][x] == S::South && sea[0][x] == S::Empty{
-            updated_sea[sea.len()-1][x] = S::Empty;
-            updated_sea[0][x] = S::South;
-            updated = true;
-        }
+    for (x, y) in sea.south.ones() {
+        frame.set(x, y, 2);
     }
-    *sea = updated_sea;
-    updated
+    frame
 }
 
 // --- TEST INPUTS ---
@@ -86,20 +125,46 @@ v.v..>>v.v
 }
 
 // --- PARSING ---
-fn parse(input: &str) -> Vec<Vec<S>> {
-    let mut grid = Vec::new();
-    for line in input.lines().into_iter() {
-        let chars = line.trim_end().chars();
-        let mut v = Vec::new();
-        for c in chars {
-            match c {
-                'v' => {v.push(S::South)},
-                '>' => {v.push(S::East)},
-                '.' => {v.push(S::Empty)},
-                _ => {panic!()},
-            }
+fn parse(input: &str) -> Result<Grid<S>, ParseError> {
+    parse::grid(input, |c| match c {
+        'v' => Ok(S::South),
+        '>' => Ok(S::East),
+        '.' => Ok(S::Empty),
+        c => Err(ParseError::new(format!("unrecognized character {:?}", c))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn sea() -> impl Strategy<Value = Grid<S>> {
+        let s = prop_oneof![Just(S::Empty), Just(S::South), Just(S::East)];
+        (1..20usize).prop_flat_map(move |width| prop::collection::vec(prop::collection::vec(s.clone(), width), 1..20))
+            .prop_map(|rows| Grid::from_rows(rows).unwrap())
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("58")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("v.x\n").is_err());
+        assert!(parse("v.\n>\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip(sea in sea()) {
+            prop_assert_eq!(parse(&sea.to_string()), Ok(sea));
+        }
+
+        #[test]
+        fn test_garbage(input in "([.v>x]{0,8}\n){0,8}") {
+            let _ = parse(&input);
         }
-        grid.push(v);
     }
-    grid
 }
@@ -1,4 +1,9 @@
-use crate::AOCDay;
+use crate::{AOCDay, StreamingDay};
+use crate::utils::{parse, ParseError};
+use crate::utils::bigint::{BigUint, Natural};
+
+use std::io::BufRead;
+use std::ops::Range;
 
 /*
  * Template for a implementing a day
@@ -7,101 +12,276 @@ use crate::AOCDay;
 pub struct Day3();
 
 impl AOCDay for Day3 {
-    fn part1(&self, input: &str) -> Option<String> { Some(power_consumption(input)) }
-    fn part2(&self, input: &str) -> Option<String> { Some(life_support_rating(input)) }
+    fn part1(&self, input: &str) -> Result<String, ParseError> { power_consumption(input) }
+    fn part2(&self, input: &str) -> Result<String, ParseError> { life_support_rating(input) }
     fn get_num(&self) -> u32 { 3 }
 }
 
+/// Part 1 only keeps a count per bit. Part 2 is not constant memory and can not be: the ratings filter
+/// the numbers again for every bit, so it keeps the digits of every number in a single buffer.
+impl StreamingDay for Day3 {
+    fn part1_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> {
+        let (mut len, mut ones) = (0, Vec::new());
+        stream_numbers(input, |line| {
+            if len == 0 { ones = vec![0; line.len()]; }
+            len += 1;
+            for (count, c) in ones.iter_mut().zip(line.bytes()) {
+                *count += (c == b'1') as usize;
+            }
+        })?;
+        Ok(format!("{}", power(len, &ones)))
+    }
+
+    fn part2_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError> {
+        let (mut width, mut digits) = (0, String::new());
+        stream_numbers(input, |line| {
+            width = line.len();
+            digits.push_str(line);
+        })?;
+        let report = Report::new(width, digits.as_bytes().chunks(width).collect());
+        Ok(format!("{}", report.rating(true) * report.rating(false)))
+    }
+}
+
 pub fn get() -> Day3 {Day3()}
 
 fn get_test_input() -> String {
     String::from("00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010")
 }
 
-fn power_consumption(input: &str) -> String {
-    let lines: Vec<&str> = input.lines().collect();
-    let number_count = lines.len() / 2;
-    let number_size = lines[0].len();
-    let ones = count_ones(&lines);
-
-    let mut gamma: u32 = 0;
-    let mut epsilon: u32 = 0;
-    for i in 0..number_size {
-        if ones[i] > number_count {
-            // 1 heavy
-            gamma = (gamma << 1) | 0x01;
-            epsilon = epsilon << 1;
-        }else {
-            // 0 heavy
-            gamma = gamma << 1;
-            epsilon = epsilon << 1 | 0x01;
-        }
-    }
-    String::from(format!("{}", gamma * epsilon).as_str())
+/// The diagnostic report, binary numbers of any width stored as their digits.
+/// The rows are sorted, such that the numbers sharing a prefix are contiguous.
+pub struct Report<'a> {
+    width: usize,
+    rows: Vec<&'a [u8]>,
 }
 
-fn life_support_rating(input: &str) -> String {
-    let lines: Vec<&str> = input.lines().collect();
+/// The candidates left after filtering by a bit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub bit: usize, // position of the bit, the most significant bit is 0
+    pub digit: char, // value of the bit of all candidates
+    pub candidates: Range<usize>, // rows of the report
+}
 
-    let mut oxygen_candidates = lines.clone();
-    let mut co2_candidates = lines;
+impl<'a> Report<'a> {
+    /// Sorts the rows in O(n·w) with a radix sort, stably partitioning them by every bit from the least significant.
+    pub fn new(width: usize, mut rows: Vec<&'a [u8]>) -> Self {
+        for bit in (0..width).rev() {
+            let (zeros, ones): (Vec<_>, Vec<_>) = rows.into_iter().partition(|row| row[bit] == b'0');
+            rows = zeros;
+            rows.extend(ones);
+        }
+        Report {width, rows}
+    }
 
-    for i in 0..oxygen_candidates.len() {
-        let number_count = (oxygen_candidates.len() as f32) / 2.0;
-        let ones = count_ones(&oxygen_candidates);
-        if ones[i] as f32 >= number_count {
-            oxygen_candidates = eliminate_with_char_at_index(i, '0', oxygen_candidates);
-        }else {
-            oxygen_candidates = eliminate_with_char_at_index(i, '1', oxygen_candidates);
+    /// Number of ones per bit
+    fn ones(&self) -> Vec<usize> {
+        let mut ones = vec![0; self.width];
+        for row in self.rows.iter() {
+            for (count, &c) in ones.iter_mut().zip(row.iter()) {
+                *count += (c == b'1') as usize;
+            }
         }
+        ones
+    }
 
-        if oxygen_candidates.len() <= 1 {break;}
+    /// The numbers in the rows `range`
+    pub fn rows(&self, range: Range<usize>) -> &[&'a [u8]] {
+        &self.rows[range]
     }
 
-    for i in 0..co2_candidates.len() {
-        let number_count = co2_candidates.len() as f32 / 2.0;
-        let ones = count_ones(&co2_candidates);
-        if ones[i] as f32 >= number_count {
-            // '0' least or equally common
-            co2_candidates = eliminate_with_char_at_index(i, '1', co2_candidates);
-        }else {
-            co2_candidates = eliminate_with_char_at_index(i, '0', co2_candidates);
+    /// Filters the numbers bit by bit, keeping those with the most common bit (ties keep 1)
+    /// or with the least common bit (ties keep 0), until a single number is left.
+    /// The candidates always share a prefix, so every step is a binary search within them.
+    pub fn filter(&self, most_common: bool) -> Vec<Step> {
+        let mut steps = Vec::new();
+        let mut candidates = 0..self.rows.len();
+        for bit in 0..self.width {
+            if candidates.len() <= 1 {break;}
+            let split = candidates.start + self.rows(candidates.clone()).partition_point(|row| row[bit] == b'0');
+            let ones = candidates.end - split;
+            let keep_ones = (ones * 2 >= candidates.len()) == most_common;
+            let kept = if keep_ones {split..candidates.end} else {candidates.start..split};
+            // if every candidate has the same bit, none of them can be eliminated
+            if !kept.is_empty() {
+                candidates = kept;
+            }
+            let digit = self.rows[candidates.start][bit] as char;
+            steps.push(Step {bit, digit, candidates: candidates.clone()});
         }
+        steps
+    }
 
-        if co2_candidates.len() <= 1 {break;}
+    /// The number left after filtering
+    fn rating(&self, most_common: bool) -> BigUint {
+        let first = self.filter(most_common).last().map_or(0, |step| step.candidates.start);
+        value(self.rows[first])
     }
+}
+
+/// The number with the binary digits `digits`
+fn value(digits: &[u8]) -> BigUint {
+    digits.iter().fold(BigUint::zero(), |n, &c| n.clone() + n + BigUint::from((c == b'1') as u64))
+}
 
+fn power_consumption(input: &str) -> Result<String, ParseError> {
+    let report = report(input)?;
+    Ok(format!("{}", power(report.rows.len(), &report.ones())))
+}
+
+/// Product of the gamma and epsilon rate, given the number of ones per bit of `len` numbers
+fn power(len: usize, ones: &[usize]) -> BigUint {
+    // 1 heavy or 0 heavy
+    let gamma: Vec<u8> = ones.iter().map(|count| if count * 2 > len {b'1'} else {b'0'}).collect();
+    let epsilon: Vec<u8> = gamma.iter().map(|&c| if c == b'1' {b'0'} else {b'1'}).collect();
+    value(&gamma) * value(&epsilon)
+}
 
-    
-    let oxygen_rating = u32::from_str_radix(oxygen_candidates.pop().unwrap(), 2).unwrap();
-    let co2_rating = u32::from_str_radix(co2_candidates.pop().unwrap(), 2).unwrap();
-     
-    String::from(format!("{}", oxygen_rating * co2_rating))
+fn life_support_rating(input: &str) -> Result<String, ParseError> {
+    let report = report(input)?;
+    let oxygen_rating = report.rating(true);
+    let co2_rating = report.rating(false);
+    Ok(format!("{}", oxygen_rating * co2_rating))
 }
 
-/// Removes string with a character at an index
-fn eliminate_with_char_at_index(index: usize, c: char, vec: Vec<&str>) -> Vec<&str> {
-    let mut result = Vec::new();
-    for e in vec {
-        let chars: Vec<char> = e.chars().collect(); 
-        if chars[index] != c {
-            result.push(e);
+/// Both ratings of part 2 with the candidates left after every step, which are listed if there are few.
+pub fn explain(input: &str) -> Result<String, ParseError> {
+    let report = report(input)?;
+    let mut lines = Vec::new();
+    for (name, most_common) in [("oxygen generator rating", true), ("CO2 scrubber rating", false)] {
+        lines.push(format!("{}: {} candidates", name, report.rows.len()));
+        let steps = report.filter(most_common);
+        for step in steps.iter() {
+            let rows = report.rows(step.candidates.clone());
+            let mut line = format!("  bit {} = {}: {} candidates", step.bit + 1, step.digit, rows.len());
+            if rows.len() <= 8 {
+                let numbers: Vec<&str> = rows.iter().map(|row| std::str::from_utf8(row).unwrap()).collect();
+                line.push_str(&format!(" ({})", numbers.join(", ")));
+            }
+            lines.push(line);
         }
+        let first = report.rows[steps.last().map_or(0, |step| step.candidates.start)];
+        lines.push(format!("  {} = {}", std::str::from_utf8(first).unwrap(), value(first)));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn report(input: &str) -> Result<Report<'_>, ParseError> {
+    let lines = parse(input)?;
+    Ok(Report::new(lines[0].len(), lines.iter().map(|l| l.as_bytes()).collect()))
+}
+
+/// Parses the diagnostic report, binary numbers of equal width
+fn parse(input: &str) -> Result<Vec<&str>, ParseError> {
+    let lines = parse::lines(input, binary_number)?;
+    if lines.is_empty() {
+        return Err(ParseError::new("empty report"));
+    }
+    if let Some(i) = lines.iter().position(|l| l.len() != lines[0].len()) {
+        return Err(width_error(lines[0].len(), lines[i]).offset(i + 1));
     }
-    result
+    Ok(lines)
 }
 
-fn count_ones(v: &Vec<&str>) -> Vec<usize> {
-    let num_len = v[0].len();
-    let mut vec = Vec::with_capacity(num_len);
-    for _ in 0..num_len { vec.push(0);} // fill vector with zeroes
-    for l in v {
-        let n: u32 = u32::from_str_radix(l, 2).unwrap();
-        for i in 0..num_len {
-            let s = 0x01 & (n >> i);
-            if s > 0 { vec[num_len-1-i] += 1; }
+fn binary_number(l: &str) -> Result<&str, ParseError> {
+    if l.is_empty() || l.chars().any(|c| c != '0' && c != '1') {
+        return Err(ParseError::new(format!("invalid binary number {:?}", l)));
+    }
+    Ok(l)
+}
+
+fn width_error(width: usize, l: &str) -> ParseError {
+    ParseError::new(format!("expected a number of {} bits, found {} bits", width, l.len()))
+}
+
+/// Passes every binary number of the report to `f`, with the same checks as `parse`
+fn stream_numbers(input: &mut dyn BufRead, mut f: impl FnMut(&str)) -> Result<(), ParseError> {
+    let mut width = None;
+    parse::stream_lines(input, |line| {
+        let line = binary_number(line)?;
+        let width = *width.get_or_insert(line.len());
+        if width != line.len() {
+            return Err(width_error(width, line));
         }
+        f(line);
+        Ok(())
+    })?;
+    if width.is_none() {
+        return Err(ParseError::new("empty report"));
     }
-    vec 
+    Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    fn report() -> impl Strategy<Value = Vec<String>> {
+        (1..=32usize).prop_flat_map(|width| prop::collection::vec(prop::collection::vec(prop::bool::ANY, width), 1..30))
+            .prop_map(|rows| rows.into_iter().map(|r| r.into_iter().map(|b| if b {'1'} else {'0'}).collect()).collect())
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(power_consumption(&get_test_input()), Ok(String::from("198")));
+        assert_eq!(life_support_rating(&get_test_input()), Ok(String::from("230")));
+        // identical numbers can not be told apart
+        assert_eq!(life_support_rating("101\n101\n"), Ok(String::from("25")));
+    }
+
+    #[test]
+    fn test_filter() {
+        let input = get_test_input();
+        let report = super::report(&input).unwrap();
+        let explain = |most_common| -> Vec<(char, usize)> {
+            report.filter(most_common).iter().map(|step| (step.digit, step.candidates.len())).collect()
+        };
+        assert_eq!(explain(true), vec![('1', 7), ('0', 4), ('1', 3), ('1', 2), ('1', 1)]);
+        assert_eq!(explain(false), vec![('0', 5), ('1', 2), ('0', 1)]);
+        assert!(super::explain(&input).unwrap().ends_with("  bit 3 = 0: 1 candidates (01010)\n  01010 = 10"));
+    }
+
+    #[test]
+    fn test_wide() {
+        let wide = format!("1{0}\n1{0}\n0{0}\n", "0".repeat(63));
+        assert_eq!(power_consumption(&wide), Ok(String::from("85070591730234615856620279821087277056")));
+        let e = parse("0101\n1100\n110\n").unwrap_err();
+        assert_eq!((e.line(), e.to_string().contains("expected a number of 4 bits, found 3 bits")), (Some(3), true));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("0101\n011\n").is_err());
+        assert!(parse("01a1\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_stream(report in report()) {
+            let input = Lines(&report).to_string();
+            prop_assert_eq!(get().part1_stream(&mut input.as_bytes()), power_consumption(&input));
+            prop_assert_eq!(get().part2_stream(&mut input.as_bytes()), life_support_rating(&input));
+        }
+
+        #[test]
+        fn test_leading_zeros(report in report()) {
+            // zeros eliminate no candidates, so the ratings do not change
+            let padded: Vec<String> = report.iter().map(|r| format!("{}{}", "0".repeat(100), r)).collect();
+            prop_assert_eq!(life_support_rating(&Lines(&padded).to_string()), life_support_rating(&Lines(&report).to_string()));
+        }
+
+        #[test]
+        fn test_roundtrip(report in report()) {
+            let serialized = Lines(&report).to_string();
+            prop_assert_eq!(parse(&serialized), Ok(report.iter().map(|r| r.as_str()).collect()));
+        }
+
+        #[test]
+        fn test_garbage(input in "[01a ]{0,6}(\n[01]{0,6}){0,5}") {
+            let _ = parse(&input);
+        }
+    }
+}
@@ -1,4 +1,8 @@
 use crate::AOCDay;
+use crate::utils::ParseError;
+
+use std::collections::HashMap;
+use std::fmt;
 
 /*
  * Template for a implementing a day
@@ -6,119 +10,152 @@ use crate::AOCDay;
 
 pub struct Day4();
 
-pub type Board = Vec<Vec<(u32, bool)>>;
 pub type Draw = Vec<u32>;
 
 impl AOCDay for Day4 {
-    fn part1(&self, input: &str) -> Option<String> { Some(find_winning_board(input)) }
-    fn part2(&self, input: &str) -> Option<String> { Some(find_losing_board(input)) }
+    fn part1(&self, input: &str) -> Result<String, ParseError> { find_winning_board(input) }
+    fn part2(&self, input: &str) -> Result<String, ParseError> { find_losing_board(input) }
     fn get_num(&self) -> u32 { 4 }
 }
 
 pub fn get() -> Day4 {Day4()}
 
-fn find_winning_board(input: &str) -> String {
-    let (draw, mut boards) = parsing::parse(&input);
+/// A board of any number of rows of equal width, stored row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    width: usize,
+    numbers: Vec<u32>,
+}
 
-    let mut winning_board: Option<Board> = None;
-    let mut winning_draw: Option<u32> = None;
+impl Board {
+    pub fn new(rows: Vec<Vec<u32>>) -> Self {
+        Board {width: rows.first().map_or(0, |row| row.len()), numbers: rows.concat()}
+    }
 
-    'outer: for d in draw {
-        for b in boards.iter_mut() {
-            mark_num(b, d);
+    fn height(&self) -> usize {
+        self.numbers.len() / self.width
+    }
 
-            if bingo(b) {
-                winning_board = Some(b.clone());
-                winning_draw = Some(d);
-                break 'outer;
-            }
-        }
+    /// The lines containing the cell `i`: its row, its column and, if enabled on a square board,
+    /// the main diagonal and the anti-diagonal, numbered in that order.
+    fn lines(&self, i: usize, diagonals: bool) -> impl Iterator<Item = usize> {
+        let (height, width) = (self.height(), self.width);
+        let (r, c) = (i / width, i % width);
+        let square = diagonals && width == height;
+        let main = (square && r == c).then_some(height + width);
+        let anti = (square && r + c == width - 1).then_some(height + width + 1);
+        [Some(r), Some(height + c), main, anti].into_iter().flatten()
     }
 
-    // find winning score for board
-    let board = winning_board.unwrap();
-    let draw = winning_draw.unwrap();
-    let score = compute_score(&board, draw);
-    String::from(format!("{}", score))
+    /// Number of cells on every line numbered by `lines`
+    fn line_lengths(&self) -> Vec<usize> {
+        let (height, width) = (self.height(), self.width);
+        let mut lengths = vec![width; height];
+        lengths.extend(vec![height; width]);
+        lengths.extend([width; 2]);
+        lengths
+    }
 }
 
-fn find_losing_board(input: &str) -> String {
-    let (draw, boards) = parsing::parse(&input);
+/// The numbers to be drawn and the boards, wins are complete rows or columns and optionally diagonals
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bingo {
+    draw: Draw,
+    boards: Vec<Board>,
+    diagonals: bool,
+}
 
-    let mut losing_boards = boards;
+/// A board winning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize, // index of the board
+    pub turn: usize, // index of the winning number in the draw
+    pub number: u32,
+    pub score: u64, // sum of the unmarked numbers times the winning number
+}
 
-    let mut draw_iter = draw.iter();
-    // Pretty inefficient, could be improved significantly
-    'outer: while let Some(n) = draw_iter.next() {
-        let mut v: Vec<Board> = Vec::with_capacity(losing_boards.len());
-        for mut b in losing_boards.into_iter() {
-            mark_num(&mut b, *n);
+impl Bingo {
+    pub fn new(draw: Draw, boards: Vec<Board>) -> Self {
+        Bingo {draw, boards, diagonals: false}
+    }
 
-            if !bingo(&b) {
-                v.push(b);
-            }
-        }
-        losing_boards = v;
-        if losing_boards.len() == 1 {
-            break 'outer; // found losing board
-        }
+    /// Lets complete diagonals of square boards win too
+    pub fn with_diagonals(mut self, diagonals: bool) -> Self {
+        self.diagonals = diagonals;
+        self
     }
 
-    let mut b = losing_boards.pop().unwrap();
-    let mut winning_draw = 0;
-    for d in draw_iter {
-        mark_num(&mut b, *d);
-        if bingo(&b) {
-            winning_draw = *d;
-            break;
+    /// Plays the whole draw and returns every board that wins, in the order they win.
+    /// Boards winning on the same number are ordered by index.
+    pub fn wins(&self) -> Vec<Win> {
+        // the cells of every number, such that a draw only visits the cells it marks
+        let mut cells: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
+        for (b, board) in self.boards.iter().enumerate() {
+            for (i, &n) in board.numbers.iter().enumerate() {
+                cells.entry(n).or_default().push((b, i));
+            }
         }
-    }
-    let score = compute_score(&b, winning_draw);
-    String::from(format!("{}", score))
-}
+        let mut marked: Vec<Vec<bool>> = self.boards.iter().map(|board| vec![false; board.numbers.len()]).collect();
+        // number of cells left on every line of every board
+        let mut left: Vec<Vec<usize>> = self.boards.iter().map(Board::line_lengths).collect();
+        let mut unmarked: Vec<u64> = self.boards.iter().map(|board| board.numbers.iter().map(|&n| n as u64).sum()).collect();
+        let mut won = vec![false; self.boards.len()];
 
-fn compute_score(board: &Board, draw: u32) -> u32 {
-    let mut score = 0;
-    for r in 0..board.len() {
-        for c in 0..board[0].len() {
-            if !board[r][c].1 { score += board[r][c].0; }
+        let mut wins = Vec::new();
+        for (turn, &number) in self.draw.iter().enumerate() {
+            for &(b, i) in cells.get(&number).into_iter().flatten() {
+                if won[b] || marked[b][i] {continue;}
+                marked[b][i] = true;
+                unmarked[b] -= number as u64;
+                let mut complete = false;
+                for line in self.boards[b].lines(i, self.diagonals) {
+                    left[b][line] -= 1;
+                    complete |= left[b][line] == 0;
+                }
+                if complete {
+                    won[b] = true;
+                    wins.push(Win {board: b, turn, number, score: unmarked[b] * number as u64});
+                }
+            }
         }
+        wins
     }
-    score * draw 
 }
 
-fn bingo(board: &Board) -> bool {
-    let c = marked_column(board, 0) || marked_column(board, 1) || marked_column(board, 2) || marked_column(board, 3) || marked_column(board, 4);
-    let r = marked_row(board, 0) || marked_row(board, 1) || marked_row(board, 2) || marked_row(board, 3) || marked_row(board, 4);
-    c || r
-}
-
-fn mark_num(board: &mut Board, num: u32) {
-    for i in 0..board.len() {
-        for j in 0..board[0].len() {
-            if board[i][j].0 == num { board[i][j].1 = true };
+impl fmt::Display for Bingo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let draw: Vec<String> = self.draw.iter().map(|n| n.to_string()).collect();
+        writeln!(f, "{}", draw.join(","))?;
+        for board in &self.boards {
+            writeln!(f)?;
+            for row in board.numbers.chunks(board.width) {
+                let row: Vec<String> = row.iter().map(|n| format!("{:>2}", n)).collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
         }
+        Ok(())
     }
 }
 
-fn marked_column(board: &Board, col: usize) -> bool {
-    for i in 0..board.len() {
-        if !board[i][col].1 {
-            return false;
-        }
-    }
-    return true;
+fn find_winning_board(input: &str) -> Result<String, ParseError> {
+    let wins = parsing::parse(input)?.wins();
+    let first = wins.first().ok_or_else(|| ParseError::new("no board wins"))?;
+    Ok(format!("{}", first.score))
 }
 
-fn marked_row(board: &Board, row: usize) -> bool {
-    for i in 0..board[0].len() {
-        if !board[row][i].1 {
-            return false;
-        }
-    }
-    return true;
+fn find_losing_board(input: &str) -> Result<String, ParseError> {
+    let wins = parsing::parse(input)?.wins();
+    let last = wins.last().ok_or_else(|| ParseError::new("no board wins"))?;
+    Ok(format!("{}", last.score))
 }
 
+/// The `k`-th board to win, counting from 1
+pub fn winner(input: &str, k: usize, diagonals: bool) -> Result<String, ParseError> {
+    let wins = parsing::parse(input)?.with_diagonals(diagonals).wins();
+    let win = k.checked_sub(1).and_then(|i| wins.get(i))
+        .ok_or_else(|| ParseError::new(format!("there is no winner {}, {} boards win", k, wins.len())))?;
+    Ok(format!("board {} wins on draw {} (number {}) with score {}", win.board + 1, win.turn + 1, win.number, win.score))
+}
 
 fn get_test_input() -> String {
     let mut s = String::from("7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1\n");
@@ -144,57 +181,112 @@ fn get_test_input() -> String {
 }
 
 pub mod parsing {
-    use super::{Board, Draw};
-
-    fn parse_draw(draw: &str) -> Draw {
-        let nums = draw.split(',');
-        let mut v: Vec<u32> = Vec::new();
-        for s in nums {
-            let n: u32 = s.parse().unwrap();
-            v.push(n);
+    use super::{Bingo, Board};
+    use crate::utils::{parse, ParseError};
+
+    /// A board of any size, every row needs to have as many numbers as the first
+    fn parse_board(board: &str) -> Result<Board, ParseError> {
+        let rows: Vec<Vec<u32>> = parse::lines(board, parse::ints)?;
+        if rows[0].is_empty() {
+            return Err(ParseError::new("board row has no numbers").offset(1));
         }
-        v
+        if let Some(i) = rows.iter().position(|row| row.len() != rows[0].len()) {
+            let e = format!("board row has {} numbers, expected {} like the first row", rows[i].len(), rows[0].len());
+            return Err(ParseError::new(e).offset(i + 1));
+        }
+        Ok(Board::new(rows))
     }
 
-    fn parse_row(row: &str) -> Vec<(u32, bool)> {
-        let nums = row.split_whitespace();
-        let mut v = Vec::new();
-        for n in nums {
-            v.push((n.parse().unwrap(), false));
+    pub fn parse(input: &str) -> Result<Bingo, ParseError> {
+        // the draw and the boards are separated by empty lines
+        let mut sections = parse::sections(input).into_iter();
+
+        let draw = sections.next().ok_or_else(|| ParseError::new("empty input"))?;
+        let draw = draw.parse(parse::ints)?;
+
+        let boards = sections.map(|b| b.parse(parse_board)).collect::<Result<Vec<Board>, _>>()?;
+        if boards.is_empty() {
+            return Err(ParseError::new("no boards"));
         }
-        v
+        Ok(Bingo::new(draw, boards))
     }
 
-    fn parse_board(board: &str) -> Vec<Vec<(u32, bool)>> {
-        let rows: Vec<&str>  = board.split('\n').collect();
-        let mut board = Vec::new();
-        for r in rows {
-            let row = parse_row(r);
-            board.push(row);
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::input::Input;
+        use proptest::prelude::*;
+
+        fn bingo() -> impl Strategy<Value = Bingo> {
+            let board = (1..7usize, 1..7usize).prop_flat_map(|(width, height)| {
+                prop::collection::vec(prop::collection::vec(0..100u32, width), height).prop_map(Board::new)
+            });
+            (prop::collection::vec(0..100u32, 1..30), prop::collection::vec(board, 1..5))
+                .prop_map(|(draw, boards)| Bingo::new(draw, boards))
+        }
+
+        #[test]
+        fn test_error_position() {
+            let input = crate::days::day4::get_test_input().replacen("14 21 16 12  6", "14 21 1x 12  6", 1);
+            let e = parse(&input).unwrap_err();
+            assert_eq!((e.line(), e.col()), (Some(13), Some(7)));
+
+            let e = parse("1,2\n\n1 2 3\n4 5 6\n7 8\n").unwrap_err();
+            assert_eq!(e.line(), Some(5));
+            let e = parse("1,2\n\n,\n").unwrap_err();
+            assert_eq!(e.line(), Some(3));
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert!(parse("7,4\n").is_err());
+            assert!(parse("7,4\n\n1 2\n3 x\n").is_err());
+        }
+
+        proptest! {
+            #[test]
+            fn test_roundtrip(bingo in bingo()) {
+                prop_assert_eq!(parse(&bingo.to_string()), Ok(bingo));
+            }
+
+            #[test]
+            fn test_crlf(bingo in bingo()) {
+                let windows = format!("\u{feff}{}\r\n\r\n", bingo.to_string().replace('\n', " \r\n"));
+                prop_assert_eq!(parse(Input::new(&windows).as_str()), Ok(bingo));
+            }
+
+            #[test]
+            fn test_garbage(input in "[0-9,]{0,10}\n(\n([ ,0-9]{0,15}\n){0,6}){0,3}") {
+                let _ = parse(&input);
+            }
         }
-        board
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn parse(input: &str) -> (Draw, Vec<Board>) {
-        let mut lines = input.lines();
-
-        let draw = lines.next().unwrap();
-        let draw = parse_draw(draw); 
-        lines.next();
-
-        let mut boards: Vec<Board> = Vec::new();
-        let boards_s: Vec<&str> = lines.collect();
-
-        for i in 0..(boards_s.len() / 6) {
-            let mut s = String::new();
-            s.push_str(format!("{}\n", boards_s[i*6]).as_str());
-            s.push_str(format!("{}\n", boards_s[i*6+1]).as_str());
-            s.push_str(format!("{}\n", boards_s[i*6+2]).as_str());
-            s.push_str(format!("{}\n", boards_s[i*6+3]).as_str());
-            s.push_str(format!("{}", boards_s[i*6+4]).as_str());
-            boards.push(parse_board(&s));
-        };
-        (draw, boards)
+    #[test]
+    fn test_example() {
+        assert_eq!(find_winning_board(&get_test_input()), Ok(String::from("4512")));
+        assert_eq!(find_losing_board(&get_test_input()), Ok(String::from("1924")));
+        let wins = parsing::parse(&get_test_input()).unwrap().wins();
+        assert_eq!(wins.iter().map(|win| (win.board, win.number)).collect::<Vec<_>>(), vec![(2, 24), (0, 16), (1, 13)]);
+        assert_eq!(winner(&get_test_input(), 2, false), Ok(String::from("board 1 wins on draw 14 (number 16) with score 2192")));
+        assert!(winner(&get_test_input(), 4, false).is_err());
     }
 
+    #[test]
+    fn test_shapes() {
+        let diagonal = "1,5,9,3\n\n1 2 3\n4 5 6\n7 8 9\n\n9 5\n1 3\n";
+        assert_eq!(winner(diagonal, 1, false), Ok(String::from("board 2 wins on draw 3 (number 9) with score 27")));
+        assert_eq!(winner(diagonal, 1, true), Ok(String::from("board 2 wins on draw 2 (number 5) with score 60")));
+        assert_eq!(winner(diagonal, 2, true), Ok(String::from("board 1 wins on draw 3 (number 9) with score 270")));
+
+        // diagonals do not exist on boards which are not square
+        let wide = "1,5,4\n\n1 2 3\n4 5 6\n";
+        assert_eq!(winner(wide, 1, true), Ok(String::from("board 1 wins on draw 3 (number 4) with score 44")));
+        assert_eq!(find_winning_board("4\n\n1 2\n"), Err(ParseError::new("no board wins")));
+    }
 }
@@ -1,4 +1,9 @@
 use crate::AOCDay;
+use crate::utils::ParseError;
+use crate::utils::geom::Point2;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 
 /*
  * Template for a implementing a day
@@ -6,104 +11,423 @@ use crate::AOCDay;
 
 pub struct Day5();
 
-type Line = ((u32,u32), (u32,u32));
+/// Line of hydrothermal vents between two points
+#[derive(Debug, PartialEq, Eq)]
+pub struct Line(Point2<u32>, Point2<u32>);
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Line(start, end) = self;
+        write!(f, "{},{} -> {},{}", start.x, start.y, end.x, end.y)
+    }
+}
+
+impl Line {
+    /// Whether the line is horizontal, vertical or (if `diagonal`) at 45 degrees
+    fn is_straight(&self, diagonal: bool) -> bool {
+        let Line(start, end) = self;
+        start.x == end.x || start.y == end.y || (diagonal && start.x.abs_diff(end.x) == start.y.abs_diff(end.y))
+    }
+
+    fn endpoints(&self) -> (Point2<i64>, Point2<i64>) {
+        (self.0.map(i64::from), self.1.map(i64::from))
+    }
+}
 
 impl AOCDay for Day5 {
-    fn part1(&self, input: &str) -> Option<String> { Some(number_of_overlapping(input, false)) }
-    fn part2(&self, input: &str) -> Option<String> { Some(number_of_overlapping(input, true)) }
+    fn part1(&self, input: &str) -> Result<String, ParseError> { number_of_overlapping(input, Angles::Straight) }
+    fn part2(&self, input: &str) -> Result<String, ParseError> { number_of_overlapping(input, Angles::Diagonal) }
     fn get_num(&self) -> u32 { 5 }
 }
 
 pub fn get() -> Day5 {Day5()}
 
-fn number_of_overlapping(input: &str, diagonal: bool) -> String {
-    let lines = parsing::parse(&input);
-    let vents = grid_with_vents(&lines, diagonal);
+/// The lines which are taken into account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Angles {
+    Straight, // horizontal and vertical lines (part 1)
+    Diagonal, // and lines at 45 degrees (part 2)
+    Any,
+}
+
+impl Angles {
+    fn includes(&self, line: &Line) -> bool {
+        match self {
+            Angles::Straight => line.is_straight(false),
+            Angles::Diagonal => line.is_straight(true),
+            Angles::Any => true,
+        }
+    }
+}
+
+fn number_of_overlapping(input: &str, angles: Angles) -> Result<String, ParseError> {
+    let lines = parsing::parse(input)?;
+    Ok(format!("{}", VentMap::new(&lines, angles).overlaps()))
+}
+
+/// Directions of the lines which are merged per row, column or diagonal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Horizontal,
+    Vertical,
+    Diagonal, // x and y grow together
+    AntiDiagonal,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [Direction::Horizontal, Direction::Vertical, Direction::Diagonal, Direction::AntiDiagonal];
 
-    let mut overlapped = 0;
-    for r in &vents {
-        for n in r {
-            if *n > 1 {
-                overlapped+=1;
+    /// The key of the line of this direction through `p` and the position of `p` on it
+    fn locate(self, p: Point2<i64>) -> (i64, i64) {
+        match self {
+            Direction::Horizontal => (p.y, p.x),
+            Direction::Vertical => (p.x, p.y),
+            Direction::Diagonal => (p.x - p.y, p.x),
+            Direction::AntiDiagonal => (p.x + p.y, p.x),
+        }
+    }
+
+    /// The point at position `t` on the line of this direction with `key`
+    fn point(self, key: i64, t: i64) -> Point2<i64> {
+        match self {
+            Direction::Horizontal => Point2::new(t, key),
+            Direction::Vertical => Point2::new(key, t),
+            Direction::Diagonal => Point2::new(t, t - key),
+            Direction::AntiDiagonal => Point2::new(t, key - t),
+        }
+    }
+}
+
+/// Positions `from..=to` on a line which are covered by `count` vent lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cover {
+    from: i64,
+    to: i64,
+    count: u32,
+}
+
+/// The rasterized lines as pieces along a direction, from one point to another
+fn pieces(line: &Line) -> Vec<(Direction, Point2<i64>, Point2<i64>)> {
+    let (start, end) = line.endpoints();
+    let diff = end - start;
+    let direction = match (diff.x, diff.y) {
+        (_, 0) => Direction::Horizontal,
+        (0, _) => Direction::Vertical,
+        (dx, dy) if dx == dy => Direction::Diagonal,
+        (dx, dy) if dx == -dy => Direction::AntiDiagonal,
+        _ => {
+            // the runs of the rasterized line along its longer axis
+            let direction = if diff.x.abs() > diff.y.abs() { Direction::Horizontal } else { Direction::Vertical };
+            let mut runs: Vec<(Direction, Point2<i64>, Point2<i64>)> = Vec::new();
+            for p in Bresenham::new(start, end) {
+                match runs.last_mut() {
+                    Some((_, first, last)) if direction.locate(*first).0 == direction.locate(p).0 => *last = p,
+                    _ => runs.push((direction, p, p)),
+                }
             }
+            return runs;
         }
+    };
+    vec![(direction, start, end)]
+}
+
+/// The rasterized lines, merged into the positions covered by the same number of lines on every row,
+/// column and diagonal. This takes memory and time proportional to the number of lines rather than
+/// their length, except for lines at other angles than multiples of 45 degrees, which are split into
+/// one piece per row or column.
+pub struct VentMap {
+    covers: [BTreeMap<i64, Vec<Cover>>; 4], // by direction and key
+}
+
+/// Largest width and height of a map to be rendered
+const MAX_RENDER: i64 = 200;
+
+/// Why the vents can not be counted or shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VentsError {
+    Input(ParseError),
+    TooLarge(Point2<i64>), // size of a map which exceeds the heatmap
+}
+
+impl From<ParseError> for VentsError {
+    fn from(e: ParseError) -> Self {
+        VentsError::Input(e)
     }
-    String::from(format!("{}", overlapped))
 }
 
-fn grid_with_vents(lines: &Vec<Line>, diagonal: bool) -> Vec<Vec<u32>> {
-    let max_coord = max_coord(&lines);
+impl fmt::Display for VentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VentsError::Input(e) => write!(f, "{}", e),
+            VentsError::TooLarge(size) => write!(f, "the map is {}x{}, the heatmap is limited to {}x{}", size.x, size.y, MAX_RENDER, MAX_RENDER),
+        }
+    }
+}
 
-    let mut grid: Vec<Vec<u32>> = Vec::with_capacity((max_coord+1) as usize); // construct grid of zeroes
-    for _ in 0..max_coord+1 {
-        let mut v = Vec::with_capacity((max_coord+1) as usize);
-        for _ in 0..(max_coord as usize)+1 {
-            v.push(0);
+impl VentMap {
+    /// Rasterizes the lines with Bresenham's algorithm, which covers exactly the points of lines at
+    /// multiples of 45 degrees, and for other slopes the points closest to the line.
+    pub fn new(lines: &[Line], angles: Angles) -> Self {
+        let mut ranges: [BTreeMap<i64, Vec<(i64, i64)>>; 4] = Default::default();
+        for (direction, start, end) in lines.iter().filter(|l| angles.includes(l)).flat_map(pieces) {
+            let ((key, a), (_, b)) = (direction.locate(start), direction.locate(end));
+            ranges[direction as usize].entry(key).or_default().push((a.min(b), a.max(b)));
         }
-        grid.push(v);
+        let covers = ranges.map(|lines| lines.into_iter().map(|(key, ranges)| (key, merge(&ranges))).collect());
+        VentMap {covers}
     }
 
-    for l in lines { // mark the lines
-        if diagonal {
-            mark_line(&mut grid, &l);
-        }else {
-            mark_line_horizontal_vertical(&mut grid, &l);
+    /// Number of lines of `direction` covering `p`
+    fn count_along(&self, direction: Direction, p: Point2<i64>) -> u32 {
+        let (key, t) = direction.locate(p);
+        let Some(covers) = self.covers[direction as usize].get(&key) else { return 0 };
+        let i = covers.partition_point(|c| c.to < t);
+        covers.get(i).filter(|c| c.from <= t).map_or(0, |c| c.count)
+    }
+
+    /// Number of lines covering `p`
+    fn count(&self, p: Point2<i64>) -> u32 {
+        Direction::ALL.iter().map(|&direction| self.count_along(direction, p)).sum()
+    }
+
+    fn covers(&self, direction: Direction) -> impl Iterator<Item = (i64, &Cover)> {
+        self.covers[direction as usize].iter().flat_map(|(&key, covers)| covers.iter().map(move |c| (key, c)))
+    }
+
+    /// Number of points covered by at least two lines
+    pub fn overlaps(&self) -> usize {
+        // points covered twice along one direction, counted once per direction
+        let mut overlaps: usize = Direction::ALL.iter().flat_map(|&d| self.covers(d))
+            .filter(|(_, c)| c.count > 1)
+            .map(|(_, c)| (c.to - c.from + 1) as usize)
+            .sum();
+        // points covered along several directions, found from the first two of these
+        for (i, &first) in Direction::ALL.iter().enumerate() {
+            for &second in Direction::ALL[i + 1..].iter() {
+                for (key, cover) in self.covers(first) {
+                    // the key of `second` at position t of the cover is `start + slope * (t - from)`
+                    let start = second.locate(first.point(key, cover.from)).0;
+                    let slope = second.locate(first.point(key, cover.from + 1)).0 - start;
+                    let end = start + slope * (cover.to - cover.from);
+                    for &other in self.covers[second as usize].range(start.min(end)..=start.max(end)).map(|(k, _)| k) {
+                        if (other - start) % slope != 0 {
+                            continue;
+                        }
+                        let p = first.point(key, cover.from + (other - start) / slope);
+                        let counts = Direction::ALL.map(|d| self.count_along(d, p));
+                        let mut covering = Direction::ALL.iter().filter(|d| counts[**d as usize] > 0);
+                        if covering.next() == Some(&first) && covering.next() == Some(&second) {
+                            overlaps += 1;
+                            overlaps -= counts.iter().filter(|&&n| n > 1).count();
+                        }
+                    }
+                }
+            }
         }
+        overlaps
+    }
+
+    /// The number of lines at every point of the bounding box, `.` where there is none
+    /// and `+` where there are more than 9.
+    pub fn render(&self) -> Result<String, VentsError> {
+        let ends = Direction::ALL.iter()
+            .flat_map(|&d| self.covers(d).flat_map(move |(key, c)| [d.point(key, c.from), d.point(key, c.to)]));
+        let Some((min, max)) = bounds(ends) else { return Ok(String::new()) };
+        check_render_size(min, max)?;
+        let rows: Vec<String> = (min.y..=max.y).map(|y| (min.x..=max.x).map(|x| {
+            match self.count(Point2::new(x, y)) {
+                0 => '.',
+                n if n > 9 => '+',
+                n => char::from_digit(n, 10).unwrap(),
+            }
+        }).collect()).collect();
+        Ok(rows.join("\n"))
     }
-    grid
 }
 
-fn mark_line_horizontal_vertical(grid: &mut Vec<Vec<u32>>, line: &Line) {
-    let ((x1,y1),(x2,y2)) = line;
-    let yd = *y2 as i32 - *y1 as i32;
-    let xd = *x2 as i32 - *x1 as i32;
-    if i32::abs(yd) > 0 && i32::abs(xd) == 0 {
-        // vertical line (so x1==x2)
-        let yd = yd / i32::abs(yd);
-        let mut i = *y1 as i32;
-        loop {
-            grid[i as usize][*x1 as usize] += 1;
-            if i == *y2 as i32 {break;} 
-            i += yd;
+/// The ranges of positions covered by the same number of the given ranges, by sweeping over their ends
+fn merge(ranges: &[(i64, i64)]) -> Vec<Cover> {
+    let mut events: Vec<(i64, i32)> = ranges.iter().flat_map(|&(from, to)| [(from, 1), (to + 1, -1)]).collect();
+    events.sort_unstable();
+    let mut covers = Vec::new();
+    let mut count = 0;
+    for (i, &(t, change)) in events.iter().enumerate() {
+        count += change;
+        match events.get(i + 1) {
+            Some(&(next, _)) if next > t && count > 0 => covers.push(Cover {from: t, to: next - 1, count: count as u32}),
+            _ => {}
         }
-    }else if i32::abs(xd) > 0 && i32::abs(yd) == 0 {
-        // horizontal line
-        let xd = xd / i32::abs(xd);
-        let mut i = *x1 as i32;
-        loop {
-            grid[*y1 as usize][i as usize] += 1;
-            if i == *x2 as i32 {break;} 
-            i += xd;
+    }
+    covers
+}
+
+/// The smallest and largest coordinates of the points
+fn bounds(mut points: impl Iterator<Item = Point2<i64>>) -> Option<(Point2<i64>, Point2<i64>)> {
+    let first = points.next()?;
+    Some(points.fold((first, first), |(min, max), p| {
+        (Point2::new(min.x.min(p.x), min.y.min(p.y)), Point2::new(max.x.max(p.x), max.y.max(p.y)))
+    }))
+}
+
+fn check_render_size(min: Point2<i64>, max: Point2<i64>) -> Result<(), VentsError> {
+    let size = max - min + Point2::new(1, 1);
+    if size.x > MAX_RENDER || size.y > MAX_RENDER {
+        return Err(VentsError::TooLarge(size));
+    }
+    Ok(())
+}
+
+/// The points of a line from `start` to `end` with Bresenham's algorithm, including both ends
+struct Bresenham {
+    p: Option<Point2<i64>>,
+    end: Point2<i64>,
+    delta: Point2<i64>, // (|dx|, -|dy|)
+    step: Point2<i64>,
+    error: i64,
+}
+
+impl Bresenham {
+    fn new(start: Point2<i64>, end: Point2<i64>) -> Self {
+        let diff = end - start;
+        let delta = Point2::new(diff.x.abs(), -diff.y.abs());
+        Bresenham {p: Some(start), end, delta, step: diff.map(i64::signum), error: delta.x + delta.y}
+    }
+}
+
+impl Iterator for Bresenham {
+    type Item = Point2<i64>;
+
+    fn next(&mut self) -> Option<Point2<i64>> {
+        let p = self.p?;
+        self.p = if p == self.end {
+            None
+        } else {
+            let mut next = p;
+            let e2 = 2 * self.error;
+            if e2 >= self.delta.y {
+                self.error += self.delta.y;
+                next.x += self.step.x;
+            }
+            if e2 <= self.delta.x {
+                self.error += self.delta.x;
+                next.y += self.step.y;
+            }
+            Some(next)
+        };
+        Some(p)
+    }
+}
+
+/// Number of integer points lying exactly on at least two lines, by intersecting every pair of lines
+/// geometrically. Unlike the rasterized lines, lines of other slopes than multiples of 45 degrees only
+/// cover the integer points they pass through.
+pub fn exact_overlaps(lines: &[Line], angles: Angles) -> usize {
+    let segments: Vec<Segment> = lines.iter().filter(|l| angles.includes(l)).map(Segment::new).collect();
+    let mut points = HashSet::new();
+    for (i, a) in segments.iter().enumerate() {
+        for b in segments[i + 1..].iter() {
+            a.intersect(b, &mut points);
         }
     }
+    points.len()
+}
+
+/// The integer points `start + k * step` for `k` in `0..=len`, where `step` is the shortest integer
+/// vector along the line, so these are all integer points on the line.
+struct Segment {
+    start: Point2<i128>,
+    step: Point2<i128>,
+    len: i128,
+}
+
+fn cross(a: Point2<i128>, b: Point2<i128>) -> i128 {
+    a.x * b.y - a.y * b.x
+}
+
+fn dot(a: Point2<i128>, b: Point2<i128>) -> i128 {
+    a.x * b.x + a.y * b.y
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
 }
 
-fn mark_line(grid: &mut Vec<Vec<u32>>, line: &Line) {
-    let ((x1,y1),(x2,y2)) = line;
-    let yd = *y2 as i32 - *y1 as i32;
-    let xd = *x2 as i32 - *x1 as i32;
+impl Segment {
+    fn new(line: &Line) -> Self {
+        let (start, end) = (line.0.map(i128::from), line.1.map(i128::from));
+        let diff = end - start;
+        let len = gcd(diff.x, diff.y);
+        let step = if len == 0 { diff } else { diff.map(|c| c / len) };
+        Segment {start, step, len}
+    }
 
-    let xd_n = if xd != 0 {xd / i32::abs(xd)} else {0};
-    let yd_n = if yd != 0 {yd / i32::abs(yd)} else {0};
+    fn point(&self, k: i128) -> Point2<i64> {
+        (self.start + self.step * k).map(|c| c as i64)
+    }
 
-    let (mut x,mut y): (i32, i32) = (*x1 as i32, *y1 as i32);
-    loop {
-        grid[y as usize][x as usize] += 1;
-        if y as u32 == *y2 && x as u32 == *x2 {break;}
-        x += xd_n;
-        y += yd_n;
+    /// The `k` with `start + k * step == p`, if `p` is one of the points of the segment
+    fn index_of(&self, p: Point2<i128>) -> Option<i128> {
+        let offset = p - self.start;
+        if self.len == 0 {
+            return (offset == Point2::default()).then_some(0);
+        }
+        let k = dot(offset, self.step) / dot(self.step, self.step);
+        (cross(offset, self.step) == 0 && (0..=self.len).contains(&k)).then_some(k)
+    }
+
+    /// Adds the integer points on both segments to `points`
+    fn intersect(&self, other: &Segment, points: &mut HashSet<Point2<i64>>) {
+        if other.len == 0 {
+            if self.index_of(other.start).is_some() { points.insert(other.point(0)); }
+            return;
+        }
+        if self.len == 0 {
+            return other.intersect(self, points);
+        }
+        let offset = other.start - self.start;
+        let denominator = cross(self.step, other.step);
+        if denominator == 0 {
+            // parallel, the steps are equal or opposite if the segments are collinear
+            let Some(first) = self.index_of(other.start).or_else(|| {
+                (cross(offset, self.step) == 0).then(|| dot(offset, self.step) / dot(self.step, self.step))
+            }) else { return };
+            let last = first + other.len * dot(self.step, other.step).signum();
+            for k in first.min(last).max(0)..=first.max(last).min(self.len) {
+                points.insert(self.point(k));
+            }
+        } else {
+            // solve start + k * step == other.start + m * other.step
+            let (k, m) = (cross(offset, other.step), cross(offset, self.step));
+            if k % denominator == 0 && m % denominator == 0 {
+                let (k, m) = (k / denominator, m / denominator);
+                if (0..=self.len).contains(&k) && (0..=other.len).contains(&m) {
+                    points.insert(self.point(k));
+                }
+            }
+        }
     }
 }
 
-fn max_coord(lines: &Vec<Line>) -> u32 {
-    let mut max = 0;
-    for l in lines {
-        max = u32::max(max, l.0.0);
-        max = u32::max(max, l.0.1);
-        max = u32::max(max, l.1.0);
-        max = u32::max(max, l.1.1);
+/// The overlaps of the vent lines of the given angles, counted exactly or on the rasterized lines,
+/// optionally followed by the heatmap of the rasterized lines
+pub fn vents(input: &str, angles: Angles, exact: bool, heatmap: bool) -> Result<String, VentsError> {
+    let lines = parsing::parse(input)?;
+    if heatmap {
+        // the lines are within the bounds of their ends, check those before rasterizing
+        let ends = lines.iter().filter(|l| angles.includes(l)).flat_map(|l| [l.endpoints().0, l.endpoints().1]);
+        if let Some((min, max)) = bounds(ends) {
+            check_render_size(min, max)?;
+        }
+    }
+    let map = (heatmap || !exact).then(|| VentMap::new(&lines, angles));
+    let overlaps = match &map {
+        Some(map) if !exact => map.overlaps(),
+        _ => exact_overlaps(&lines, angles),
+    };
+    match map {
+        Some(map) if heatmap => Ok(format!("{}\n{}", map.render()?, overlaps)),
+        _ => Ok(format!("{}", overlaps)),
     }
-    max 
 }
 
 fn test_input() -> String {
@@ -111,28 +435,138 @@ fn test_input() -> String {
 }
 
 mod parsing {
-    use nom;
     use nom::IResult;
     use nom::bytes::complete::tag;
-    use nom::character::complete::digit0;
+    use nom::character::complete;
+    use nom::sequence::separated_pair;
+
+    use super::{Line, Point2};
+    use crate::utils::{parse, ParseError};
+
+    fn parse_line(input: &str) -> IResult<&str, Line> {
+        let coord1 = separated_pair(complete::u32, tag(","), complete::u32);
+        let coord2 = separated_pair(complete::u32, tag(","), complete::u32);
+        let mut coords = separated_pair(coord1, tag(" -> "), coord2);
+        coords(input).map(|(rem, ((x1, y1), (x2, y2)))| (rem, Line(Point2::new(x1, y1), Point2::new(x2, y2))))
+    }
 
-    use super::Line;
+    pub fn parse(input: &str) -> Result<Vec<Line>, ParseError> {
+        parse::lines(input, |l| parse::complete(l, parse_line))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::utils::Lines;
+        use proptest::prelude::*;
+
+        #[test]
+        fn test_invalid() {
+            assert!(parse("0,9 -> 5\n").is_err());
+            assert!(parse("0,9 => 5,9\n").is_err());
+        }
+
+        proptest! {
+            #[test]
+            fn test_roundtrip(coords in prop::collection::vec(any::<((u32, u32), (u32, u32))>(), 0..30)) {
+                let lines: Vec<Line> = coords.into_iter().map(|((x1, y1), (x2, y2))| Line(Point2::new(x1, y1), Point2::new(x2, y2))).collect();
+                prop_assert_eq!(parse(&Lines(&lines).to_string()), Ok(lines));
+            }
+
+            #[test]
+            fn test_garbage(input in "([0-9]{0,3},?[0-9]{0,3}( -> )?[0-9]{0,3},?[0-9]{0,3}\n){0,4}") {
+                let _ = parse(&input);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(number_of_overlapping(&test_input(), Angles::Straight), Ok(String::from("5")));
+        assert_eq!(number_of_overlapping(&test_input(), Angles::Diagonal), Ok(String::from("12")));
+        let heatmap = vents(&test_input(), Angles::Diagonal, false, true).unwrap();
+        assert!(heatmap.starts_with("1.1....11.\n.111...2..\n..2.1.111.\n...1.2.2..\n.112313211\n"));
+        assert!(heatmap.ends_with("222111....\n12"));
+    }
+
+    #[test]
+    fn test_any_angle() {
+        let points: Vec<_> = Bresenham::new(Point2::new(0, 0), Point2::new(5, -2)).collect();
+        let expected = [(0, 0), (1, 0), (2, -1), (3, -1), (4, -2), (5, -2)].map(|(x, y)| Point2::new(x, y));
+        assert_eq!(points, expected);
 
-    fn parse_line(input: &str) -> Line {
-        let coord1 = nom::sequence::separated_pair(digit0, tag(","), digit0);
-        let coord2 = nom::sequence::separated_pair(digit0, tag(","), digit0);
-        let mut coords = nom::sequence::separated_pair(coord1, tag(" -> "), coord2);
-        let res: IResult<&str, ((&str,&str),(&str,&str))> = coords(input);
-        res.map(|(_, ((x1,y1),(x2,y2)))| ((x1.parse().unwrap(),y1.parse().unwrap()),(x2.parse().unwrap(),y2.parse().unwrap()))).unwrap()
+        // the steep line only passes through the integer points (0,0), (1,3) and (2,6)
+        let input = "0,0 -> 2,6\n0,1 -> 2,1\n2,6 -> 2,6\n1,0 -> 1,9";
+        assert_eq!(vents(input, Angles::Any, true, false), Ok(String::from("3")));
+        // rasterized it also covers (0,1), (1,2), (1,4) and (2,5)
+        assert_eq!(vents(input, Angles::Any, false, false), Ok(String::from("6")));
+        assert_eq!(vents(input, Angles::Straight, true, false), Ok(String::from("1")));
     }
-    
-    pub fn parse(input: &str) -> Vec<Line> {
-        let lines = input.lines();
-        let mut v = Vec::new();
-        for l in lines {
-            v.push(parse_line(l));
+
+    #[test]
+    fn test_large_coordinates() {
+        let input = "0,0 -> 4000000000,4000000000\n4000000000,0 -> 0,4000000000\n1,1 -> 5,5";
+        assert_eq!(vents(input, Angles::Diagonal, true, false), Ok(String::from("6")));
+        assert!(matches!(vents(input, Angles::Diagonal, false, true), Err(VentsError::TooLarge(_))));
+        assert_eq!(vents(input, Angles::Diagonal, false, false), Ok(String::from("6")));
+
+        // crossing both diagonals away from the overlapping part, and both ends of the horizontal line
+        let input = format!("{}\n0,7 -> 4000000000,7\n0,0 -> 0,4000000000\n4000000000,7 -> 4000000000,9", input);
+        for exact in [true, false] {
+            assert_eq!(vents(&input, Angles::Diagonal, exact, false), Ok(String::from("12")));
+            assert_eq!(vents(&input, Angles::Straight, exact, false), Ok(String::from("2")));
+        }
+    }
+
+    fn lines() -> impl Strategy<Value = Vec<Line>> {
+        let point = (0..12u32, 0..12u32).prop_map(|(x, y)| Point2::new(x, y));
+        prop::collection::vec((point.clone(), point), 0..12).prop_map(|l| l.into_iter().map(|(a, b)| Line(a, b)).collect())
+    }
+
+    /// Number of lines at every point, by rasterizing every line
+    fn rasterized(lines: &[Line], angles: Angles) -> HashMap<Point2<i64>, u32> {
+        let mut counts = HashMap::new();
+        for line in lines.iter().filter(|l| angles.includes(l)) {
+            for p in Bresenham::new(line.endpoints().0, line.endpoints().1) {
+                *counts.entry(p).or_insert(0) += 1;
+            }
         }
-        v
+        counts
     }
 
+    proptest! {
+        #[test]
+        fn test_merged(lines in lines()) {
+            for angles in [Angles::Straight, Angles::Diagonal, Angles::Any] {
+                let counts = rasterized(&lines, angles);
+                let map = VentMap::new(&lines, angles);
+                prop_assert_eq!(map.overlaps(), counts.values().filter(|&&n| n > 1).count());
+                for p in (-1..13).flat_map(|y| (-1..13).map(move |x| Point2::new(x, y))) {
+                    prop_assert_eq!(map.count(p), counts.get(&p).copied().unwrap_or(0));
+                }
+            }
+        }
+
+        #[test]
+        fn test_exact(lines in lines()) {
+            // both agree on lines at multiples of 45 degrees
+            for angles in [Angles::Straight, Angles::Diagonal] {
+                prop_assert_eq!(exact_overlaps(&lines, angles), VentMap::new(&lines, angles).overlaps());
+            }
+            // the integer points on a line are among its rasterized points
+            for line in lines.iter() {
+                let (start, end) = line.endpoints();
+                let rasterized: HashSet<Point2<i64>> = Bresenham::new(start, end).collect();
+                let segment = Segment::new(line);
+                prop_assert!((0..=segment.len).all(|k| rasterized.contains(&segment.point(k))));
+            }
+        }
+    }
 }
@@ -1,70 +1,222 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::linalg::{self, Matrix, Ring};
 
 /*
  * Template for a implementing a day
  */
 
-type Fishes = Vec<u64>;
-
 pub struct Day6();
 
 impl AOCDay for Day6 {
-    fn part1(&self, input: &str) -> Option<String> { Some(part1(input)) }
-    fn part2(&self, input: &str) -> Option<String> { Some(part2(input)) }
+    fn part1(&self, input: &str) -> Result<String, ParseError> { part1(input) }
+    fn part2(&self, input: &str) -> Result<String, ParseError> { part2(input) }
     fn get_num(&self) -> u32 { 6 }
 }
 
 pub fn get() -> Day6 {Day6()}
 
-fn part1(input: &str) -> String {
-    let mut fishes = parse(&input);
-    simulate_days(80, &mut fishes);
-    let count: u64 = fishes.iter().sum();
-    String::from(format!("{}", count))
+fn part1(input: &str) -> Result<String, ParseError> {
+    let count: u64 = population(input, Model::PUZZLE, 80)?.total();
+    Ok(format!("{}", count))
+}
+
+fn part2(input: &str) -> Result<String, ParseError> {
+    let count: u64 = population(input, Model::PUZZLE, 256)?.total();
+    Ok(format!("{}", count))
+}
+
+/// Life cycle of the lanternfish: after giving birth a fish waits `cycle` days until the next birth,
+/// newborns wait `delay` days longer before their first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Model {
+    cycle: usize,
+    delay: usize,
+}
+
+impl Model {
+    pub const PUZZLE: Model = Model {cycle: 7, delay: 2};
+
+    /// A model whose cycle lasts at least one day
+    pub fn new(cycle: usize, delay: usize) -> Result<Model, String> {
+        if cycle == 0 {
+            return Err(String::from("the cycle needs to last at least one day"));
+        }
+        Ok(Model {cycle, delay})
+    }
+
+    /// Number of distinct timer values, the timer of newborns is one less
+    fn timers(&self) -> usize {
+        self.cycle + self.delay
+    }
 }
 
-fn part2(input: &str) -> String {
-    let mut fishes = parse(&input);
-    simulate_days(256, &mut fishes);
-    let count: u64 = fishes.iter().sum();
-    String::from(format!("{}", count))
+/// The fishes per timer value, stored in a ring buffer which rotates by one slot per day:
+/// the fishes with timer 0 stay in their slot as the newborns and are added to the slot of the reset timer.
+#[derive(Debug, Clone)]
+pub struct Population<R> {
+    model: Model,
+    counts: Vec<R>,
+    start: usize, // slot of timer 0
+    total: R,
 }
 
-fn simulate_days(days: u64, fishes: &mut Fishes) {
+impl<R: Ring> Population<R> {
+    fn new(model: Model, ages: &[u8]) -> Self {
+        let counts: Vec<R> = fishes(ages, model.timers()).into_iter().map(R::from).collect();
+        Population {model, counts, start: 0, total: R::from(ages.len() as u64)}
+    }
+
+    /// Simulates one day in O(1)
+    pub fn advance(&mut self) {
+        let born = self.counts[self.start].clone();
+        self.start = (self.start + 1) % self.counts.len();
+        let reset = (self.start + self.model.cycle - 1) % self.counts.len();
+        self.counts[reset] = self.counts[reset].clone() + born.clone();
+        self.total = self.total.clone() + born;
+    }
+
+    pub fn total(&self) -> R {
+        self.total.clone()
+    }
+}
+
+/// The population after `days` days, simulated day by day
+pub fn population<R: Ring>(input: &str, model: Model, days: u64) -> Result<Population<R>, ParseError> {
+    let mut population = Population::new(model, &parse(input, model)?);
     for _ in 0..days {
-        simulate_day(fishes);
+        population.advance();
     }
+    Ok(population)
 }
 
-fn simulate_day(fishes: &mut Fishes) {
-    let fish_old = fishes.clone();
-    // update fishes
-    for i in 0..8 {
-        // older fish become younger
-        fishes[i] = fish_old[i+1]
+/// Number of fishes on every day from 0 to `days`
+pub fn series<R: Ring>(input: &str, model: Model, days: u64) -> Result<Vec<R>, ParseError> {
+    let mut population = Population::new(model, &parse(input, model)?);
+    let mut counts = vec![population.total()];
+    for _ in 0..days {
+        population.advance();
+        counts.push(population.total());
     }
-    fishes[8] = fish_old[0]; // new fish are born
-    fishes[6] += fish_old[0];
+    Ok(counts)
 }
 
-fn test_input() -> String {
-    String::from("3,4,3,1,2")
+/// Number of fishes after `days` days, with O(log days) matrix multiplications
+pub fn lanternfish<R: Ring>(input: &str, model: Model, days: u64) -> Result<R, ParseError> {
+    let fishes: Vec<R> = fishes(&parse(input, model)?, model.timers()).into_iter().map(R::from).collect();
+    let fishes = linalg::pow(&day_matrix(model), days).apply(&fishes);
+    Ok(fishes.into_iter().fold(R::from(0), |count, n| count + n))
 }
 
-fn parse(input: &str) -> Fishes {
-    let nums: Vec<&str> = input.trim_end().split(',').collect(); 
-    let mut ages: Vec<u64> = Vec::new();
-    for n in nums {
-        ages.push(n.parse().unwrap());
+/// Maps the fishes per timer value onto those of the next day:
+/// older fish become younger, fish with timer 0 reset to `cycle - 1` and give birth to a fish
+/// with the largest timer. Without delay both are the same timer.
+fn day_matrix<R: Ring>(model: Model) -> Matrix<R> {
+    let last = model.timers() - 1;
+    Matrix::from_fn(model.timers(), |timer, prev| {
+        let older = prev == timer + 1;
+        let (reset, born) = (prev == 0 && timer == model.cycle - 1, prev == 0 && timer == last);
+        R::from(older as u64 + reset as u64 + born as u64)
+    })
+}
+
+/// Naive reference: simulates every fish individually
+pub fn reference_part1(input: &str) -> Option<String> {
+    let mut fishes = parse(input, Model::PUZZLE).ok()?;
+    for _ in 0..80 {
+        let mut born = 0;
+        for f in fishes.iter_mut() {
+            if *f == 0 {
+                *f = 6;
+                born += 1;
+            } else {
+                *f -= 1;
+            }
+        }
+        fishes.extend(std::iter::repeat_n(8, born));
     }
+    Some(fishes.len().to_string())
+}
+
+fn test_input() -> String {
+    String::from("3,4,3,1,2")
+}
 
-    let mut fishes: Vec<u64> = Vec::new();
-    for _ in 0..9 {
-        fishes.push(0);
+/// Parses the internal timers of the fishes, which are below the number of timers of the model
+fn parse(input: &str, model: Model) -> Result<Vec<u8>, ParseError> {
+    let ages: Vec<u8> = parse::ints(input)?;
+    if let Some(age) = ages.iter().find(|&&age| age as usize >= model.timers()) {
+        return Err(ParseError::new(format!("invalid timer {}", age)));
     }
+    Ok(ages)
+}
 
+/// Counts the fishes per timer value
+fn fishes(ages: &[u8], timers: usize) -> Vec<u64> {
+    let mut fishes = vec![0; timers];
     for age in ages {
-        fishes[age as usize] += 1;
+        fishes[*age as usize] += 1;
     }
     fishes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Commas;
+    use crate::utils::bigint::BigUint;
+    use crate::utils::linalg::{ModInt, PRIME};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(lanternfish::<u64>(&test_input(), Model::PUZZLE, 18), Ok(26));
+        assert_eq!(part1(&test_input()), Ok(String::from("5934")));
+        assert_eq!(part2(&test_input()), Ok(String::from("26984457539")));
+        // far beyond 64 bits
+        let count: BigUint = lanternfish(&test_input(), Model::PUZZLE, 2000).unwrap();
+        assert!(count > BigUint::from(u64::MAX));
+        assert_eq!(population::<BigUint>(&test_input(), Model::PUZZLE, 2000).unwrap().total(), count);
+        assert_eq!(lanternfish::<BigUint>(&test_input(), Model::PUZZLE, 256).unwrap().to_string(), "26984457539");
+        let count: ModInt<PRIME> = lanternfish(&test_input(), Model::PUZZLE, 256).unwrap();
+        assert_eq!(count, ModInt::from(26984457539));
+    }
+
+    #[test]
+    fn test_models() {
+        assert_eq!(series::<u64>(&test_input(), Model::PUZZLE, 5), Ok(vec![5, 5, 6, 7, 9, 10]));
+        // every fish splits in two every day
+        assert_eq!(series::<u64>("0,0,0", Model {cycle: 1, delay: 0}, 4), Ok(vec![3, 6, 12, 24, 48]));
+        assert!(parse("2,3", Model {cycle: 2, delay: 1}).is_err());
+        assert!(Model::new(0, 1).is_err());
+        assert_eq!(Model::new(7, 2), Ok(Model::PUZZLE));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("3,9\n", Model::PUZZLE).is_err());
+        assert!(parse("3,a\n", Model::PUZZLE).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_buffer(cycle in 1..8usize, delay in 0..4usize, ages in prop::collection::vec(0..8u8, 1..10), days in 0..500u64) {
+            // the counts outgrow 64 bits after 64 days with a cycle of one day, so they are compared modulo a prime
+            let model = Model {cycle, delay};
+            let ages: Vec<u8> = ages.into_iter().map(|age| age % model.timers() as u8).collect();
+            let input = Commas(&ages).to_string();
+            let count: ModInt<PRIME> = population(&input, model, days).unwrap().total();
+            prop_assert_eq!(Ok(count), lanternfish(&input, model, days));
+        }
+
+        #[test]
+        fn test_roundtrip(ages in prop::collection::vec(0..=8u8, 1..50)) {
+            prop_assert_eq!(parse(&Commas(&ages).to_string(), Model::PUZZLE), Ok(ages));
+        }
+
+        #[test]
+        fn test_garbage(input in "[0-9,a-]{0,20}\n?") {
+            let _ = parse(&input, Model::PUZZLE);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
 
 // [1] https://en.wikipedia.org/wiki/Triangular_number
 // Tried a more functional approach this time.
@@ -6,47 +7,161 @@ use crate::AOCDay;
 pub struct Day7();
 
 impl AOCDay for Day7 {
-    fn part1(&self, input: &str) -> Option<String> { Some(String::from(format!("{}", cheapest_alignment_dist(input)))) }
-    fn part2(&self, input: &str) -> Option<String> { Some(String::from(format!("{}", cheapest_alignment_fuel(input)))) }
+    fn part1(&self, input: &str) -> Result<String, ParseError> { align(input, Cost::Linear).map(|a| a.cost.to_string()) }
+    fn part2(&self, input: &str) -> Result<String, ParseError> { align(input, Cost::Triangular).map(|a| a.cost.to_string()) }
     fn get_num(&self) -> u32 { 7 }
 }
 
 pub fn get() -> Day7 {Day7()}
 
-fn cheapest_alignment_dist(input: &str) -> u32{
-    let mut crabs = parsing(&input);
-    crabs.sort();
-    calculate_dist(crabs[crabs.len()/2], &crabs)
+/// Fuel a crab needs to move a distance, the fuel may not decrease and has to grow convexly
+#[derive(Debug, Clone, Copy)]
+pub enum Cost {
+    Linear, // one fuel per step (part 1)
+    Triangular, // one more fuel for every step than for the previous one (part 2)
+    Quadratic,
+    Custom(fn(u64) -> u64),
 }
 
-fn cheapest_alignment_fuel(input: &str) -> u32{
-    let crabs = parsing(&input);
-    let mean: u32 = crabs.iter().sum::<u32>() as u32 / crabs.len() as u32; // answer is around mean
-    ((mean-1)..).take(3).map(|i| calculate_fuel(i, &crabs)).min().unwrap()
+impl Cost {
+    /// Fuel for distance `d`, which fits 64 bits for distances below 2^32
+    fn fuel(&self, d: u64) -> u64 {
+        match self {
+            Cost::Linear => d,
+            Cost::Triangular => d * (d + 1) / 2, // utilize [1]
+            Cost::Quadratic => d * d,
+            Cost::Custom(f) => f(d),
+        }
+    }
+}
+
+/// The cheapest position to align the crabs at, the leftmost one if there are several
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: u64,
+    pub cost: u128, // the fuel of up to 2^32 crabs moving distances below 2^32 each
 }
 
-fn calculate_fuel(x: u32, crabs: &Vec<u32>) -> u32 {
-    crabs.iter().map(|c| ((dist(x,*c)+1)*dist(x,*c)) / 2).sum::<u32>() // utilize [1]
+/// Fuel to align all crabs at `x`, summed in 128 bits such that it can not overflow
+fn total_fuel(crabs: &[u64], x: u64, cost: Cost) -> u128 {
+    crabs.iter().map(|c| cost.fuel(x.abs_diff(*c)) as u128).sum()
+}
+
+/// Finds the cheapest alignment exactly. With linear costs any median is optimal. Otherwise the total
+/// fuel is convex in the position, so the leftmost position from which it no longer falls is found by
+/// a binary search between the outermost crabs.
+pub fn align(input: &str, cost: Cost) -> Result<Alignment, ParseError> {
+    let mut crabs: Vec<u64> = parsing(input)?.into_iter().map(u64::from).collect();
+    if crabs.is_empty() {
+        return Err(ParseError::new("no crabs"));
+    }
+    crabs.sort_unstable();
+    let position = match cost {
+        Cost::Linear => crabs[(crabs.len() - 1) / 2],
+        _ => {
+            let (mut lo, mut hi) = (crabs[0], crabs[crabs.len() - 1]);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if total_fuel(&crabs, mid, cost) <= total_fuel(&crabs, mid + 1, cost) {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+            lo
+        }
+    };
+    Ok(Alignment {position, cost: total_fuel(&crabs, position, cost)})
 }
 
-fn calculate_dist(x: u32, crabs: &Vec<u32>) -> u32 {
-    crabs.iter().map(|c| dist(x, *c)).sum::<u32>()
+/// Naive reference: tries every position between the outermost crabs
+pub fn reference_part1(input: &str) -> Option<String> {
+    reference(input, |d| d)
+}
+
+/// Naive reference: tries every position, summing every single step of fuel
+pub fn reference_part2(input: &str) -> Option<String> {
+    reference(input, |d| (1..=d).sum())
+}
+
+fn reference(input: &str, fuel: fn(u64) -> u64) -> Option<String> {
+    brute_force(input, fuel).map(|a| a.cost.to_string())
+}
+
+/// The leftmost cheapest position between the outermost crabs, trying all of them
+fn brute_force(input: &str, fuel: impl Fn(u64) -> u64) -> Option<Alignment> {
+    let crabs: Vec<u64> = parsing(input).ok()?.into_iter().map(u64::from).collect();
+    let (min, max) = (*crabs.iter().min()?, *crabs.iter().max()?);
+    (min..=max).map(|x| Alignment {position: x, cost: crabs.iter().map(|c| fuel(x.abs_diff(*c)) as u128).sum()})
+        .min_by_key(|a| (a.cost, a.position))
 }
 
 fn test_input() -> String {
     String::from("16,1,2,0,4,2,7,1,2,14")
 }
 
-fn dist(x: u32, y: u32) -> u32 {
-    i32::abs(x as i32 - y as i32) as u32
+fn parsing(input: &str) -> Result<Vec<u32>, ParseError> {
+    parse::ints(input)
 }
 
-fn parsing(input: &str) -> Vec<u32> {
-    let nums = input.trim_end().split(',');
-    let mut v = Vec::new();
-    for n in nums {
-        v.push(n.parse().unwrap());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Commas;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(align(&test_input(), Cost::Linear), Ok(Alignment {position: 2, cost: 37}));
+        assert_eq!(align(&test_input(), Cost::Triangular), Ok(Alignment {position: 5, cost: 168}));
+        // the optimum can be at the leftmost crab
+        assert_eq!(align("0,0,0,1", Cost::Triangular), Ok(Alignment {position: 0, cost: 1}));
+        assert_eq!(align("7", Cost::Quadratic), Ok(Alignment {position: 7, cost: 0}));
+        let far = format!("0,{}", u32::MAX);
+        assert_eq!(align(&far, Cost::Linear), Ok(Alignment {position: 0, cost: u32::MAX as u128}));
+        // beyond 64 bits
+        let fuel = 3 * ((1u128 << 31) - 1).pow(2) + 3 * (1u128 << 31).pow(2);
+        assert_eq!(align(&format!("{0},{0},{0}", far), Cost::Quadratic), Ok(Alignment {position: (1 << 31) - 1, cost: fuel}));
+    }
+
+    fn cost() -> impl Strategy<Value = (Cost, fn(u64) -> u64)> {
+        prop_oneof![
+            Just((Cost::Linear, (|d| d) as fn(u64) -> u64)),
+            Just((Cost::Triangular, (|d| (1..=d).sum()) as fn(u64) -> u64)),
+            Just((Cost::Quadratic, (|d| d * d) as fn(u64) -> u64)),
+            Just((Cost::Custom(|d| d.saturating_sub(3) * 5), (|d| d.saturating_sub(3) * 5) as fn(u64) -> u64)),
+            Just((Cost::Custom(|d| d * d * d + 10), (|d| d * d * d + 10) as fn(u64) -> u64)),
+        ]
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parsing("16,x\n").is_err());
+        assert!(parsing("16,-1\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_brute_force(crabs in prop::collection::vec(0..200u32, 1..20), (cost, fuel) in cost()) {
+            let input = Commas(&crabs).to_string();
+            let expected = brute_force(&input, fuel).unwrap();
+            let found = align(&input, cost).unwrap();
+            prop_assert_eq!(found.cost, expected.cost);
+            // any median is optimal, otherwise the leftmost optimum is found
+            if !matches!(cost, Cost::Linear) {
+                prop_assert_eq!(found.position, expected.position);
+            }
+        }
+
+        #[test]
+        fn test_roundtrip(crabs in prop::collection::vec(any::<u32>(), 1..50)) {
+            prop_assert_eq!(parsing(&Commas(&crabs).to_string()), Ok(crabs));
+        }
+
+        #[test]
+        fn test_garbage(input in "[0-9,a-]{0,20}\n?") {
+            let _ = parsing(&input);
+        }
     }
-    v
 }
 
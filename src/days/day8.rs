@@ -1,6 +1,8 @@
 use crate::AOCDay;
+use crate::utils::{parse, ParseError};
+use crate::utils::bits::BitSet;
 
-use std::collections::HashSet;
+use std::fmt;
 
 use nom::IResult;
 use nom::sequence::separated_pair;
@@ -15,19 +17,158 @@ use nom::bytes::complete::tag;
 pub struct Day8();
 
 impl AOCDay for Day8 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 8 }
 }
 
-// Maps a signal to its corresponding true signal
-type Mapping = (char, char);
+/// The lit segments of a digit, segment `a` is 0
+type Segments = BitSet;
 
-fn part1(input: &str) -> String {
-    let parsed = parser(&input);
+fn segments(pattern: &str) -> Segments {
+    pattern.bytes().map(|b| (b - b'a') as usize).collect()
+}
+
+/// The segments of the digits 0 to 9
+fn digits() -> [Segments; 10] {
+    ["abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg"].map(segments)
+}
+
+fn letter(segment: usize) -> char {
+    (b'a' + segment as u8) as char
+}
+
+/// The ten unique signal patterns and the four digit output value of a display,
+/// some of the patterns may be missing
+#[derive(Debug, PartialEq, Eq)]
+struct Entry<'a> {
+    patterns: Vec<&'a str>,
+    output: Vec<&'a str>,
+}
+
+impl fmt::Display for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | {}", self.patterns.join(" "), self.output.join(" "))
+    }
+}
+
+/// The segment every wire is connected to, wire `a` is 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Wiring([usize; 7]);
+
+impl Wiring {
+    fn apply(&self, wires: Segments) -> Segments {
+        wires.iter().map(|w| self.0[w]).collect()
+    }
+}
+
+/// Every wiring consistent with an entry and the output value they all decode
+#[derive(Debug)]
+struct Decoding {
+    wirings: Vec<Wiring>,
+    value: u32,
+}
+
+impl fmt::Display for Decoding {
+    /// The segment of every wire, `?` where the wirings disagree
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segments: String = (0..7).map(|w| {
+            let segment = self.wirings[0].0[w];
+            if self.wirings.iter().all(|wiring| wiring.0[w] == segment) { letter(segment) } else { '?' }
+        }).collect();
+        write!(f, "abcdefg -> {}: {:04}", segments, self.value)
+    }
+}
+
+/// Searches every wiring which maps each pattern to a different digit and the output to digits.
+/// A wire can only be connected to segments that are lit in some digit with as many segments as every
+/// pattern containing the wire, and unlit in such a digit for the other patterns. The wires are assigned
+/// one at a time and a pattern is checked as soon as all of its wires are.
+fn decode(entry: &Entry) -> Result<Decoding, String> {
+    let digits = digits();
+    let all = segments("abcdefg");
+    let observed: Vec<(&str, Segments)> = entry.patterns.iter().chain(entry.output.iter()).map(|p| (*p, segments(p))).collect();
+
+    let mut candidates = [all; 7];
+    for (pattern, wires) in observed.iter() {
+        if wires.len() != pattern.len() {
+            return Err(format!("pattern {:?} repeats a segment", pattern));
+        }
+        let same_size: Vec<Segments> = digits.iter().copied().filter(|d| d.len() == wires.len()).collect();
+        if same_size.is_empty() {
+            return Err(format!("pattern {:?} lights {} segments, which no digit does", pattern, wires.len()));
+        }
+        let lit = same_size.iter().fold(Segments::new(), |union, d| union | *d);
+        let unlit = same_size.iter().fold(Segments::new(), |union, d| union | (all - *d));
+        for (w, candidate) in candidates.iter_mut().enumerate() {
+            *candidate = *candidate & if wires.contains(w) { lit } else { unlit };
+        }
+    }
+    for (i, a) in entry.patterns.iter().enumerate() {
+        if let Some(b) = entry.patterns[i + 1..].iter().find(|b| segments(b) == segments(a)) {
+            return Err(format!("patterns {:?} and {:?} are the same digit", a, b));
+        }
+    }
+    if let Some(w) = candidates.iter().position(|c| c.is_empty()) {
+        return Err(format!("wire {} can not be connected to any segment", letter(w)));
+    }
+
+    let mut checks: Vec<Vec<Segments>> = vec![Vec::new(); 7];
+    for (_, wires) in observed.iter() {
+        if let Some(last) = wires.iter().last() {
+            checks[last].push(*wires);
+        }
+    }
+    let mut wirings = Vec::new();
+    search(0, &mut Wiring([0; 7]), Segments::new(), &Constraints {digits, candidates, checks}, &mut wirings);
+
+    let value = |wiring: &Wiring| -> u32 {
+        entry.output.iter().map(|o| digits.iter().position(|d| *d == wiring.apply(segments(o))).unwrap() as u32)
+            .fold(0, |value, digit| value * 10 + digit)
+    };
+    // the digits of the patterns need to differ, which the search does not check
+    wirings.retain(|wiring| {
+        let mapped: Vec<Segments> = entry.patterns.iter().map(|p| wiring.apply(segments(p))).collect();
+        mapped.iter().enumerate().all(|(i, a)| !mapped[i + 1..].contains(a))
+    });
+    let Some(first) = wirings.first() else {
+        return Err(String::from("no wiring maps every pattern to a different digit"));
+    };
+    let first_value = value(first);
+    if let Some(other) = wirings.iter().map(value).find(|v| *v != first_value) {
+        return Err(format!("the output is ambiguous, it could be {:04} or {:04} with {} possible wirings", first_value, other, wirings.len()));
+    }
+    Ok(Decoding {value: first_value, wirings})
+}
+
+/// The constraints of the search for wirings
+struct Constraints {
+    digits: [Segments; 10],
+    candidates: [Segments; 7], // segments every wire may be connected to
+    checks: Vec<Vec<Segments>>, // observations to map to digits once their last wire is assigned
+}
+
+fn search(wire: usize, wiring: &mut Wiring, used: Segments, constraints: &Constraints, found: &mut Vec<Wiring>) {
+    if wire == 7 {
+        found.push(*wiring);
+        return;
+    }
+    let Constraints {digits, candidates, checks} = constraints;
+    for segment in (candidates[wire] - used).iter() {
+        wiring.0[wire] = segment;
+        if checks[wire].iter().all(|wires| digits.contains(&wiring.apply(*wires))) {
+            let mut used = used;
+            used.insert(segment);
+            search(wire + 1, wiring, used, constraints, found);
+        }
+    }
+}
+
+fn part1(input: &str) -> Result<String, ParseError> {
+    let parsed = parser(input)?;
     let mut count = 0;
-    for (_, os) in parsed {
-        for o in os {
+    for entry in parsed {
+        for o in entry.output {
             match o.len() {
                 2 => count+=1,
                 3 => count+=1,
@@ -37,134 +178,26 @@ fn part1(input: &str) -> String {
             }
         }
     }
-    String::from(format!("{}", count))
+    Ok(format!("{}", count))
 }
 
-fn part2(input: &str) -> String {
-    let parsed = parser(&input);
-    let sequences = parsed.into_iter().map(|(ss, os)| {
-        // convert to HashSet
-        let hs: Vec<HashSet<char>> = ss.into_iter().map(|s| s.chars().collect()).collect();
-        let ho: Vec<HashSet<char>> = os.into_iter().map(|s| s.chars().collect()).collect();
-        (hs, ho)
-    });
-
+fn part2(input: &str) -> Result<String, ParseError> {
     let mut total = 0;
-    for (seq, out) in sequences {
-        let result = decrypt_output(seq, out);
-        total += result;
-    }
-    String::from(format!("{}", total))
-}
-
-fn decrypt_output(seq: Vec<HashSet<char>>, output: Vec<HashSet<char>>) -> u32 {
-    let mut lfives: Vec<HashSet<char>> = Vec::with_capacity(3);
-    let mut lsixes: Vec<HashSet<char>> = Vec::with_capacity(3);
-
-    let mut one: Option<HashSet<char>> = None;
-    let mut seven: Option<HashSet<char>> = None;
-    let mut eight: Option<HashSet<char>> = None;
-    let mut four: Option<HashSet<char>> = None;
-
-    // Separate segment sequences by number of segments
-    for hs in seq {
-        match hs.len() {
-            2 => {one = Some(hs)},
-            3 => {seven = Some(hs)},
-            4 => {four = Some(hs)},
-            7 => {eight = Some(hs)},
-            5 => {lfives.push(hs)},
-            6 => {lsixes.push(hs)},
-            _ => panic!("Encountered Uknown Length Segment Sequence {:?}", hs),
-        }
-    }
-    let one = one.unwrap();
-    let seven = seven.unwrap();
-    let eight = eight.unwrap();
-    let four = four.unwrap();
-    
-    let nine = determine_nine(&mut lsixes, &four);
-    let e = (*eight.difference(&nine).next().unwrap(), 'e');
-    let (zero, six) = determine_zero_six(lsixes, &one);
-
-    let three = determine_three(&mut lfives); 
-
-    let mut three_clone = three.clone(); // determine b segment
-    three_clone.insert(e.0);
-    let b = (*eight.difference(&three_clone).next().unwrap(), 'b');
-
-    let (two, five) = determine_two_and_five(lfives, &b); // use b segment to identify 2 and 5
-
-    // Determine score
-    let mut output_value = 0;
-    for s in output {
-        let val: u32;
-        if zero.eq(&s) {
-            val = 0;
-        }else if one.eq(&s) {
-            val = 1;
-        }else if two.eq(&s) {
-            val = 2;
-        }else if three.eq(&s) {
-            val = 3;
-        }else if four.eq(&s) {
-            val = 4;
-        }else if five.eq(&s) {
-            val = 5;
-        }else if six.eq(&s) {
-            val = 6;
-        }else if seven.eq(&s) {
-            val = 7;
-        }else if eight.eq(&s) {
-            val = 8;
-        }else { val = 9}
-        output_value = output_value * 10 + val;
-    }
-    output_value
-}
-
-fn determine_three(fives: &mut Vec<HashSet<char>>) -> HashSet<char> {
-    assert!(fives.len() == 3);
-    let s1 = &fives[0];
-    let s2 = &fives[1];
-    let s3 = &fives[2];
-    if s1.intersection(s2).count() == 3 {
-        return fives.remove(2);// s3 is three
-    }else if s1.intersection(s3).count() == 3 {
-        return fives.remove(1);
-    }else if s2.intersection(s3).count() == 3 {
-        return fives.remove(0);
-    }else {
-        panic!("Could not determine three");
-    }
-}
-
-fn determine_nine(sixes: &mut Vec<HashSet<char>>, four: &HashSet<char>) -> HashSet<char> {
-    assert!(sixes.len() == 3);
-    for i in 0..3 {
-        if four.intersection(&sixes[i]).count() == 4 {
-            return sixes.remove(i);
-        }
+    for (i, entry) in parser(input)?.iter().enumerate() {
+        total += decode(entry).map_err(|e| ParseError::new(e).offset(i + 1))?.value;
     }
-    panic!("Could not determine nine");
-}
-
-// Requires that sixes only contain zero and six.
-fn determine_zero_six(mut sixes: Vec<HashSet<char>>, one: &HashSet<char>) -> (HashSet<char>, HashSet<char>) {
-    assert!(sixes.len() == 2);
-    let s1 = sixes.pop().unwrap();
-    let s2 = sixes.pop().unwrap(); 
-    if one.is_subset(&s1) {(s1, s2)}else {(s2, s1)}
+    Ok(format!("{}", total))
 }
 
-fn determine_two_and_five(mut fives: Vec<HashSet<char>>, b: &Mapping) -> (HashSet<char>, HashSet<char>) {
-    assert!(fives.len() == 2);
-    let s1 = fives.pop().unwrap();
-    let s2 = fives.pop().unwrap();
-    if s1.contains(&b.0) {(s2, s1)} else if s2.contains(&b.0) {(s1, s2)} else {panic!("Could not determine two and five");}
+/// The wiring and output value of every entry, or why it can not be decoded
+pub fn explain(input: &str) -> Result<String, ParseError> {
+    let lines: Vec<String> = parser(input)?.iter().enumerate().map(|(i, entry)| match decode(entry) {
+        Ok(decoding) => format!("line {}: {}", i + 1, decoding),
+        Err(e) => format!("line {}: {}", i + 1, e),
+    }).collect();
+    Ok(lines.join("\n"))
 }
 
-
 fn test_input() -> String {
     let s = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf
 be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
@@ -187,16 +220,107 @@ fn test_input2() -> String {
 pub fn get() -> Day8 {Day8()}
 
 
-fn parser(input: &str) -> Vec<(Vec<&str>, Vec<&str>)> {
-    input.lines().map(|l| parse_line(l.trim())).collect()
+fn parser(input: &str) -> Result<Vec<Entry<'_>>, ParseError> {
+    parse::lines(input, |l| {
+        let (patterns, output) = parse::complete(l, parse_line)?;
+        if patterns.len() > 10 || output.len() != 4 {
+            return Err(ParseError::new(format!("expected up to 10 patterns and 4 output digits in {:?}", l)));
+        }
+        if patterns.iter().chain(output.iter()).any(|s| s.chars().any(|c| !('a'..='g').contains(&c))) {
+            return Err(ParseError::new(format!("segments must be a-g in {:?}", l)));
+        }
+        Ok(Entry {patterns, output})
+    })
 }
 
-fn parse_line(input: &str) -> (Vec<&str>, Vec<&str>) {
-    let mut pair = separated_pair(parse_list_of_chars, tag(" | "), parse_list_of_chars);
-    let res: IResult<&str, (Vec<&str>, Vec<&str>)> = pair(input);
-    res.unwrap().1
+fn parse_line(input: &str) -> IResult<&str, (Vec<&str>, Vec<&str>)> {
+    separated_pair(parse_list_of_chars, tag(" | "), parse_list_of_chars)(input)
 }
 
 fn parse_list_of_chars(input: &str) -> IResult<&str, Vec<&str>> {
     separated_list0(nom::character::complete::char(' '), alpha1)(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Lines;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("26")));
+        // the larger example follows the first line
+        assert_eq!(part2(test_input().split_once('\n').unwrap().1), Ok(String::from("61229")));
+        let input = test_input();
+        let decodings: Vec<String> = parser(&input).unwrap().iter().map(|entry| decode(entry).unwrap().to_string()).collect();
+        assert_eq!(decodings[0], "abcdefg -> cfgabde: 5353");
+        assert_eq!(decodings[1], "abcdefg -> ecdafgb: 8394");
+    }
+
+    #[test]
+    fn test_missing_patterns() {
+        // without 5, 6 and 9 as well as the 2 and 3 of the output the wiring is still determined
+        let entry = Entry {patterns: vec!["acedgfb", "dab", "cefabd", "cdfgeb", "eafb", "cagedb", "ab"], output: vec!["cdfeb", "fcadb", "cdfeb", "cdbaf"]};
+        assert_eq!(decode(&entry).unwrap().to_string(), "abcdefg -> cfgabde: 5353");
+        // 1 and 7 alone only determine a single wire, but the output is still known
+        let entry = Entry {patterns: vec!["ab", "dab"], output: vec!["ab", "dab", "ba", "acedgfb"]};
+        assert_eq!(decode(&entry).unwrap().to_string(), "abcdefg -> ???a???: 1718");
+        let entry = Entry {patterns: vec!["ab"], output: vec!["ab", "cdfbe", "ab", "ab"]};
+        assert!(decode(&entry).unwrap_err().starts_with("the output is ambiguous, it could be "));
+    }
+
+    #[test]
+    fn test_contradictions() {
+        let explain = |patterns: Vec<&str>, output: Vec<&str>| decode(&Entry {patterns, output}).unwrap_err();
+        assert_eq!(explain(vec!["ab", "abc", "a"], vec!["ab"; 4]), "pattern \"a\" lights 1 segments, which no digit does");
+        assert_eq!(explain(vec!["ab", "abc", "ba"], vec!["ab"; 4]), "patterns \"ab\" and \"ba\" are the same digit");
+        assert_eq!(explain(vec!["ab", "acd"], vec!["ab"; 4]), "wire b can not be connected to any segment");
+        // the 8 of the first example replaced by another 5 segment pattern
+        let input = format!("{}\nabdef cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf\n", test_input2());
+        let e = part2(&input).unwrap_err();
+        assert_eq!(e.line(), Some(2));
+        assert!(super::explain(&input).unwrap().ends_with("line 2: no wiring maps every pattern to a different digit"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parser("ab cd | ab cd ab cd ab\n").is_err());
+        assert!(parser("abcxyz | ab\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_generated(seed in any::<u64>(), keep in prop::collection::vec(prop::bool::ANY, 10)) {
+            let input = crate::gen::generate(8, 1, seed);
+            let full = parser(&input).unwrap().remove(0);
+            let expected = decode(&full).unwrap();
+            prop_assert_eq!(expected.wirings.len(), 1);
+            // with patterns missing the same value is decoded, unless it is ambiguous
+            let patterns = full.patterns.iter().zip(keep.iter()).filter(|(_, k)| **k).map(|(p, _)| *p).collect();
+            let partial = Entry {patterns, output: full.output.clone()};
+            match decode(&partial) {
+                Ok(decoding) => {
+                    prop_assert_eq!(decoding.value, expected.value);
+                    prop_assert!(decoding.wirings.contains(&expected.wirings[0]));
+                },
+                Err(e) => prop_assert!(e.starts_with("the output is ambiguous"), "{}", e),
+            }
+        }
+
+        #[test]
+        fn test_roundtrip(entries in prop::collection::vec((prop::collection::vec("[a-g]{1,7}", 0..=10), prop::collection::vec("[a-g]{1,7}", 4)), 0..20)) {
+            let entries: Vec<Entry> = entries.iter().map(|(p, o)| Entry {
+                patterns: p.iter().map(|s| s.as_str()).collect(),
+                output: o.iter().map(|s| s.as_str()).collect(),
+            }).collect();
+            let serialized = Lines(&entries).to_string();
+            prop_assert_eq!(parser(&serialized), Ok(entries));
+        }
+
+        #[test]
+        fn test_garbage(input in "([a-hA ]{0,20}\\|?[a-h ]{0,10}\n){0,4}") {
+            let _ = parser(&input);
+        }
+    }
+}
@@ -1,4 +1,8 @@
 use crate::AOCDay;
+use crate::utils::ParseError;
+use crate::utils::bits::DynBitSet;
+use crate::utils::grid::{Coord, Grid};
+use crate::visualize::{Colour, Frame, Visualize};
 
 /*
  * Template for a implementing a day
@@ -6,85 +10,139 @@ use crate::AOCDay;
 
 pub struct Day9();
 
-type Grid = Vec<Vec<u32>>;
-type Coord = (usize, usize);
-
 impl AOCDay for Day9 {
-    fn part1(&self, _input: &str) -> Option<String> { Some(part1(_input)) }
-    fn part2(&self, _input: &str) -> Option<String> { Some(part2(_input)) }
+    fn part1(&self, _input: &str) -> Result<String, ParseError> { part1(_input) }
+    fn part2(&self, _input: &str) -> Result<String, ParseError> { part2(_input) }
     fn get_num(&self) -> u32 { 9 }
 }
 
+impl Visualize for Day9 {
+    /// A single frame of the basins, each in one of a few colours, separated by the dark walls
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError> {
+        let grid = parser(input)?;
+        Ok(Box::new(std::iter::once(frame(&Basins::new(&grid)))))
+    }
+}
+
 pub fn get() -> Day9 {Day9()}
 
-fn part1(input: &str) -> String {
-    let grid = parser(&input);
-    let lows = low_points(&grid);
-    let risk: u32 = lows.into_iter().map(|(x,y)| grid[y][x]+1).sum();
-    String::from(format!("{}", risk))
+// Walls are dark, neighbouring basins mostly differ in colour
+const PALETTE: [Colour; 8] = [
+    (20, 20, 30), (230, 90, 70), (240, 190, 60), (120, 200, 90),
+    (70, 170, 220), (150, 110, 220), (230, 120, 190), (90, 210, 190),
+];
+
+fn frame(basins: &Basins) -> Frame {
+    let labels = &basins.labels;
+    let mut frame = Frame::new(labels.width(), labels.height(), &PALETTE);
+    for (x, y) in labels.coords() {
+        if let Some(basin) = basins.basin((x, y)) {
+            frame.set(x, y, 1 + (basin % (PALETTE.len() - 1)) as u8);
+        }
+    }
+    frame
 }
 
-fn part2(input: &str) -> String {
-    let grid = parser(&input);
+fn part1(input: &str) -> Result<String, ParseError> {
+    let grid = parser(input)?;
     let lows = low_points(&grid);
-    // get sizes of various basins
-    let mut sizes = Vec::new();
-    for coord in lows {
-        let s = basin_size(&grid, coord);
-        sizes.push(s);
-    }
+    let risk: u32 = lows.into_iter().map(|c| grid[c] as u32 + 1).sum();
+    Ok(format!("{}", risk))
+}
 
-    sizes.sort(); 
-    let sum: u32 = sizes.into_iter().rev().take(3).product();
-    String::from(format!("{}", sum))
+fn part2(input: &str) -> Result<String, ParseError> {
+    let grid = parser(input)?;
+    let mut sizes = basin_sizes(&grid);
+    sizes.sort_unstable();
+    // three basins of a third of a huge map each overflow u64
+    let product: u128 = sizes.into_iter().rev().take(3).map(u128::from).product();
+    Ok(format!("{}", product))
 }
 
-fn basin_size(grid: &Grid, low_point: Coord) -> u32 {
-    let mut visited = Vec::new();
-    for _ in 0..grid.len() {
-        let mut r = Vec::new();
-        for _ in 0..grid[0].len() {
-            r.push(false);
+/// Fills every basin in turn with an iterative flood fill, such that huge basins do not overflow the
+/// stack, and returns their sizes. `claim` adds a cell to the basin of the given number, if it is in
+/// none yet, and tells whether it did.
+fn fill(grid: &Grid<u8>, mut claim: impl FnMut(Coord, usize) -> bool) -> Vec<u64> {
+    let mut sizes = Vec::new();
+    let mut stack = Vec::new();
+    for start in grid.coords() {
+        if grid[start] == 9 || !claim(start, sizes.len()) {continue;}
+        stack.push(start);
+        let mut size = 0;
+        while let Some(coord) = stack.pop() {
+            size += 1;
+            for next in grid.neighbours4(coord) {
+                if grid[next] != 9 && claim(next, sizes.len()) {
+                    stack.push(next);
+                }
+            }
         }
-        visited.push(r);
+        sizes.push(size);
     }
-    1 + _basin_size_visit_coord(grid, &mut visited, low_point)
+    sizes
 }
 
-fn _basin_size_visit_coord(grid: &Grid, mut visited: &mut Vec<Vec<bool>>, (x, y): Coord) -> u32 {
-    let n = grid[y][x];
+/// Number of cells of every basin, marking the filled cells with a bit each
+fn basin_sizes(grid: &Grid<u8>) -> Vec<u64> {
+    let mut filled = DynBitSet::new(grid.len());
+    fill(grid, |(x, y), _| {
+        let i = y * grid.width() + x;
+        let new = !filled.get(i);
+        filled.set(i, true);
+        new
+    })
+}
 
-    let mut size = 0;
-    let nc = get_neighbour_coords(x, y, &grid);
+/// Label of the cells of height 9, which belong to no basin
+const WALL: u32 = u32::MAX;
+
+/// The basins of a heightmap: the areas of cells connected without crossing a cell of height 9.
+/// The labels take four bytes per cell, so only the frames label the basins, part 2 merely counts
+/// their cells with [`basin_sizes`].
+pub struct Basins {
+    labels: Grid<u32>, // basin of every cell
+    sizes: Vec<u64>,
+}
 
-    for (x2, y2) in nc {
-        if grid[y2][x2] < 9 {
-            // potentially new node
-            if !visited[y2][x2] && grid[y2][x2] > n { // visit node only if its bigger and not visited already
-                visited[y2][x2] = true;
-                // +1, because we have found another node, which flows into the basin
-                size = size + 1 + _basin_size_visit_coord(&grid, &mut visited, (x2, y2));
+impl Basins {
+    pub fn new(grid: &Grid<u8>) -> Self {
+        let mut labels = Grid::filled(grid.width(), grid.height(), WALL);
+        let sizes = fill(grid, |coord, basin| {
+            assert!(basin < WALL as usize, "too many basins");
+            let new = labels[coord] == WALL;
+            if new {
+                labels[coord] = basin as u32;
             }
-        }
+            new
+        });
+        Basins {labels, sizes}
+    }
+
+    /// The basin of a cell, `None` for cells of height 9
+    pub fn basin(&self, coord: Coord) -> Option<usize> {
+        let label = self.labels[coord];
+        (label != WALL).then_some(label as usize)
+    }
+
+    /// Number of cells of every basin
+    pub fn sizes(&self) -> &[u64] {
+        &self.sizes
     }
-    size
-}
 
-fn low_points(grid: &Grid) -> Vec<Coord> {
-    let num_rows = grid.len();
-    let num_cols = grid[0].len();
-
-    let mut lows = Vec::new();
-    for y in 0..num_rows {
-        for x in 0.. num_cols {
-            let neighbours = get_neighbours(x,y,&grid);
-            let e = grid[y][x];
-            if !neighbours.iter().any(|n| n <= &e) {
-                lows.push((x,y));
+    /// The cells of every basin, row by row
+    pub fn members(&self) -> Vec<Vec<Coord>> {
+        let mut members: Vec<Vec<Coord>> = self.sizes.iter().map(|&size| Vec::with_capacity(size as usize)).collect();
+        for coord in self.labels.coords() {
+            if let Some(basin) = self.basin(coord) {
+                members[basin].push(coord);
             }
         }
+        members
     }
-    lows
+}
+
+fn low_points(grid: &Grid<u8>) -> Vec<Coord> {
+    grid.coords().filter(|&c| grid.neighbours4(c).all(|n| grid[n] > grid[c])).collect()
 }
 
 fn test_input() -> String {
@@ -95,30 +153,92 @@ fn test_input() -> String {
 9899965678")
 }
 
-fn get_neighbours(x: usize, y: usize, grid: &Grid) -> Vec<u32> {
-    let mut v = Vec::new();
-    if x > 0 {v.push(grid[y][x-1]);}
-    if x < (grid[0].len() - 1) {v.push(grid[y][x+1]);}
-    if y > 0 {v.push(grid[y-1][x]);}
-    if y < (grid.len() - 1) {v.push(grid[y+1][x]);}
-    v
+/// Parses the heightmap, with a byte per cell for large maps
+fn parser(input: &str) -> Result<Grid<u8>, ParseError> {
+    Grid::parse_digits(input)
 }
 
-fn get_neighbour_coords(x: usize, y: usize, grid: &Grid) -> Vec<Coord> {
-    let mut coords = Vec::new();
-    if x > 0 {coords.push((x-1, y))};
-    if x < (grid[0].len() - 1) {coords.push((x+1, y))};
-    if y > 0 {coords.push((x, y-1))};
-    if y < (grid.len() - 1) {coords.push((x, y+1))};
-    coords
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn grid() -> impl Strategy<Value = Grid<u8>> {
+        (1..20usize).prop_flat_map(|width| prop::collection::vec(prop::collection::vec(0..10u8, width), 1..20))
+            .prop_map(|rows| Grid::from_rows(rows).unwrap())
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&test_input()), Ok(String::from("15")));
+        assert_eq!(part2(&test_input()), Ok(String::from("1134")));
+        let grid = parser(&test_input()).unwrap();
+        let basins = Basins::new(&grid);
+        assert_eq!(basins.sizes(), &[3, 9, 14, 9]);
+        assert_eq!(basins.members()[0], vec![(0, 0), (1, 0), (0, 1)]);
+        assert_eq!((basins.basin((2, 0)), basins.basin((9, 0))), (None, Some(1)));
+        assert_eq!(frame(&basins).get(2, 2), 3);
+    }
+
+    #[test]
+    fn test_large() {
+        // a single snake shaped basin, which is too deep to recurse through
+        let rows: Vec<String> = (0..1000).map(|y| match y % 4 {
+            1 => format!("{}0", "9".repeat(999)),
+            3 => format!("0{}", "9".repeat(999)),
+            _ => "1".repeat(1000),
+        }).collect();
+        let grid = parser(&rows.join("\n")).unwrap();
+        assert_eq!(Basins::new(&grid).sizes(), &[500 * 1000 + 500]);
+        assert_eq!(basin_sizes(&grid), vec![500 * 1000 + 500]);
+    }
 
-fn parser(input: &str) -> Grid {
-    let mut grid = Vec::new();
-    let lines = input.lines();
-    for l in lines {
-        let nums = l.chars().map(|s|  s.to_digit(10).unwrap()).collect();
-        grid.push(nums);
+    #[test]
+    fn test_huge() {
+        // 2x2 basins between walls on every third row and column, and the same snake
+        let n = 3000;
+        let grid = Grid::from_fn(n, n, |(x, y)| if x % 3 == 2 || y % 3 == 2 { 9 } else { 5 });
+        assert_eq!(basin_sizes(&grid), vec![4; (n / 3) * (n / 3)]);
+        let snake = Grid::from_fn(n, n, |(x, y)| match y % 4 {
+            1 if x != n - 1 => 9,
+            3 if x != 0 => 9,
+            _ => 1,
+        });
+        assert_eq!(basin_sizes(&snake), vec![(n * n / 2 + n / 2) as u64]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parser("12\n3\n").is_err());
+        assert!(parser("1a\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_members(grid in grid()) {
+            let basins = Basins::new(&grid);
+            let members = basins.members();
+            prop_assert_eq!(members.iter().map(|m| m.len() as u64).sum::<u64>(), grid.iter().filter(|h| **h != 9).count() as u64);
+            for (basin, cells) in members.iter().enumerate() {
+                prop_assert_eq!(cells.len() as u64, basins.sizes()[basin]);
+                // every basin is connected and bounded by walls or other basins
+                for &coord in cells {
+                    prop_assert_eq!(basins.basin(coord), Some(basin));
+                    for next in grid.neighbours4(coord) {
+                        prop_assert!(grid[next] == 9 || basins.basin(next) == Some(basin));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn test_roundtrip(grid in grid()) {
+            prop_assert_eq!(parser(&grid.to_string()), Ok(grid));
+        }
+
+        #[test]
+        fn test_garbage(input in "([0-9a ]{0,6}\\n){0,4}") {
+            let _ = parser(&input);
+        }
     }
-    grid
 }
@@ -0,0 +1,186 @@
+/*
+ * Differential testing
+ *
+ * Several solutions rely on shortcuts (bucket counts, inclusion-exclusion, reverse engineering).
+ * These are compared against naive reference implementations on small generated inputs.
+ * Inputs on which they disagree are shrunk to a minimal counterexample.
+ */
+
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::days;
+use crate::gen;
+
+type Solution = fn(&str) -> Option<String>;
+
+/// Parts of an input, which can be removed while shrinking a counterexample
+#[derive(Debug, Clone, Copy)]
+enum Atom {
+    Comma, // comma separated numbers on a single line
+    Line,
+    Chunk(usize), // blocks of a fixed number of lines
+}
+
+/// A solution together with a naive reference implementation
+pub struct Subject {
+    pub day: u32,
+    pub part: u32,
+    size: usize, // maximum size of generated inputs
+    solution: Solution,
+    reference: Solution,
+    atom: Atom,
+}
+
+/// An input on which the solution and the reference disagree
+#[derive(Debug)]
+pub struct Counterexample {
+    pub seed: u64,
+    pub input: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// All solutions which have a reference implementation
+pub fn subjects() -> Vec<Subject> {
+    vec![
+        Subject {day: 6, part: 1, size: 8, solution: |i| crate::day(6).part1(i).ok(), reference: days::day6::reference_part1, atom: Atom::Comma},
+        Subject {day: 7, part: 1, size: 20, solution: |i| crate::day(7).part1(i).ok(), reference: days::day7::reference_part1, atom: Atom::Comma},
+        Subject {day: 7, part: 2, size: 20, solution: |i| crate::day(7).part2(i).ok(), reference: days::day7::reference_part2, atom: Atom::Comma},
+        Subject {day: 22, part: 1, size: 16, solution: |i| crate::day(22).part1(i).ok(), reference: days::day22::reference_part1, atom: Atom::Line},
+        Subject {day: 22, part: 2, size: 16, solution: |i| crate::day(22).part2(i).ok(), reference: days::day22::reference_part2, atom: Atom::Line},
+        Subject {day: 24, part: 1, size: 3, solution: |i| crate::day(24).part1(i).ok(), reference: days::day24::reference_part1, atom: Atom::Chunk(18)},
+        Subject {day: 24, part: 2, size: 3, solution: |i| crate::day(24).part2(i).ok(), reference: days::day24::reference_part2, atom: Atom::Chunk(18)},
+    ]
+}
+
+impl Subject {
+    /// Compares the solution with the reference on `cases` generated inputs of increasing size.
+    /// Returns the first disagreement, shrunk to a minimal counterexample.
+    pub fn check(&self, cases: u64, seed: u64) -> Option<Counterexample> {
+        for case in 0..cases {
+            let seed = seed.wrapping_add(case);
+            let size = 1 + (case as usize % self.size);
+            let input = gen::generate(self.day, size, seed);
+            if let Some(c) = self.disagreement(&input, seed) {
+                return Some(self.shrink(c));
+            }
+        }
+        None
+    }
+
+    /// Inputs for which the reference has no answer are considered invalid.
+    fn disagreement(&self, input: &str, seed: u64) -> Option<Counterexample> {
+        let expected = run(self.reference, input).ok()??;
+        let actual = match run(self.solution, input) {
+            Ok(Some(actual)) if actual == expected => return None,
+            Ok(Some(actual)) => actual,
+            Ok(None) => String::from("no answer"),
+            Err(msg) => format!("panicked: {}", msg),
+        };
+        Some(Counterexample {seed, input: String::from(input), expected, actual})
+    }
+
+    /// Greedily removes and simplifies atoms of the input, as long as the disagreement remains.
+    fn shrink(&self, mut counterexample: Counterexample) -> Counterexample {
+        'outer: loop {
+            for candidate in self.candidates(&counterexample.input) {
+                if let Some(c) = self.disagreement(&candidate, counterexample.seed) {
+                    counterexample = c;
+                    continue 'outer;
+                }
+            }
+            return counterexample;
+        }
+    }
+
+    /// Smaller variations of an input
+    fn candidates(&self, input: &str) -> Vec<String> {
+        let atoms = split(input, self.atom);
+        let mut candidates = Vec::new();
+        // remove a single atom
+        for i in 0..atoms.len() {
+            let mut smaller = atoms.clone();
+            smaller.remove(i);
+            candidates.push(smaller);
+        }
+        // remove pairs of atoms, e.g. matching push and pop chunks of day 24
+        for i in 0..atoms.len() {
+            for j in i+1..atoms.len() {
+                let smaller: Vec<String> = atoms.iter().enumerate().filter(|(k, _)| *k != i && *k != j).map(|(_, a)| a.clone()).collect();
+                candidates.push(smaller);
+            }
+        }
+        // replace numbers with smaller numbers
+        for (i, atom) in atoms.iter().enumerate() {
+            if let Ok(n) = atom.parse::<u64>() {
+                for smaller in [0, n / 2, n.saturating_sub(1)].iter().filter(|s| **s < n) {
+                    let mut simpler = atoms.clone();
+                    simpler[i] = smaller.to_string();
+                    candidates.push(simpler);
+                }
+            }
+        }
+        candidates.into_iter().filter(|c| !c.is_empty()).map(|c| join(&c, self.atom)).collect()
+    }
+}
+
+/// Runs a solution, catching panics
+fn run(solution: Solution, input: &str) -> Result<Option<String>, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| solution(input))).map_err(|e| {
+        if let Some(s) = e.downcast_ref::<&str>() {
+            String::from(*s)
+        } else if let Some(s) = e.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            String::from("unknown panic")
+        }
+    })
+}
+
+fn split(input: &str, atom: Atom) -> Vec<String> {
+    match atom {
+        Atom::Comma => input.trim_end().split(',').map(String::from).collect(),
+        Atom::Line => input.lines().map(String::from).collect(),
+        Atom::Chunk(n) => {
+            let lines: Vec<&str> = input.lines().collect();
+            lines.chunks(n).map(|c| c.join("\n")).collect()
+        },
+    }
+}
+
+fn join(atoms: &[String], atom: Atom) -> String {
+    let sep = match atom {
+        Atom::Comma => ",",
+        Atom::Line | Atom::Chunk(_) => "\n",
+    };
+    format!("{}\n", atoms.join(sep))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subjects() {
+        for subject in subjects() {
+            let c = subject.check(10, 0);
+            assert!(c.is_none(), "day {} part {}: {:?}", subject.day, subject.part, c);
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        // fails as soon as a crab is beyond position 1000
+        let subject = Subject {
+            day: 7,
+            part: 1,
+            size: 20,
+            solution: |i| if i.trim_end().split(',').any(|n| n.parse::<u32>().unwrap() > 1000) {None} else {Some(String::from("0"))},
+            reference: |_| Some(String::from("0")),
+            atom: Atom::Comma,
+        };
+        let c = subject.check(10, 0).unwrap();
+        assert_eq!(c.input, "1001\n");
+        assert_eq!(c.actual, "no answer");
+    }
+}
@@ -0,0 +1,683 @@
+/*
+ * Random input generators
+ *
+ * Every day has a generator producing a structurally valid input of a configurable size.
+ * Inputs are reproducible: the same day, size and seed always result in the same input.
+ * Useful for benchmarking how solutions scale beyond the official inputs.
+ */
+
+use std::collections::HashSet;
+
+/// Small deterministic pseudo random number generator (SplitMix64)
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+        self.next_u64() % n
+    }
+
+    /// Uniform number in `lo..=hi`
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        assert!(lo <= hi, "empty range");
+        lo + self.below((hi - lo) as u64 + 1) as i64
+    }
+
+    /// True with a probability of `percentage`%
+    pub fn chance(&mut self, percentage: u64) -> bool {
+        self.below(100) < percentage
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    /// Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Size of a generated input, when none is provided. Roughly matches the official inputs.
+pub fn default_size(day: u32) -> usize {
+    match day {
+        1 => 2000,
+        2 | 3 | 7 => 1000,
+        4 | 9 | 10 | 15 | 18 | 20 => 100,
+        5 => 500,
+        6 => 300,
+        8 => 200,
+        11 => 10,
+        12 => 6,
+        13 => 800,
+        14 => 20,
+        16 => 60,
+        17 => 100,
+        19 => 30,
+        22 => 420,
+        24 => 7,
+        25 => 137,
+        _ => 1,
+    }
+}
+
+/// Generates an input for a day.
+///
+/// What `size` means differs per day (lines, grid dimension, number of boards, ...).
+/// Day 21 and 23 have a fixed input size, `size` is ignored for those days.
+pub fn generate(day: u32, size: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let rng = &mut rng;
+    match day {
+        1 => day1(rng, size),
+        2 => day2(rng, size),
+        3 => day3(rng, size),
+        4 => day4(rng, size),
+        5 => day5(rng, size),
+        6 => day6(rng, size),
+        7 => day7(rng, size),
+        8 => day8(rng, size),
+        9 => digit_grid(rng, size, 0),
+        10 => day10(rng, size),
+        11 => day11(rng, size),
+        12 => day12(rng, size),
+        13 => day13(rng, size),
+        14 => day14(rng, size),
+        15 => digit_grid(rng, size, 1),
+        16 => day16(rng, size),
+        17 => day17(rng, size),
+        18 => day18(rng, size),
+        19 => day19(rng, size),
+        20 => day20(rng, size),
+        21 => day21(rng),
+        22 => day22(rng, size),
+        23 => day23(rng),
+        24 => day24(rng, size),
+        25 => day25(rng, size),
+        _ => panic!("Day does not exist"),
+    }
+}
+
+fn lines(lines: impl Iterator<Item = String>) -> String {
+    let mut s = String::new();
+    for l in lines {
+        s.push_str(&l);
+        s.push('\n');
+    }
+    s
+}
+
+fn comma_list(nums: impl Iterator<Item = i64>) -> String {
+    nums.map(|n| n.to_string()).collect::<Vec<String>>().join(",")
+}
+
+/// `size` random depth measurements following a random walk.
+fn day1(rng: &mut Rng, size: usize) -> String {
+    let mut depth = rng.range(100, 200);
+    lines((0..size).map(|_| {
+        depth = i64::max(0, depth + rng.range(-20, 40));
+        depth.to_string()
+    }))
+}
+
+/// `size` submarine commands, which never take the submarine above the surface.
+fn day2(rng: &mut Rng, size: usize) -> String {
+    let mut aim = 0;
+    lines((0..size).map(|_| {
+        let n = rng.range(1, 9);
+        match rng.below(3) {
+            0 => format!("forward {}", n),
+            1 if n <= aim => {
+                aim -= n;
+                format!("up {}", n)
+            },
+            _ => {
+                aim += n;
+                format!("down {}", n)
+            },
+        }
+    }))
+}
+
+/// `size` distinct binary numbers of at least 12 bits, at least one as an empty report has no ratings.
+fn day3(rng: &mut Rng, size: usize) -> String {
+    let size = size.max(1);
+    let mut width = 12;
+    while (1 << width) < 2 * size { width += 1; }
+    loop {
+        let mut seen = HashSet::new();
+        let mut rows = Vec::with_capacity(size);
+        while rows.len() < size {
+            let n = rng.below(1 << width);
+            if seen.insert(n) { rows.push(n); }
+        }
+        if co2_rating_exists(&rows, width) {
+            return lines(rows.into_iter().map(|n| format!("{:0width$b}", n, width = width)));
+        }
+    }
+}
+
+/// Filtering on the least common bit removes all candidates, when the remaining candidates share a bit.
+fn co2_rating_exists(rows: &[u64], width: usize) -> bool {
+    let mut candidates = rows.to_vec();
+    for i in (0..width).rev() {
+        if candidates.len() <= 1 { break; }
+        let ones = candidates.iter().filter(|n| (*n >> i) & 1 == 1).count();
+        let keep = if 2 * ones >= candidates.len() { 0 } else { 1 };
+        candidates.retain(|n| (n >> i) & 1 == keep);
+    }
+    candidates.len() == 1
+}
+
+/// A draw and `size` 5x5 bingo boards. Every number is drawn, and a single board wins last.
+fn day4(rng: &mut Rng, size: usize) -> String {
+    let max = usize::max(100, 25 * size / 4);
+    let mut draw: Vec<usize> = (0..max).collect();
+    let mut numbers: Vec<usize> = (0..max).collect();
+    loop {
+        rng.shuffle(&mut draw);
+        let mut turn = vec![0; max]; // turn at which a number is drawn
+        for (t, n) in draw.iter().enumerate() { turn[*n] = t; }
+
+        let mut boards = Vec::with_capacity(size);
+        let mut wins = Vec::with_capacity(size);
+        for _ in 0..size {
+            rng.shuffle(&mut numbers);
+            let board = numbers[..25].to_vec();
+            let rows = (0..5).map(|r| (0..5).map(|c| turn[board[r * 5 + c]]).max().unwrap());
+            let cols = (0..5).map(|c| (0..5).map(|r| turn[board[r * 5 + c]]).max().unwrap());
+            wins.push(rows.chain(cols).min().unwrap());
+            boards.push(board);
+        }
+        let last = wins.iter().max().copied().unwrap_or(0);
+        if size > 1 && wins.iter().filter(|w| **w == last).count() > 1 { continue; }
+
+        let mut s = comma_list(draw.iter().map(|n| *n as i64));
+        s.push('\n');
+        for board in boards {
+            s.push('\n');
+            for row in board.chunks(5) {
+                let row: Vec<String> = row.iter().map(|n| format!("{:2}", n)).collect();
+                s.push_str(&row.join(" "));
+                s.push('\n');
+            }
+        }
+        return s;
+    }
+}
+
+/// `size` horizontal, vertical and diagonal vent lines within a 1000x1000 area.
+fn day5(rng: &mut Rng, size: usize) -> String {
+    lines((0..size).map(|_| {
+        let (x1, y1) = (rng.range(10, 989), rng.range(10, 989));
+        let (dx, dy) = *rng.choose(&[(1, 0), (0, 1), (1, 1), (1, -1)]);
+        // longest line staying within the area
+        let max_len = [(dx, x1), (dy, y1)].iter().filter(|(d, _)| *d != 0)
+            .map(|&(d, c)| if d > 0 { 989 - c } else { c - 10 }).min().unwrap();
+        let len = rng.range(0, max_len);
+        let (x2, y2) = (x1 + dx * len, y1 + dy * len);
+        if rng.chance(50) {
+            format!("{},{} -> {},{}", x1, y1, x2, y2)
+        } else {
+            format!("{},{} -> {},{}", x2, y2, x1, y1)
+        }
+    }))
+}
+
+/// `size` lanternfish timers.
+fn day6(rng: &mut Rng, size: usize) -> String {
+    let fish = (0..size).map(|_| rng.range(1, 5)).collect::<Vec<i64>>();
+    lines(std::iter::once(comma_list(fish.into_iter())))
+}
+
+/// `size` crab positions.
+fn day7(rng: &mut Rng, size: usize) -> String {
+    let crabs = (0..size).map(|_| rng.range(0, 2000)).collect::<Vec<i64>>();
+    lines(std::iter::once(comma_list(crabs.into_iter())))
+}
+
+/// `size` entries of scrambled seven segment displays.
+fn day8(rng: &mut Rng, size: usize) -> String {
+    const DIGITS: [&str; 10] = ["abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg"];
+    lines((0..size).map(|_| {
+        let mut wires: Vec<char> = "abcdefg".chars().collect();
+        rng.shuffle(&mut wires);
+        let mut patterns: Vec<String> = DIGITS.iter().map(|d| scramble(rng, &wires, d)).collect();
+        let output: Vec<String> = (0..4).map(|_| {
+            let digit = *rng.choose(&DIGITS);
+            scramble(rng, &wires, digit)
+        }).collect();
+        rng.shuffle(&mut patterns);
+        format!("{} | {}", patterns.join(" "), output.join(" "))
+    }))
+}
+
+/// Rewires the segments of a digit and shuffles their order
+fn scramble(rng: &mut Rng, wires: &[char], digit: &str) -> String {
+    let mut segments: Vec<char> = digit.chars().map(|c| wires[(c as u8 - b'a') as usize]).collect();
+    rng.shuffle(&mut segments);
+    segments.into_iter().collect()
+}
+
+/// A `size`x`size` grid of digits from `min` to 9.
+fn digit_grid(rng: &mut Rng, size: usize, min: i64) -> String {
+    lines((0..size).map(|_| (0..size).map(|_| rng.range(min, 9).to_string()).collect()))
+}
+
+/// A `size`x`size` grid of octopuses, which synchronize their flashes within 1000 steps.
+/// Random grids often never synchronize, so grids are partially uniform when needed.
+fn day11(rng: &mut Rng, size: usize) -> String {
+    let base = rng.range(0, 9) as u8;
+    for attempt in 0.. {
+        let noise = 100u64.saturating_sub(attempt * 5);
+        let mut grid: Vec<Vec<u8>> = (0..size).map(|_| (0..size).map(|_| if rng.chance(noise) { rng.range(0, 9) as u8 } else { base }).collect()).collect();
+        let input = lines(grid.iter().map(|r| r.iter().map(|n| n.to_string()).collect()));
+        if (0..1000).any(|_| octopus_step(&mut grid) == size * size) {
+            return input;
+        }
+    }
+    unreachable!()
+}
+
+/// Simulates a step of the octopuses, returns the number of flashes
+fn octopus_step(grid: &mut [Vec<u8>]) -> usize {
+    let n = grid.len();
+    let mut flashing = Vec::new();
+    for (y, row) in grid.iter_mut().enumerate() {
+        for (x, e) in row.iter_mut().enumerate() {
+            *e += 1;
+            if *e == 10 { flashing.push((x, y)); }
+        }
+    }
+    let mut flashes = 0;
+    while let Some((x, y)) = flashing.pop() {
+        flashes += 1;
+        for (nx, ny) in (x.saturating_sub(1)..usize::min(n, x + 2)).flat_map(|nx| (y.saturating_sub(1)..usize::min(n, y + 2)).map(move |ny| (nx, ny))) {
+            grid[ny][nx] += 1;
+            if grid[ny][nx] == 10 { flashing.push((nx, ny)); }
+        }
+    }
+    for e in grid.iter_mut().flatten() {
+        if *e > 9 { *e = 0; }
+    }
+    flashes
+}
+
+/// `size` lines of brackets, an odd number of them incomplete and the others corrupted.
+fn day10(rng: &mut Rng, size: usize) -> String {
+    const OPEN: [char; 4] = ['(', '[', '{', '<'];
+    const CLOSE: [char; 4] = [')', ']', '}', '>'];
+    // the middle completion score requires an odd number of incomplete lines
+    let incomplete = usize::max(1, size / 2) | 1;
+    let mut statuses: Vec<bool> = (0..usize::max(size, incomplete)).map(|i| i < incomplete).collect();
+    rng.shuffle(&mut statuses);
+    lines(statuses.into_iter().map(|is_incomplete| {
+        let mut line = String::new();
+        let mut stack = Vec::new();
+        for _ in 0..rng.range(20, 110) {
+            if !stack.is_empty() && rng.chance(50) {
+                line.push(CLOSE[stack.pop().unwrap()]);
+            } else {
+                let b = rng.below(4) as usize;
+                stack.push(b);
+                line.push(OPEN[b]);
+            }
+        }
+        if stack.is_empty() {
+            let b = rng.below(4) as usize;
+            stack.push(b);
+            line.push(OPEN[b]);
+        }
+        if is_incomplete {
+            // completion scores grow by a factor 5 per character, keep them within an u64
+            while stack.len() > 20 { line.push(CLOSE[stack.pop().unwrap()]); }
+        } else {
+            // close an open chunk with the wrong character
+            let expected = stack.pop().unwrap();
+            line.push(CLOSE[(expected + rng.range(1, 3) as usize) % 4]);
+            for _ in 0..rng.below(10) { line.push(*rng.choose(&CLOSE)); }
+        }
+        line
+    }))
+}
+
+/// A cave system with `size` small caves. Big caves are never connected to each other.
+fn day12(rng: &mut Rng, size: usize) -> String {
+    let small = unique_names(rng, usize::max(1, size), false);
+    let big = unique_names(rng, usize::max(1, size / 3), true);
+    let mut caves: Vec<&str> = small.iter().chain(big.iter()).map(|s| s.as_str()).collect();
+    caves.push("start");
+    caves.push("end");
+    let is_big = |c: &str| c.chars().all(|c| c.is_uppercase());
+    let valid = |c1: &str, c2: &str| c1 != c2 && !(is_big(c1) && is_big(c2)) && !(c1 == "start" && c2 == "end") && !(c1 == "end" && c2 == "start");
+
+    let mut edges: HashSet<(&str, &str)> = HashSet::new();
+    // connect every cave to at least one other cave, then add some more tunnels
+    let mut candidates: Vec<(&str, &str)> = caves.iter().map(|c| (*c, *rng.choose(&caves))).collect();
+    candidates.extend((0..2 * size).map(|_| (*rng.choose(&caves), *rng.choose(&caves))));
+    for (c1, c2) in candidates {
+        let c2 = if valid(c1, c2) { c2 } else if is_big(c1) || c1 == "start" || c1 == "end" { small[0].as_str() } else { continue };
+        if !edges.contains(&(c2, c1)) { edges.insert((c1, c2)); }
+    }
+    let mut edges: Vec<String> = edges.into_iter().map(|(a, b)| format!("{}-{}", a, b)).collect();
+    edges.sort();
+    rng.shuffle(&mut edges);
+    lines(edges.into_iter())
+}
+
+fn unique_names(rng: &mut Rng, n: usize, upper: bool) -> Vec<String> {
+    let mut names = HashSet::new();
+    let base = if upper { b'A' } else { b'a' };
+    let mut len = 2;
+    while names.len() < n {
+        let name: String = (0..len).map(|_| (base + rng.below(26) as u8) as char).collect();
+        if name != "start" && name != "end" { names.insert(name); }
+        if names.len() as u64 >= 26u64.pow(len as u32) / 2 { len += 1; }
+    }
+    let mut names: Vec<String> = names.into_iter().collect();
+    names.sort();
+    names
+}
+
+/// `size` dots on a transparent paper, which is folded 12 times to a 40x6 code.
+fn day13(rng: &mut Rng, size: usize) -> String {
+    let (mut w, mut h) = (40, 6);
+    let mut xfolds = Vec::new();
+    let mut yfolds = Vec::new();
+    for _ in 0..5 { xfolds.push(w); w = 2 * w + 1; }
+    for _ in 0..7 { yfolds.push(h); h = 2 * h + 1; }
+
+    let mut dots = HashSet::new();
+    while dots.len() < usize::min(size, ((w - 5) * (h - 7)) as usize) {
+        let (x, y) = (rng.range(0, w - 1), rng.range(0, h - 1));
+        if !xfolds.contains(&x) && !yfolds.contains(&y) { dots.insert((x, y)); }
+    }
+    let mut dots: Vec<String> = dots.into_iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+    dots.sort();
+    rng.shuffle(&mut dots);
+
+    // folds alternate between the axes, starting with the largest fold
+    let mut folds = Vec::new();
+    while !xfolds.is_empty() || !yfolds.is_empty() {
+        if let Some(x) = xfolds.pop() { folds.push(format!("fold along x={}", x)); }
+        if let Some(y) = yfolds.pop() { folds.push(format!("fold along y={}", y)); }
+    }
+    format!("{}\n{}", lines(dots.into_iter()), lines(folds.into_iter()))
+}
+
+/// A polymer template of length `size` with insertion rules for every pair of 10 elements.
+fn day14(rng: &mut Rng, size: usize) -> String {
+    let elements: Vec<char> = "BCFHKNOPSV".chars().collect();
+    let template: String = (0..usize::max(2, size)).map(|_| *rng.choose(&elements)).collect();
+    let mut rules = Vec::new();
+    for c1 in elements.iter() {
+        for c2 in elements.iter() {
+            rules.push(format!("{}{} -> {}", c1, c2, rng.choose(&elements)));
+        }
+    }
+    rng.shuffle(&mut rules);
+    format!("{}\n\n{}", template, lines(rules.into_iter()))
+}
+
+/// A transmission consisting of approximately `size` packets.
+fn day16(rng: &mut Rng, size: usize) -> String {
+    let mut bits = String::new();
+    let mut budget = usize::max(1, size);
+    packet(rng, &mut bits, &mut budget, false, true);
+    while !bits.len().is_multiple_of(4) { bits.push('0'); }
+    let hex: String = bits.as_bytes().chunks(4).map(|c| {
+        let n = u32::from_str_radix(std::str::from_utf8(c).unwrap(), 2).unwrap();
+        std::char::from_digit(n, 16).unwrap().to_ascii_uppercase()
+    }).collect();
+    lines(std::iter::once(hex))
+}
+
+/// Encodes a random packet in `bits`. `small` limits values, so products of them don't overflow.
+fn packet(rng: &mut Rng, bits: &mut String, budget: &mut usize, small: bool, outermost: bool) {
+    *budget = budget.saturating_sub(1);
+    bits.push_str(&format!("{:03b}", rng.below(8)));
+    if *budget == 0 || small || (!outermost && rng.chance(30)) {
+        // literal value
+        bits.push_str("100");
+        let value = if small { rng.below(16) } else { rng.below(4096) };
+        let groups = format!("{:012b}", value);
+        let groups: Vec<&str> = (0..3).map(|i| &groups[i * 4..i * 4 + 4]).collect();
+        for (i, g) in groups.iter().enumerate() {
+            bits.push(if i == 2 { '0' } else { '1' });
+            bits.push_str(g);
+        }
+        return;
+    }
+    // the outermost packet combines subpackets until the budget is spent
+    let type_id = if outermost { *rng.choose(&[0, 2, 3]) } else { *rng.choose(&[0, 1, 2, 3, 5, 6, 7]) };
+    bits.push_str(&format!("{:03b}", type_id));
+    let n = if type_id >= 5 { 2 } else { rng.range(1, 4) as usize };
+    let mut sub = String::new();
+    let mut count = 0;
+    while count < n || (outermost && *budget > 0 && count < 2047) {
+        packet(rng, &mut sub, budget, type_id == 1, false);
+        count += 1;
+    }
+    if rng.chance(50) && sub.len() < (1 << 15) {
+        bits.push_str(&format!("0{:015b}", sub.len()));
+    } else {
+        bits.push_str(&format!("1{:011b}", count));
+    }
+    bits.push_str(&sub);
+}
+
+/// A target area of roughly `size` units to the right of, and below the launcher.
+fn day17(rng: &mut Rng, size: usize) -> String {
+    let size = i64::max(10, size as i64);
+    let x1 = rng.range(size, 3 * size);
+    let x2 = x1 + rng.range(size / 10, size / 3);
+    let y1 = -rng.range(size, 2 * size);
+    let y2 = y1 + rng.range(size / 10, size / 3);
+    lines(std::iter::once(format!("target area: x={}..{}, y={}..{}", x1, x2, y1, y2)))
+}
+
+/// `size` reduced snailfish numbers.
+fn day18(rng: &mut Rng, size: usize) -> String {
+    fn snailfish(rng: &mut Rng, depth: usize) -> String {
+        if depth > 0 && (depth == 4 || rng.chance(35)) {
+            return rng.range(0, 9).to_string();
+        }
+        format!("[{},{}]", snailfish(rng, depth + 1), snailfish(rng, depth + 1))
+    }
+    lines((0..size).map(|_| snailfish(rng, 0)))
+}
+
+/// `size` scanners, which are placed such that consecutive scanners see 14 common beacons.
+fn day19(rng: &mut Rng, size: usize) -> String {
+    type Point = [i64; 3];
+    let in_range = |s: &Point, b: &Point| (0..3).all(|i| (s[i] - b[i]).abs() <= 1000);
+
+    let mut scanners: Vec<Point> = vec![[0, 0, 0]];
+    let mut beacons: Vec<Point> = Vec::new();
+    for i in 0..usize::max(1, size) {
+        if i > 0 {
+            let prev = scanners[i - 1];
+            let mut offset = [0; 3];
+            for o in offset.iter_mut() { *o = rng.range(-1200, 1200); }
+            offset[rng.below(3) as usize] = *rng.choose(&[-1, 1]) * rng.range(800, 1200);
+            let scanner = [prev[0] + offset[0], prev[1] + offset[1], prev[2] + offset[2]];
+            // common beacons in the overlapping region
+            for _ in 0..14 {
+                let mut b = [0; 3];
+                for (j, c) in b.iter_mut().enumerate() {
+                    let (lo, hi) = (i64::max(prev[j], scanner[j]) - 1000, i64::min(prev[j], scanner[j]) + 1000);
+                    *c = rng.range(lo, hi);
+                }
+                beacons.push(b);
+            }
+            scanners.push(scanner);
+        }
+        let s = scanners[i];
+        for _ in 0..12 {
+            beacons.push([rng.range(s[0] - 1000, s[0] + 1000), rng.range(s[1] - 1000, s[1] + 1000), rng.range(s[2] - 1000, s[2] + 1000)]);
+        }
+    }
+    beacons.sort();
+    beacons.dedup();
+
+    let mut reports = Vec::new();
+    for (i, s) in scanners.iter().enumerate() {
+        let (perm, signs) = random_rotation(rng);
+        let mut seen: Vec<String> = beacons.iter().filter(|b| in_range(s, b)).map(|b| {
+            let rel = [b[0] - s[0], b[1] - s[1], b[2] - s[2]];
+            let r: Vec<String> = (0..3).map(|j| (signs[j] * rel[perm[j]]).to_string()).collect();
+            r.join(",")
+        }).collect();
+        rng.shuffle(&mut seen);
+        reports.push(format!("--- scanner {} ---\n{}", i, lines(seen.into_iter())));
+    }
+    reports.join("\n")
+}
+
+/// One of the 24 rotations as an axis permutation and signs
+fn random_rotation(rng: &mut Rng) -> ([usize; 3], [i64; 3]) {
+    const PERMS: [([usize; 3], i64); 6] = [([0, 1, 2], 1), ([1, 2, 0], 1), ([2, 0, 1], 1), ([0, 2, 1], -1), ([2, 1, 0], -1), ([1, 0, 2], -1)];
+    let (perm, parity) = *rng.choose(&PERMS);
+    let (s0, s1) = (*rng.choose(&[-1, 1]), *rng.choose(&[-1, 1]));
+    // the determinant must be 1 for a proper rotation
+    (perm, [s0, s1, parity * s0 * s1])
+}
+
+/// An enhancement algorithm and a `size`x`size` image.
+fn day20(rng: &mut Rng, size: usize) -> String {
+    let mut alg: Vec<char> = (0..512).map(|_| if rng.chance(50) { '#' } else { '.' }).collect();
+    if alg[0] == '#' { alg[511] = '.'; } // otherwise infinitely many pixels stay lit
+    let alg: String = alg.into_iter().collect();
+    let img = (0..size).map(|_| (0..size).map(|_| if rng.chance(50) { '#' } else { '.' }).collect::<String>());
+    format!("{}\n\n{}", alg, lines(img))
+}
+
+fn day21(rng: &mut Rng) -> String {
+    format!("Player 1 starting position: {}\nPlayer 2 starting position: {}\n", rng.range(1, 10), rng.range(1, 10))
+}
+
+/// `size` reboot steps, the first quarter of them within the initialization region.
+fn day22(rng: &mut Rng, size: usize) -> String {
+    lines((0..size).map(|i| {
+        let (limit, width) = if i < usize::max(1, size / 4) { (50, 50) } else { (100000, 60000) };
+        let on = i == 0 || rng.chance(60);
+        let ranges: Vec<String> = ["x", "y", "z"].iter().map(|axis| {
+            let lo = rng.range(-limit, limit - 1);
+            let hi = i64::min(limit, lo + rng.range(1, width));
+            format!("{}={}..{}", axis, lo, hi)
+        }).collect();
+        format!("{} {}", if on { "on" } else { "off" }, ranges.join(","))
+    }))
+}
+
+/// A burrow with two amphipods of every type in random rooms.
+/// Note: not every arrangement can be organized after unfolding the diagram in part 2.
+fn day23(rng: &mut Rng) -> String {
+    let mut amphipods: Vec<char> = "AABBCCDD".chars().collect();
+    rng.shuffle(&mut amphipods);
+    let row = |a: &[char]| a.iter().map(|c| c.to_string()).collect::<Vec<String>>().join("#");
+    format!("#############\n#...........#\n###{}###\n  #{}#\n  #########\n", row(&amphipods[..4]), row(&amphipods[4..]))
+}
+
+/// A MONAD program with `size` pairs of chunks pushing and popping a digit from the `z` stack.
+fn day24(rng: &mut Rng, size: usize) -> String {
+    // random balanced sequence of pushes (true) and pops (false)
+    let mut order = Vec::with_capacity(2 * size);
+    let (mut pushes, mut open) = (0, 0);
+    while order.len() < 2 * size {
+        if pushes < size && (open == 0 || rng.chance(50)) {
+            order.push(true);
+            pushes += 1;
+            open += 1;
+        } else {
+            order.push(false);
+            open -= 1;
+        }
+    }
+    let mut stack = Vec::new();
+    let chunks = order.into_iter().map(|push| {
+        let (div, eql_offset, stack_offset) = if push {
+            let stack_offset = rng.range(1, 16);
+            stack.push(stack_offset);
+            (1, rng.range(10, 16), stack_offset)
+        } else {
+            // the digit of the pop chunk equals the digit of the push chunk plus an offset
+            let offset = rng.range(-8, 8);
+            (26, offset - stack.pop().unwrap(), rng.range(1, 16))
+        };
+        format!("inp w\nmul x 0\nadd x z\nmod x 26\ndiv z {}\nadd x {}\neql x w\neql x 0\nmul y 0\nadd y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y {}\nmul y x\nadd z y", div, eql_offset, stack_offset)
+    });
+    lines(chunks)
+}
+
+/// A `size`x`size` sea floor, densely filled with both herds of sea cucumbers.
+fn day25(rng: &mut Rng, size: usize) -> String {
+    lines((0..size).map(|_| (0..size).map(|_| match rng.below(100) {
+        0..=43 => '.',
+        44..=71 => '>',
+        _ => 'v',
+    }).collect::<String>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        for day in 1..26 {
+            assert_eq!(generate(day, 10, 7), generate(day, 10, 7), "day {}", day);
+        }
+        assert_ne!(generate(1, 10, 1), generate(1, 10, 2));
+    }
+
+    #[test]
+    fn test_generated_inputs_solve() {
+        // day 12 grows exponentially, day 23 is slow in debug builds
+        for day in (1..26).filter(|d| *d != 23) {
+            let size = match day { 4 | 9 | 15 | 20 | 25 => 20, 11 => 10, 12 => 4, 19 => 3, 24 => 7, _ => 40 };
+            let input = generate(day, size, 42);
+            let day_impl = crate::day(day);
+            if let Err(e) = day_impl.part1(&input).and(day_impl.part2(&input)) {
+                panic!("day {}: {}", day, e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_sizes() {
+        for day in 1..26 {
+            for size in 0..3 {
+                generate(day, size, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let mut rng = Rng::new(3);
+        for _ in 0..100 {
+            let (perm, signs) = random_rotation(&mut rng);
+            let mut p = perm;
+            p.sort();
+            assert_eq!(p, [0, 1, 2]);
+            assert!(signs.iter().all(|s| s.abs() == 1));
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * Normalization of puzzle inputs
+ *
+ * Inputs saved on Windows or copied from the browser may contain carriage returns,
+ * a byte order mark, trailing whitespace or trailing blank lines.
+ * `Input` removes these, so every day sees the same layout.
+ */
+
+/// A normalized puzzle input: `\n` line endings, no BOM, no trailing whitespace on any line,
+/// no trailing blank lines and (unless empty) a single terminating newline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input(String);
+
+impl Input {
+    pub fn new(raw: &str) -> Self {
+        let raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+        let raw = raw.replace("\r\n", "\n");
+        let mut text = String::with_capacity(raw.len() + 1);
+        for line in raw.split(['\n', '\r']) {
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        while text.ends_with('\n') { text.pop(); }
+        if !text.is_empty() { text.push('\n'); }
+        Input(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Input {
+    fn from(raw: &str) -> Self {
+        Input::new(raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(Input::new("1\r\n2\r\n\r\n").as_str(), "1\n2\n");
+        assert_eq!(Input::new("\u{feff}1\n2").as_str(), "1\n2\n");
+        assert_eq!(Input::new("1 \t\n2\n\n\n  \n").as_str(), "1\n2\n");
+        assert_eq!(Input::new("1\r2\r").as_str(), "1\n2\n");
+        assert_eq!(Input::new("\r\n\n").as_str(), "");
+    }
+}
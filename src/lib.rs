@@ -4,12 +4,25 @@
  */
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufRead, Write};
 use std::time::{Duration, Instant};
 
 mod days;
 mod web;
 mod utils;
+pub mod input;
+pub mod visualize;
+pub mod gen;
+pub mod differential;
+pub mod stats;
+pub mod variants;
+
+use input::Input;
+use stats::Stats;
+use visualize::{Frame, Visualize};
+
+pub use utils::ParseError;
+pub use utils::search;
 
 /// Part enum for puzzle part 1 or part 2
 pub enum Part {
@@ -28,11 +41,17 @@ impl From<&Part> for u32 {
 
 /// AOCDay trait
 pub trait AOCDay {
-    fn part1(&self, input: &str) -> Option<String>;
-    fn part2(&self, input: &str) -> Option<String>;
+    fn part1(&self, input: &str) -> Result<String, ParseError>;
+    fn part2(&self, input: &str) -> Result<String, ParseError>;
     fn get_num(&self) -> u32;
 }
 
+/// Days which can solve an input while reading it line by line, without holding it in memory
+pub trait StreamingDay {
+    fn part1_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError>;
+    fn part2_stream(&self, input: &mut dyn BufRead) -> Result<String, ParseError>;
+}
+
 
 pub fn get_day_header(day: u32) -> String {
     let filepath = utils::get_description_filepath(day);
@@ -118,29 +137,65 @@ pub fn get_day_description(num: u32) -> String {
 }
 
 /// Solves a specific day.
-pub fn solve(num: u32, part: Part) -> Option<(String, Duration)> {
+/// Errors are rendered against the offending line of the input.
+pub fn solve(num: u32, part: Part) -> Result<(String, Duration), String> {
+    solve_with_stats(num, part).map(|(sol, dur, _)| (sol, dur))
+}
+
+/// Solves a specific day, collecting the statistics recorded by the solution.
+pub fn solve_with_stats(num: u32, part: Part) -> Result<(String, Duration, Stats), String> {
     let day = day(num);
-    let input = get_day_input(num);
-
-    match part {
-        Part::One => {
-            let now = Instant::now();
-            let sol = day.part1(&input);
-            let dur = now.elapsed();
-            if sol.is_some() {
-                return Some((sol.unwrap(), dur));
-            }
-            None
-        },
-        Part::Two => {
-            let now = Instant::now();
-            let sol = day.part2(&input);
-            let dur = now.elapsed();
-            if sol.is_some() {
-                return Some((sol.unwrap(), dur));
-            }
-            None
-        }
+    let input = Input::new(&get_day_input(num));
+
+    let now = Instant::now();
+    let (sol, stats) = stats::collect(|| match part {
+        Part::One => day.part1(input.as_str()),
+        Part::Two => day.part2(input.as_str()),
+    });
+    let dur = now.elapsed();
+    sol.map(|sol| (sol, dur, stats)).map_err(|e| e.render(input.as_str()))
+}
+
+/// Frames of the simulation of a specific day, if the day can be visualized.
+/// Errors are rendered against the input.
+pub fn frames(num: u32) -> Option<Result<Box<dyn Iterator<Item = Frame>>, String>> {
+    let visualizer = visualizer(num)?;
+    let input = Input::new(&get_day_input(num));
+    Some(visualizer.frames(input.as_str()).map_err(|e| e.render(input.as_str())))
+}
+
+/// Solves a specific day while reading the input, if the day supports streaming.
+/// Errors are reported with their line number, as the input is gone by then.
+pub fn solve_stream(num: u32, part: Part, input: &mut dyn BufRead) -> Option<Result<(String, Duration), String>> {
+    let day = streaming_day(num)?;
+    let now = Instant::now();
+    let sol = match part {
+        Part::One => day.part1_stream(input),
+        Part::Two => day.part2_stream(input),
+    };
+    let dur = now.elapsed();
+    Some(sol.map(|sol| (sol, dur)).map_err(|e| e.to_string()))
+}
+
+fn streaming_day(num: u32) -> Option<Box<dyn StreamingDay>> {
+    match num {
+        1 => Some(Box::new(days::day1::get())),
+        2 => Some(Box::new(days::day2::get())),
+        3 => Some(Box::new(days::day3::get())),
+        10 => Some(Box::new(days::day10::get())),
+        _ => None,
+    }
+}
+
+fn visualizer(num: u32) -> Option<Box<dyn Visualize>> {
+    match num {
+        9 => Some(Box::new(days::day9::get())),
+        11 => Some(Box::new(days::day11::get())),
+        13 => Some(Box::new(days::day13::get())),
+        17 => Some(Box::new(days::day17::get())),
+        20 => Some(Box::new(days::day20::get())),
+        25 => Some(Box::new(days::day25::get())),
+        _ => None,
     }
 }
 
@@ -177,5 +232,7 @@ fn day(num:u32) -> Box<dyn AOCDay> {
 
 
 pub fn run() {
-    solve(1, Part::One);
+    if let Err(e) = solve(1, Part::One) {
+        eprintln!("{}", e);
+    }
 }
@@ -3,7 +3,10 @@
  * Provide helper functions for parsing inputs
  */
 
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
 use std::num::ParseIntError;
+use std::path::Path;
 use std::time::Duration;
 
 use clap::{App, Arg, ArgMatches};
@@ -11,6 +14,7 @@ use clap::{arg};
 
 use aoc_2021;
 use aoc_2021::{Part};
+use aoc_2021::stats::quote;
 
 
 fn main() {
@@ -28,7 +32,13 @@ fn main() {
             .arg(arg!([day] "Day number").required(true))
             .arg(Arg::new("bench")
                 .short('b')
-                .help("Print Time Taken")))
+                .help("Print Time Taken"))
+            .arg(Arg::new("stats")
+                .long("stats")
+                .help("Print the statistics recorded by the solutions"))
+            .arg(Arg::new("json")
+                .long("json")
+                .help("Print solutions, timings and statistics as JSON")))
         .subcommand(
             App::new("solve-all")
             .about("Tries to solve all days sequentially")
@@ -38,6 +48,19 @@ fn main() {
             .arg(Arg::new("bench")
                 .short('b')
                 .help("Print Time Taken")))
+        .subcommand(
+            App::new("stream")
+            .about("Solves a day while reading the input, for inputs too large for memory (days 1, 2, 3 and 10)")
+            .arg(arg!([day] "Day number").required(true))
+            .arg(arg!([file] "Input file, - reads standard input").required(true))
+            .arg(Arg::new("part")
+                .short('p')
+                .long("part")
+                .takes_value(true)
+                .help("Only solve this part, required when reading standard input"))
+            .arg(Arg::new("bench")
+                .short('b')
+                .help("Print Time Taken")))
         .subcommand(
             App::new("input")
             .about("Retrieves the input for a day")
@@ -47,7 +70,165 @@ fn main() {
                 .help("Store the input as a file in inputs directory")))
         .subcommand(
             App::new("bench")
-            .about("Benchmarks all of the puzzles (non-scientifically)"));
+            .about("Benchmarks all of the puzzles (non-scientifically)"))
+        .subcommand(
+            App::new("visualize")
+            .about("Animates the simulation of a day in the terminal")
+            .arg(arg!([day] "Day number").required(true))
+            .arg(Arg::new("delay")
+                .short('d')
+                .long("delay")
+                .takes_value(true)
+                .default_value("100")
+                .help("Milliseconds between frames"))
+            .arg(Arg::new("frames")
+                .short('n')
+                .long("frames")
+                .takes_value(true)
+                .help("Maximum number of frames"))
+            .arg(Arg::new("out")
+                .short('o')
+                .long("out")
+                .takes_value(true)
+                .help("Write frames as PBM/PPM images into a directory instead")))
+        .subcommand(
+            App::new("gen")
+            .about("Generates a random valid input for a day")
+            .arg(arg!([day] "Day number").required(true))
+            .arg(Arg::new("size")
+                .long("size")
+                .takes_value(true)
+                .help("Size of the input, defaults to roughly the official input size"))
+            .arg(Arg::new("seed")
+                .long("seed")
+                .takes_value(true)
+                .default_value("0")
+                .help("Seed of the random generator")))
+        .subcommand(
+            App::new("diff")
+            .about("Compares solutions against naive reference implementations on random inputs")
+            .arg(arg!([day] "Day number, all days with a reference if omitted"))
+            .arg(Arg::new("cases")
+                .long("cases")
+                .takes_value(true)
+                .default_value("100")
+                .help("Number of random inputs per part"))
+            .arg(Arg::new("seed")
+                .long("seed")
+                .takes_value(true)
+                .default_value("0")
+                .help("Seed of the first random input")))
+        .subcommand(
+            App::new("sonar")
+            .about("Analyzes the sonar sweep of day 1 with sliding windows of any size")
+            .arg(Arg::new("window")
+                .long("window")
+                .takes_value(true)
+                .default_value("3")
+                .help("Number of measurements per window")))
+        .subcommand(
+            App::new("submarine")
+            .about("Exports the trajectory of the submarine of day 2 as CSV")
+            .arg(Arg::new("aimed")
+                .short('a')
+                .long("aimed")
+                .help("Follow the aim like in part 2")))
+        .subcommand(
+            App::new("diagnostics")
+            .about("Explains the ratings of day 3 step by step"))
+        .subcommand(
+            App::new("bingo")
+            .about("Finds the k-th board to win at bingo on day 4")
+            .arg(Arg::new("winner")
+                .long("winner")
+                .takes_value(true)
+                .default_value("1")
+                .help("Position of the board among the winners, counting from 1"))
+            .arg(Arg::new("diagonals")
+                .short('d')
+                .long("diagonals")
+                .help("Let complete diagonals of square boards win too")))
+        .subcommand(
+            App::new("vents")
+            .about("Counts the overlaps of the vent lines of day 5 at any angle")
+            .arg(Arg::new("angles")
+                .long("angles")
+                .takes_value(true)
+                .possible_values(["straight", "diagonal", "any"])
+                .default_value("diagonal")
+                .help("Lines to take into account"))
+            .arg(Arg::new("exact")
+                .long("exact")
+                .help("Intersect the lines exactly instead of rasterizing them"))
+            .arg(Arg::new("heatmap")
+                .long("heatmap")
+                .help("Show the number of lines at every point of small maps")))
+        .subcommand(
+            App::new("crabs")
+            .about("Finds the cheapest alignment of the crabs of day 7 for other fuel costs")
+            .arg(Arg::new("cost")
+                .long("cost")
+                .takes_value(true)
+                .possible_values(["linear", "triangular", "quadratic"])
+                .default_value("triangular")
+                .help("Fuel needed per distance")))
+        .subcommand(
+            App::new("displays")
+            .about("Shows the wiring of every seven segment display of day 8"))
+        .subcommand(
+            App::new("lint")
+            .about("Shows where the bracket lines of day 10, or of another file, are corrupted or incomplete")
+            .arg(arg!([file] "File of bracket lines, the input of day 10 if not given"))
+            .arg(Arg::new("brackets")
+                .long("brackets")
+                .takes_value(true)
+                .default_value("()[]{}<>")
+                .help("Pairs of opening and closing brackets")))
+        .subcommand(
+            App::new("lanternfish")
+            .about("Counts the lanternfish of day 6 after any number of days")
+            .arg(Arg::new("days")
+                .long("days")
+                .takes_value(true)
+                .default_value("256")
+                .help("Number of days to simulate"))
+            .arg(Arg::new("cycle")
+                .long("cycle")
+                .takes_value(true)
+                .default_value("7")
+                .help("Number of days between two births of a fish"))
+            .arg(Arg::new("delay")
+                .long("delay")
+                .takes_value(true)
+                .default_value("2")
+                .help("Number of extra days until the first birth of a newborn"))
+            .arg(Arg::new("series")
+                .long("series")
+                .help("Print the number of fishes on every day as CSV"))
+            .arg(Arg::new("modulo")
+                .short('m')
+                .long("modulo")
+                .help("Compute the answer modulo 1000000007, in logarithmic time")))
+        .subcommand(
+            App::new("polymer")
+            .about("Grows the polymer of day 14 for any number of steps")
+            .arg(Arg::new("steps")
+                .long("steps")
+                .takes_value(true)
+                .default_value("40")
+                .help("Number of pair insertion steps"))
+            .arg(Arg::new("modulo")
+                .short('m')
+                .long("modulo")
+                .help("Count every element modulo 1000000007, in logarithmic time")))
+        .subcommand(
+            App::new("dirac")
+            .about("Plays Dirac Dice of day 21 up to any score")
+            .arg(Arg::new("score")
+                .long("score")
+                .takes_value(true)
+                .default_value("21")
+                .help("Score required to win")));
 
     let parser = app.get_matches_mut();
 
@@ -56,8 +237,26 @@ fn main() {
         Some(("desc", sub_m)) => { description(sub_m) },
         Some(("solve", sub_m)) => { solve(sub_m)},
         Some(("solve-all", sub_m)) => { solve_all(sub_m)},
+        Some(("stream", sub_m)) => { stream(sub_m)},
         Some(("input", sub_m)) => { input(sub_m)},
         Some(("bench", _)) => { benchmark()},
+        Some(("visualize", sub_m)) => { visualize(sub_m)},
+        Some(("gen", sub_m)) => { generate(sub_m)},
+        Some(("diff", sub_m)) => { differential(sub_m)},
+        Some(("sonar", sub_m)) => { variant(sub_m, "window", aoc_2021::variants::sonar)},
+        Some(("submarine", sub_m)) => { show(aoc_2021::variants::submarine(sub_m.is_present("aimed")))},
+        Some(("diagnostics", _)) => { show(aoc_2021::variants::diagnostics())},
+        Some(("bingo", sub_m)) => { variant(sub_m, "winner", |k| aoc_2021::variants::bingo(k, sub_m.is_present("diagonals")))},
+        Some(("vents", sub_m)) => { show(aoc_2021::variants::vents(sub_m.value_of("angles").unwrap(), sub_m.is_present("exact"), sub_m.is_present("heatmap")))},
+        Some(("crabs", sub_m)) => { show(aoc_2021::variants::crabs(sub_m.value_of("cost").unwrap()))},
+        Some(("displays", _)) => { show(aoc_2021::variants::displays())},
+        Some(("lint", sub_m)) => { show(aoc_2021::variants::lint(sub_m.value_of("file"), sub_m.value_of("brackets").unwrap()))},
+        Some(("lanternfish", sub_m)) => { variant(sub_m, "days", |days| {
+            let (cycle, delay) = (number(sub_m, "cycle")?, number(sub_m, "delay")?);
+            aoc_2021::variants::lanternfish(days, cycle, delay, sub_m.is_present("modulo"), sub_m.is_present("series"))
+        })},
+        Some(("polymer", sub_m)) => { variant(sub_m, "steps", |steps| aoc_2021::variants::polymer(steps, sub_m.is_present("modulo")))},
+        Some(("dirac", sub_m)) => { variant(sub_m, "score", aoc_2021::variants::dirac)},
         _ => { 
             eprintln!("Invalid Command, provide -h for help"); 
             app.print_help().unwrap();
@@ -76,8 +275,9 @@ fn solve_all(_matches: &ArgMatches) {
     for i in 1..26 {
         println!("{}", aoc_2021::get_day_header(i));
         let solution_1 = aoc_2021::solve(i, Part::One);
-        if solution_1.is_none() {
+        if let Err(e) = &solution_1 {
             println!("- Part 1: ❌");
+            if print_solution { println!("{}", e); }
         }else {
             let (sol, dur) = solution_1.unwrap();
             part1_dur += dur;
@@ -95,8 +295,9 @@ fn solve_all(_matches: &ArgMatches) {
         }
 
         let solution_2 = aoc_2021::solve(i, Part::Two);
-        if solution_2.is_none() {
+        if let Err(e) = &solution_2 {
             println!("- Part 2: ❌");
+            if print_solution { println!("{}", e); }
         }else {
             let (sol, dur) = solution_2.unwrap();
             part2_dur += dur;
@@ -130,7 +331,7 @@ fn benchmark() {
     for i in 1..26 {
         print!("|day {:<14}", i);
         let solution1 = aoc_2021::solve(i, Part::One);
-        if solution1.is_none() {
+        if solution1.is_err() {
             print!("|{:18}", "");
         }else {
             let dur = solution1.unwrap().1;
@@ -138,7 +339,7 @@ fn benchmark() {
             print!("|{:18}", duration_to_string(dur));
         }
         let solution2 = aoc_2021::solve(i, Part::Two);
-        if solution2.is_none() {
+        if solution2.is_err() {
             print!("|{:18}|", "");
         }else {
             let dur = solution2.unwrap().1;
@@ -177,19 +378,25 @@ fn description(matches: &ArgMatches) {
 /// Solves the puzzle for a specific day
 fn solve(matches: &ArgMatches) {
     let print_dur = matches.is_present("bench");
+    let print_stats = matches.is_present("stats");
 
     let day = retrieve_day_arg(matches);
     if day.is_none() {
         return;
     }
     let num = day.unwrap();
-    let solution_1 = aoc_2021::solve(num, Part::One);
-    if solution_1.is_none() {
-        println!("Part 1 for Day {} has not been solved yet.", num);
+    if matches.is_present("json") {
+        println!("{{\"day\": {}, \"parts\": [{}, {}]}}", num, part_json(num, Part::One), part_json(num, Part::Two));
+        return;
+    }
+
+    let solution_1 = aoc_2021::solve_with_stats(num, Part::One);
+    if let Err(e) = &solution_1 {
+        println!("Part 1 for Day {} could not be solved:\n{}", num, e);
         return;
     }
 
-    let (sol, dur) = solution_1.unwrap();
+    let (sol, dur, stats) = solution_1.unwrap();
     let dur_str;
     if print_dur {
         dur_str = format!(" ({})", duration_to_string(dur));
@@ -198,14 +405,15 @@ fn solve(matches: &ArgMatches) {
 
     }
     println!("Part 1{}:\n{}", dur_str, sol);
+    if print_stats { print!("{}", stats); }
 
-    let solution_2 = aoc_2021::solve(num, Part::Two);
-    if solution_2.is_none() {
-        println!("Part 2 for Day {} has not been solved yet.", num);
+    let solution_2 = aoc_2021::solve_with_stats(num, Part::Two);
+    if let Err(e) = &solution_2 {
+        println!("Part 2 for Day {} could not be solved:\n{}", num, e);
         return;
     }
 
-    let (sol, dur) = solution_2.unwrap();
+    let (sol, dur, stats) = solution_2.unwrap();
     let dur_str;
     if print_dur {
         dur_str = format!(" ({})", duration_to_string(dur));
@@ -215,6 +423,68 @@ fn solve(matches: &ArgMatches) {
     }
 
     println!("Part 2:{}\n{}", dur_str, sol);
+    if print_stats { print!("{}", stats); }
+}
+
+/// Solves a part as a JSON object, holding either the solution or the error
+fn part_json(num: u32, part: Part) -> String {
+    let n = u32::from(&part);
+    match aoc_2021::solve_with_stats(num, part) {
+        Ok((sol, dur, stats)) => format!("{{\"part\": {}, \"solution\": {}, \"micros\": {}, \"stats\": {}}}",
+            n, quote(&sol), dur.as_micros(), stats.to_json()),
+        Err(e) => format!("{{\"part\": {}, \"error\": {}}}", n, quote(&e)),
+    }
+}
+
+/// Solves the puzzle for a specific day, reading the input from a file or standard input
+fn stream(matches: &ArgMatches) {
+    let print_dur = matches.is_present("bench");
+    let day = retrieve_day_arg(matches);
+    if day.is_none() {
+        return;
+    }
+    let num = day.unwrap();
+    let file = matches.value_of("file").unwrap();
+    let parts = match matches.value_of("part") {
+        None if file == "-" => {
+            eprintln!("Standard input can only be read once, please provide a part");
+            return;
+        },
+        None => vec![Part::One, Part::Two],
+        Some("1") => vec![Part::One],
+        Some("2") => vec![Part::Two],
+        Some(_) => {
+            eprintln!("Part must be 1 or 2");
+            return;
+        }
+    };
+
+    for part in parts {
+        let n = u32::from(&part);
+        // every part reads the input from the start
+        let mut reader: Box<dyn BufRead> = if file == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            match File::open(file) {
+                Ok(f) => Box::new(BufReader::new(f)),
+                Err(e) => {
+                    eprintln!("Could not open {}: {}", file, e);
+                    return;
+                }
+            }
+        };
+        match aoc_2021::solve_stream(num, part, &mut reader) {
+            None => {
+                println!("Day {} can not be streamed.", num);
+                return;
+            },
+            Some(Err(e)) => println!("Part {} for Day {} could not be solved:\n{}", n, num, e),
+            Some(Ok((sol, dur))) => {
+                let dur_str = if print_dur { format!(" ({})", duration_to_string(dur)) } else { String::from("") };
+                println!("Part {}{}:\n{}", n, dur_str, sol);
+            }
+        }
+    }
 }
 
 /// Retrieves the input for a puzzle
@@ -228,6 +498,149 @@ fn input(matches: &ArgMatches) {
     print!("{}", input);
 }
 
+/// Visualizes the simulation of a puzzle
+fn visualize(matches: &ArgMatches) {
+    let day = retrieve_day_arg(matches);
+    if day.is_none() {
+        return;
+    }
+    let num = day.unwrap();
+    let delay: u64 = match matches.value_of("delay").unwrap().parse() {
+        Ok(delay) => delay,
+        Err(_) => {
+            eprintln!("Delay must be a number of milliseconds");
+            return;
+        }
+    };
+    let limit: usize = match matches.value_of("frames").map(|n| n.parse()) {
+        None => usize::MAX,
+        Some(Ok(n)) => n,
+        Some(Err(_)) => {
+            eprintln!("Number of frames must be a positive number");
+            return;
+        }
+    };
+
+    let frames = match aoc_2021::frames(num) {
+        Some(Ok(frames)) => frames.take(limit),
+        Some(Err(e)) => {
+            println!("Day {} could not be visualized:\n{}", num, e);
+            return;
+        }
+        None => {
+            println!("Day {} can not be visualized.", num);
+            return;
+        }
+    };
+
+    let result = match matches.value_of("out") {
+        Some(dir) => aoc_2021::visualize::dump(frames, Path::new(dir)),
+        None => aoc_2021::visualize::animate(frames, Duration::from_millis(delay)),
+    };
+    match result {
+        Ok(count) => println!("Rendered {} frames", count),
+        Err(e) => eprintln!("Could not render frames: {}", e),
+    }
+}
+
+/// Generates a random input for a puzzle
+fn generate(matches: &ArgMatches) {
+    let day = retrieve_day_arg(matches);
+    if day.is_none() {
+        return;
+    }
+    let num = day.unwrap();
+    let size = match matches.value_of("size").map(|n| n.parse()) {
+        None => aoc_2021::gen::default_size(num),
+        Some(Ok(n)) => n,
+        Some(Err(_)) => {
+            eprintln!("Size must be a positive number");
+            return;
+        }
+    };
+    let seed: u64 = match matches.value_of("seed").unwrap().parse() {
+        Ok(seed) => seed,
+        Err(_) => {
+            eprintln!("Seed must be a positive number");
+            return;
+        }
+    };
+    print!("{}", aoc_2021::gen::generate(num, size, seed));
+}
+
+/// Compares solutions against their reference implementations
+fn differential(matches: &ArgMatches) {
+    let day = if matches.is_present("day") {
+        match retrieve_day_arg(matches) {
+            Some(num) => Some(num),
+            None => return,
+        }
+    } else {
+        None
+    };
+    let cases: u64 = match matches.value_of("cases").unwrap().parse() {
+        Ok(cases) => cases,
+        Err(_) => {
+            eprintln!("Number of cases must be a positive number");
+            return;
+        }
+    };
+    let seed: u64 = match matches.value_of("seed").unwrap().parse() {
+        Ok(seed) => seed,
+        Err(_) => {
+            eprintln!("Seed must be a positive number");
+            return;
+        }
+    };
+
+    let subjects: Vec<_> = aoc_2021::differential::subjects().into_iter()
+        .filter(|s| day.is_none() || day == Some(s.day))
+        .collect();
+    if subjects.is_empty() {
+        println!("Day {} has no reference implementation.", day.unwrap());
+        return;
+    }
+    // panics of the solutions are reported as counterexamples
+    std::panic::set_hook(Box::new(|_| {}));
+    for subject in subjects {
+        match subject.check(cases, seed) {
+            None => println!("Day {} part {}: ✅ ({} cases)", subject.day, subject.part, cases),
+            Some(c) => {
+                println!("Day {} part {}: ❌ (seed {})", subject.day, subject.part, c.seed);
+                println!("Input:\n{}", c.input);
+                println!("Expected: {}", c.expected);
+                println!("Actual: {}", c.actual);
+            }
+        }
+    }
+    let _ = std::panic::take_hook();
+}
+
+/// Prints the solution of a puzzle variant
+fn show(solution: Result<String, String>) {
+    match solution {
+        Ok(sol) => println!("{}", sol.trim_end()),
+        Err(e) => println!("Could not be solved:\n{}", e),
+    }
+}
+
+/// The value of a numeric parameter
+fn number(matches: &ArgMatches, param: &str) -> Result<u64, String> {
+    matches.value_of(param).unwrap().parse().map_err(|_| format!("The {} must be a positive number", param))
+}
+
+/// Solves a puzzle variant with a single numeric parameter
+fn variant(matches: &ArgMatches, param: &str, solve: impl Fn(u64) -> Result<String, String>) {
+    let n = match number(matches, param) {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    show(solve(n));
+}
+
 // ===== helper functions =====
 /// Retrieves the day number argument from user input
 fn retrieve_day_arg(matches: &ArgMatches) -> Option<u32> {
@@ -0,0 +1,177 @@
+/*
+ * Statistics of a solution
+ *
+ * Days record named counters and timed spans through the free functions of this module. These write
+ * into the statistics of the current thread while they are being collected and do nothing otherwise,
+ * such that solutions do not need to pass a context around.
+ */
+
+use std::cell::RefCell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A single named measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    Count(u64), // sum of all recorded values
+    Peak(u64), // maximum of all recorded values
+    Span {total: Duration, calls: u64}, // time spent within a span
+}
+
+/// Named measurements in the order they were first recorded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    entries: Vec<(&'static str, Entry)>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Stats>> = const { RefCell::new(None) };
+}
+
+impl Stats {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Entry> {
+        self.entries.iter().find(|(n, _)| *n == name).map(|(_, entry)| *entry)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(&'static str, Entry)> {
+        self.entries.iter()
+    }
+
+    /// Merges `entry` into the entry called `name`, entries of a different kind are replaced.
+    fn record(&mut self, name: &'static str, entry: Entry) {
+        let existing = match self.entries.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => existing,
+            None => {
+                self.entries.push((name, entry));
+                return;
+            }
+        };
+        *existing = match (*existing, entry) {
+            (Entry::Count(a), Entry::Count(b)) => Entry::Count(a + b),
+            (Entry::Peak(a), Entry::Peak(b)) => Entry::Peak(a.max(b)),
+            (Entry::Span {total: t1, calls: c1}, Entry::Span {total: t2, calls: c2}) => Entry::Span {total: t1 + t2, calls: c1 + c2},
+            (_, entry) => entry,
+        };
+    }
+
+    /// The statistics as a JSON object, spans are given in microseconds.
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self.entries.iter().map(|(name, entry)| {
+            let value = match entry {
+                Entry::Count(n) | Entry::Peak(n) => n.to_string(),
+                Entry::Span {total, calls} => format!("{{\"micros\": {}, \"calls\": {}}}", total.as_micros(), calls),
+            };
+            format!("{}: {}", quote(name), value)
+        }).collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, entry) in self.entries.iter() {
+            match entry {
+                Entry::Count(n) => writeln!(f, "{}: {}", name, n)?,
+                Entry::Peak(n) => writeln!(f, "{}: {} (peak)", name, n)?,
+                Entry::Span {total, calls} => writeln!(f, "{}: {:?} ({} calls)", name, total, calls)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn record(name: &'static str, entry: Entry) {
+    CURRENT.with(|current| {
+        if let Some(stats) = current.borrow_mut().as_mut() {
+            stats.record(name, entry);
+        }
+    });
+}
+
+/// Adds `n` to the counter `name`
+pub fn count(name: &'static str, n: u64) {
+    record(name, Entry::Count(n));
+}
+
+/// Raises the peak `name` to `n`, if `n` is larger
+pub fn peak(name: &'static str, n: u64) {
+    record(name, Entry::Peak(n));
+}
+
+/// Times the span `name` until the returned guard is dropped
+pub fn span(name: &'static str) -> Span {
+    Span {name, start: Instant::now()}
+}
+
+/// Guard of a running span
+pub struct Span {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        record(self.name, Entry::Span {total: self.start.elapsed(), calls: 1});
+    }
+}
+
+/// Runs `f` and collects everything it records. Statistics collected by an enclosing call
+/// do not include those of `f`.
+pub fn collect<R>(f: impl FnOnce() -> R) -> (R, Stats) {
+    let outer = CURRENT.with(|current| current.replace(Some(Stats::default())));
+    let result = f();
+    let stats = CURRENT.with(|current| current.replace(outer)).unwrap_or_default();
+    (result, stats)
+}
+
+/// A string as a JSON string literal
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect() {
+        count("ignored", 1);
+        let ((), stats) = collect(|| {
+            count("nodes", 2);
+            peak("queue", 5);
+            {
+                let _span = span("search");
+                count("nodes", 3);
+                peak("queue", 4);
+            }
+            let ((), inner) = collect(|| count("inner", 1));
+            assert_eq!(inner.get("inner"), Some(Entry::Count(1)));
+        });
+        assert_eq!(stats.get("nodes"), Some(Entry::Count(5)));
+        assert_eq!(stats.get("queue"), Some(Entry::Peak(5)));
+        assert!(matches!(stats.get("search"), Some(Entry::Span {calls: 1, ..})));
+        assert_eq!(stats.get("inner"), None);
+        assert_eq!(stats.get("ignored"), None);
+        assert!(stats.to_json().starts_with("{\"nodes\": 5, \"queue\": 5, \"search\": {\"micros\": "));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a \"b\"\\\n\t"), "\"a \\\"b\\\"\\\\\\n\\u0009\"");
+    }
+}
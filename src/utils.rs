@@ -7,6 +7,17 @@ use std::io;
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 
+pub mod bigint;
+pub mod bits;
+pub mod geom;
+pub mod grid;
+pub mod linalg;
+pub mod memo;
+pub mod parse;
+pub mod search;
+
+pub use parse::ParseError;
+
 /// Finds the root directory of the project.
 pub fn get_project_path() -> io::Result<PathBuf> {
     let cur_dir = env::current_dir()?;
@@ -62,6 +73,34 @@ pub fn get_description_filepath(num: u32) -> io::Result<PathBuf> {
     Ok(input_dir)
 }
 
+#[cfg(test)]
+pub use serialize::{Commas, Lines};
+
+/// Serializers which turn parsed items back into puzzle input, for the round trip tests
+#[cfg(test)]
+mod serialize {
+    use std::fmt;
+
+    /// Serializes items one per line
+    pub struct Lines<'a, T>(pub &'a [T]);
+
+    impl<T: fmt::Display> fmt::Display for Lines<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.iter().try_for_each(|item| writeln!(f, "{}", item))
+        }
+    }
+
+    /// Serializes items as a single comma separated line
+    pub struct Commas<'a, T>(pub &'a [T]);
+
+    impl<T: fmt::Display> fmt::Display for Commas<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let items: Vec<String> = self.0.iter().map(|item| item.to_string()).collect();
+            writeln!(f, "{}", items.join(","))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
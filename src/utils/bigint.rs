@@ -0,0 +1,212 @@
+/*
+ * Arbitrary precision natural numbers
+ *
+ * Counting simulations are generic over `Natural`, such that they run on `u64` for the puzzles
+ * and on `BigUint` once the counts outgrow 64 bits.
+ */
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+/// Non-negative integers with the operations needed for counting
+pub trait Natural: Clone + Ord + fmt::Debug + fmt::Display + From<u64> + Add<Output = Self> + AddAssign + Sub<Output = Self> + Mul<Output = Self> {
+    fn zero() -> Self {
+        Self::from(0)
+    }
+
+    fn one() -> Self {
+        Self::from(1)
+    }
+}
+
+impl Natural for u64 {}
+
+/// Natural number of unbounded size, stored as base 2^32 digits with the least significant first.
+/// The most significant digit is never zero, such that zero has no digits.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    digits: Vec<u32>,
+}
+
+impl Natural for BigUint {}
+
+impl BigUint {
+    fn normalize(mut self) -> Self {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        self
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Divides in place by a single digit, returns the remainder
+    fn div_rem_digit(&mut self, divisor: u32) -> u32 {
+        let mut rem: u64 = 0;
+        for d in self.digits.iter_mut().rev() {
+            let cur = (rem << 32) | *d as u64;
+            *d = (cur / divisor as u64) as u32;
+            rem = cur % divisor as u64;
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        rem as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        BigUint {digits: vec![n as u32, (n >> 32) as u32]}.normalize()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.digits.len().cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.digits.len() < other.digits.len() {
+            self.digits.resize(other.digits.len(), 0);
+        }
+        let mut carry = 0;
+        for (i, d) in self.digits.iter_mut().enumerate() {
+            let sum = *d as u64 + other.digits.get(i).copied().unwrap_or(0) as u64 + carry;
+            *d = sum as u32;
+            carry = sum >> 32;
+            if carry == 0 && i >= other.digits.len() { break; }
+        }
+        if carry > 0 {
+            self.digits.push(carry as u32);
+        }
+    }
+}
+
+impl AddAssign for BigUint {
+    fn add_assign(&mut self, other: BigUint) {
+        *self += &other;
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(mut self, other: BigUint) -> BigUint {
+        self += &other;
+        self
+    }
+}
+
+/// Panics if `other` is larger, just like the primitive integers do.
+impl Sub for BigUint {
+    type Output = BigUint;
+
+    fn sub(mut self, other: BigUint) -> BigUint {
+        assert!(self >= other, "attempt to subtract with overflow");
+        let mut borrow = 0;
+        for (i, d) in self.digits.iter_mut().enumerate() {
+            let diff = *d as i64 - other.digits.get(i).copied().unwrap_or(0) as i64 - borrow;
+            *d = diff.rem_euclid(1 << 32) as u32;
+            borrow = (diff < 0) as i64;
+        }
+        self.normalize()
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::default();
+        }
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let cur = digits[i + j] as u64 + a as u64 * b as u64 + carry;
+                digits[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigUint {digits}.normalize()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        &self * &other
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // split into chunks of nine decimal digits, least significant first
+        const CHUNK: u32 = 1_000_000_000;
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        while !n.is_zero() {
+            chunks.push(n.div_rem_digit(CHUNK));
+        }
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                for chunk in rest.iter().rev() {
+                    write!(f, "{:09}", chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Reference value of small numbers
+    fn value(n: &BigUint) -> u128 {
+        n.digits.iter().rev().fold(0, |v, &d| (v << 32) | d as u128)
+    }
+
+    #[test]
+    fn test_factorial() {
+        let factorial = (1..=30u64).fold(BigUint::one(), |n, i| n * BigUint::from(i));
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        let shifted = (0..100).fold(BigUint::one(), |n, _| n * BigUint::from(1 << 32));
+        assert_eq!(shifted.digits.len(), 101);
+        assert_eq!(shifted.clone() - shifted, BigUint::zero());
+        assert_eq!(BigUint::zero().to_string(), "0");
+    }
+
+    proptest! {
+        #[test]
+        fn test_ops(a in any::<u64>(), b in any::<u64>()) {
+            let (x, y) = (BigUint::from(a), BigUint::from(b));
+            prop_assert_eq!(value(&(x.clone() + y.clone())), a as u128 + b as u128);
+            prop_assert_eq!(value(&(x.clone() * y.clone())), a as u128 * b as u128);
+            prop_assert_eq!(x.cmp(&y), a.cmp(&b));
+            prop_assert_eq!(x.to_string(), a.to_string());
+            let (big, small) = if a >= b { (x, y) } else { (y, x) };
+            prop_assert_eq!(value(&(big - small)), a.abs_diff(b) as u128);
+        }
+    }
+}
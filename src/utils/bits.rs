@@ -0,0 +1,460 @@
+/*
+ * Bitsets and bitboards
+ *
+ * `BitSet` holds small integers in a fixed number of words, `DynBitSet` has a length chosen at runtime
+ * and `BitBoard` stores a two dimensional grid of bits as one `DynBitSet` per row, such that whole rows
+ * are combined and shifted a word at a time.
+ */
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not, Sub};
+
+use crate::utils::grid::{Coord, Grid};
+
+/// Set of integers below `64 * W`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitSet<const W: usize = 1>([u64; W]);
+
+impl<const W: usize> BitSet<W> {
+    pub const CAPACITY: usize = 64 * W;
+
+    pub const fn new() -> Self {
+        BitSet([0; W])
+    }
+
+    /// Adds `i`, returns whether it was absent
+    pub fn insert(&mut self, i: usize) -> bool {
+        assert!(i < Self::CAPACITY, "{} exceeds the capacity of the bitset", i);
+        let absent = !self.contains(i);
+        self.0[i / 64] |= 1 << (i % 64);
+        absent
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        i < Self::CAPACITY && self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|w| *w == 0)
+    }
+
+    /// The elements in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        ones(&self.0)
+    }
+}
+
+impl<const W: usize> Default for BitSet<W> {
+    fn default() -> Self {
+        BitSet::new()
+    }
+}
+
+impl<const W: usize> FromIterator<usize> for BitSet<W> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        for i in iter {
+            set.insert(i);
+        }
+        set
+    }
+}
+
+/// Implements a binary operator word by word
+macro_rules! impl_bitset_op {
+    ($op:ident, $f:ident, |$a:ident, $b:ident| $word:expr) => {
+        impl<const W: usize> $op for BitSet<W> {
+            type Output = Self;
+            fn $f(mut self, other: Self) -> Self {
+                for (a, b) in self.0.iter_mut().zip(other.0) {
+                    let ($a, $b) = (*a, b);
+                    *a = $word;
+                }
+                self
+            }
+        }
+    };
+}
+
+impl_bitset_op!(BitAnd, bitand, |a, b| a & b);
+impl_bitset_op!(BitOr, bitor, |a, b| a | b);
+impl_bitset_op!(BitXor, bitxor, |a, b| a ^ b);
+impl_bitset_op!(Sub, sub, |a, b| a & !b);
+
+/// Indices of the set bits in a slice of words
+fn ones(words: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    words.iter().enumerate().flat_map(|(i, &word)| {
+        let mut word = word;
+        std::iter::from_fn(move || {
+            if word == 0 { return None; }
+            let bit = word.trailing_zeros() as usize;
+            word &= word - 1;
+            Some(i * 64 + bit)
+        })
+    })
+}
+
+/// Bitset with a number of bits chosen at runtime.
+/// Bits beyond the length are always zero.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DynBitSet {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl DynBitSet {
+    /// `len` bits, all of them zero
+    pub fn new(len: usize) -> Self {
+        DynBitSet {len, words: vec![0; len.div_ceil(64)]}
+    }
+
+    /// `len` bits, all of them one
+    pub fn full(len: usize) -> Self {
+        let mut set = DynBitSet {len, words: vec![u64::MAX; len.div_ceil(64)]};
+        set.clear_tail();
+        set
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len && self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < self.len, "bit {} is out of range for length {}", i, self.len);
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn any(&self) -> bool {
+        self.words.iter().any(|w| *w != 0)
+    }
+
+    /// Indices of the set bits in increasing order
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        ones(&self.words)
+    }
+
+    /// The `n <= 64` bits starting at `start`, where `start` may be negative.
+    /// Bit `start + i` becomes bit `i` of the result, bits outside the set are `outside`.
+    pub fn bits(&self, start: isize, n: usize, outside: bool) -> u64 {
+        assert!(n <= 64);
+        if n < 64 && start >= 0 && start as usize + n <= self.len && (start as usize % 64) + n <= 64 {
+            // all bits are within a single word
+            let start = start as usize;
+            return (self.words[start / 64] >> (start % 64)) & ((1 << n) - 1);
+        }
+        let mut result = 0;
+        let mut i = 0;
+        while i < n {
+            let pos = start + i as isize;
+            if pos < 0 || pos as usize >= self.len {
+                result |= (outside as u64) << i;
+                i += 1;
+                continue;
+            }
+            // copy as many bits as possible from the current word
+            let (pos, offset) = (pos as usize, pos as usize % 64);
+            let take = (64 - offset).min(n - i).min(self.len - pos);
+            let mask = if take == 64 { u64::MAX } else { (1 << take) - 1 };
+            result |= ((self.words[pos / 64] >> offset) & mask) << i;
+            i += take;
+        }
+        result
+    }
+
+    /// Moves bit `i` to `(i + n) % len`
+    pub fn rotate_up(&self, n: usize) -> Self {
+        if self.len == 0 { return self.clone(); }
+        let n = n % self.len;
+        let mut result = DynBitSet::new(self.len);
+        self.or_shifted_up(n, &mut result.words);
+        self.or_shifted_down(self.len - n, &mut result.words);
+        result.clear_tail();
+        result
+    }
+
+    /// Moves bit `i` to `(i - n) % len`
+    pub fn rotate_down(&self, n: usize) -> Self {
+        if self.len == 0 { return self.clone(); }
+        self.rotate_up(self.len - n % self.len)
+    }
+
+    fn or_shifted_up(&self, n: usize, out: &mut [u64]) {
+        let (words, bits) = (n / 64, n % 64);
+        for (i, out) in out.iter_mut().enumerate().take(self.words.len()).skip(words) {
+            let mut word = self.words[i - words] << bits;
+            if bits > 0 && i > words {
+                word |= self.words[i - words - 1] >> (64 - bits);
+            }
+            *out |= word;
+        }
+    }
+
+    fn or_shifted_down(&self, n: usize, out: &mut [u64]) {
+        let (words, bits) = (n / 64, n % 64);
+        for (i, out) in out.iter_mut().enumerate().take(self.words.len().saturating_sub(words)) {
+            let mut word = self.words[i + words] >> bits;
+            if bits > 0 && i + words + 1 < self.words.len() {
+                word |= self.words[i + words + 1] << (64 - bits);
+            }
+            *out |= word;
+        }
+    }
+
+    fn clear_tail(&mut self) {
+        if !self.len.is_multiple_of(64) {
+            let last = self.words.len() - 1;
+            self.words[last] &= (1 << (self.len % 64)) - 1;
+        }
+    }
+}
+
+/// Implements a binary operator word by word, both bitsets need to have the same length
+macro_rules! impl_dyn_op {
+    ($op:ident, $f:ident, $op_assign:ident, $f_assign:ident, $sym:tt) => {
+        impl $op for &DynBitSet {
+            type Output = DynBitSet;
+            fn $f(self, other: &DynBitSet) -> DynBitSet {
+                let mut result = self.clone();
+                result.$f_assign(other);
+                result
+            }
+        }
+
+        impl $op_assign<&DynBitSet> for DynBitSet {
+            fn $f_assign(&mut self, other: &DynBitSet) {
+                assert_eq!(self.len, other.len, "bitsets differ in length");
+                for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+                    *a = *a $sym *b;
+                }
+            }
+        }
+    };
+}
+
+impl_dyn_op!(BitAnd, bitand, BitAndAssign, bitand_assign, &);
+impl_dyn_op!(BitOr, bitor, BitOrAssign, bitor_assign, |);
+
+impl BitXor for &DynBitSet {
+    type Output = DynBitSet;
+    fn bitxor(self, other: &DynBitSet) -> DynBitSet {
+        assert_eq!(self.len, other.len, "bitsets differ in length");
+        DynBitSet {len: self.len, words: self.words.iter().zip(other.words.iter()).map(|(a, b)| a ^ b).collect()}
+    }
+}
+
+impl Not for &DynBitSet {
+    type Output = DynBitSet;
+    fn not(self) -> DynBitSet {
+        let mut result = DynBitSet {len: self.len, words: self.words.iter().map(|w| !w).collect()};
+        result.clear_tail();
+        result
+    }
+}
+
+/// Grid of bits, bit `x` of row `y` is the cell `(x, y)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitBoard {
+    width: usize,
+    rows: Vec<DynBitSet>,
+}
+
+impl BitBoard {
+    pub fn new(width: usize, height: usize) -> Self {
+        BitBoard {width, rows: vec![DynBitSet::new(width); height]}
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.rows.len() }
+
+    pub fn get(&self, (x, y): Coord) -> bool {
+        self.rows.get(y).is_some_and(|row| row.get(x))
+    }
+
+    pub fn set(&mut self, (x, y): Coord, value: bool) {
+        self.rows[y].set(x, value);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.rows.iter().map(|r| r.count_ones()).sum()
+    }
+
+    pub fn any(&self) -> bool {
+        self.rows.iter().any(|r| r.any())
+    }
+
+    /// Coordinates of the set cells, row by row
+    pub fn ones(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| row.ones().map(move |x| (x, y)))
+    }
+
+    /// The 3x3 neighbourhoods centered at the cells `(x, y)` of row `y`, from `x = -1` up to `x = width`.
+    /// Every neighbourhood is a 9 bit number, read row by row with the top left cell as most significant bit.
+    /// Cells outside the board are `outside`. The three rows are read a word at a time.
+    pub fn row_windows3(&self, y: isize, outside: bool) -> impl Iterator<Item = usize> + '_ {
+        let rows = [y - 1, y, y + 1].map(|y| usize::try_from(y).ok().and_then(|y| self.rows.get(y)));
+        let width = self.width as isize;
+        // chunks of up to 62 windows are cut from the 64 bits around them, reversed such that
+        // the left column of every window ends up in its most significant bit
+        (-1..=width).step_by(62).flat_map(move |start| {
+            let n = (width + 1 - start).min(62) as usize;
+            let words = rows.map(|row| {
+                let bits = match row {
+                    Some(row) => row.bits(start - 1, n + 2, outside),
+                    None if outside => u64::MAX,
+                    None => 0,
+                };
+                bits.reverse_bits() >> (62 - n)
+            });
+            (0..n).rev().map(move |shift| words.iter().fold(0, |window, word| (window << 3) | (word >> shift & 0b111) as usize))
+        })
+    }
+
+    /// Moves every cell by `(dx, dy)`, wrapping around the edges as if the board is a torus.
+    pub fn wrapping_shift(&self, dx: isize, dy: isize) -> Self {
+        let height = self.rows.len() as isize;
+        let rows = (0..height).map(|y| {
+            let row = &self.rows[(y - dy).rem_euclid(height) as usize];
+            if dx >= 0 { row.rotate_up(dx as usize) } else { row.rotate_down(dx.unsigned_abs()) }
+        }).collect();
+        BitBoard {width: self.width, rows}
+    }
+}
+
+impl From<&Grid<bool>> for BitBoard {
+    fn from(grid: &Grid<bool>) -> Self {
+        let mut board = BitBoard::new(grid.width(), grid.height());
+        for c in grid.coords().filter(|c| grid[*c]) {
+            board.set(c, true);
+        }
+        board
+    }
+}
+
+/// Implements a cell by cell operator, both boards need to have the same size
+macro_rules! impl_board_op {
+    ($op:ident, $f:ident) => {
+        impl $op for &BitBoard {
+            type Output = BitBoard;
+            fn $f(self, other: &BitBoard) -> BitBoard {
+                assert_eq!((self.width, self.rows.len()), (other.width, other.rows.len()), "boards differ in size");
+                BitBoard {width: self.width, rows: self.rows.iter().zip(other.rows.iter()).map(|(a, b)| a.$f(b)).collect()}
+            }
+        }
+    };
+}
+
+impl_board_op!(BitAnd, bitand);
+impl_board_op!(BitOr, bitor);
+impl_board_op!(BitXor, bitxor);
+
+impl Not for &BitBoard {
+    type Output = BitBoard;
+    fn not(self) -> BitBoard {
+        BitBoard {width: self.width, rows: self.rows.iter().map(|r| !r).collect()}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use crate::utils::parse;
+
+    #[test]
+    fn test_bitset() {
+        let a: BitSet = [0, 2, 5].into_iter().collect();
+        let b: BitSet = [2, 5].into_iter().collect();
+        assert_eq!(a.len(), 3);
+        assert_eq!((a - b).iter().collect::<Vec<_>>(), vec![0]);
+        assert_eq!((a & b), b);
+        assert!((a ^ a).is_empty());
+        let mut big = BitSet::<2>::new();
+        assert!(big.insert(100) && !big.insert(100));
+        assert!(big.contains(100) && !big.contains(36) && !big.contains(1000));
+    }
+
+    #[test]
+    fn test_windows() {
+        let grid = parse::grid("#..\n.#.\n..#\n", |c| Ok(c == '#')).unwrap();
+        let board = BitBoard::from(&grid);
+        let windows: Vec<usize> = board.row_windows3(1, false).collect();
+        assert_eq!(windows.len(), 5);
+        assert_eq!(windows[2], 0b100_010_001);
+        assert_eq!(board.row_windows3(0, false).nth(1), Some(0b000_010_001));
+        assert_eq!(board.row_windows3(0, true).nth(1), Some(0b111_110_101));
+        assert_eq!(board.row_windows3(5, true).next(), Some(0b111_111_111));
+    }
+
+    #[test]
+    fn test_wrapping_shift() {
+        let grid = parse::grid("#..\n..#\n", |c| Ok(c == '#')).unwrap();
+        let board = BitBoard::from(&grid);
+        assert_eq!(board.wrapping_shift(1, 1).ones().collect::<Vec<_>>(), vec![(0, 0), (1, 1)]);
+        assert_eq!(board.wrapping_shift(-1, 0).ones().collect::<Vec<_>>(), vec![(2, 0), (1, 1)]);
+        assert_eq!((&board & &!&board).count_ones(), 0);
+    }
+
+    fn bitset() -> impl Strategy<Value = (usize, Vec<bool>)> {
+        (1..200usize).prop_flat_map(|len| (Just(len), prop::collection::vec(any::<bool>(), len)))
+    }
+
+    fn from_bools(bits: &[bool]) -> DynBitSet {
+        let mut set = DynBitSet::new(bits.len());
+        for (i, b) in bits.iter().enumerate() {
+            set.set(i, *b);
+        }
+        set
+    }
+
+    proptest! {
+        #[test]
+        fn test_rotations((len, bits) in bitset(), n in 0..250usize) {
+            let set = from_bools(&bits);
+            let rotated: Vec<bool> = (0..len).map(|i| bits[(i + len - n % len) % len]).collect();
+            prop_assert_eq!(set.rotate_up(n), from_bools(&rotated));
+            prop_assert_eq!(set.rotate_up(n).rotate_down(n), set.clone());
+            prop_assert_eq!((!&set).count_ones(), len - set.count_ones());
+        }
+
+        #[test]
+        fn test_row_windows(width in 1..150usize, rows in prop::collection::vec(any::<u64>(), 3), y in -2..5isize, outside in any::<bool>()) {
+            let grid = Grid::from_fn(width, 3, |(x, y)| rows[y] >> (x % 64) & 1 == 1);
+            let board = BitBoard::from(&grid);
+            let cell = |x: isize, y: isize| match (usize::try_from(x), usize::try_from(y)) {
+                (Ok(x), Ok(y)) if x < width && y < 3 => grid[(x, y)],
+                _ => outside,
+            };
+            let expected: Vec<usize> = (-1..=width as isize).map(|x| {
+                (y-1..=y+1).flat_map(|y| (x-1..=x+1).map(move |x| (x, y))).fold(0, |w, (x, y)| (w << 1) | cell(x, y) as usize)
+            }).collect();
+            prop_assert_eq!(board.row_windows3(y, outside).collect::<Vec<_>>(), expected);
+        }
+
+        #[test]
+        fn test_bits((len, bits) in bitset(), start in -70..200isize, n in 0..=64usize, outside in any::<bool>()) {
+            let set = from_bools(&bits);
+            let expected = (0..n).fold(0u64, |acc, i| {
+                let pos = start + i as isize;
+                let bit = if pos < 0 || pos as usize >= len { outside } else { bits[pos as usize] };
+                acc | (bit as u64) << i
+            });
+            prop_assert_eq!(set.bits(start, n, outside), expected);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+/*
+ * Integer geometry
+ *
+ * Points double as vectors, such that offsets between points can be added to other points.
+ * Boxes are axis-aligned and include both corners.
+ */
+
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A point or vector in the plane
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point2<T = i32> {
+    pub x: T,
+    pub y: T,
+}
+
+/// A point or vector in space, ordered by x, then y, then z
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point3<T = i32> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+/// Distance along a single axis, without requiring signed values
+fn abs_diff<T: Ord + Sub<Output = T>>(a: T, b: T) -> T {
+    if a > b { a - b } else { b - a }
+}
+
+impl<T> Point2<T> {
+    pub const fn new(x: T, y: T) -> Self {
+        Point2 {x, y}
+    }
+
+    /// Applies `f` to every coordinate, e.g. to convert between coordinate types.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Point2<U> {
+        Point2 {x: f(self.x), y: f(self.y)}
+    }
+}
+
+impl<T: Copy + Ord + Add<Output = T> + Sub<Output = T>> Point2<T> {
+    pub fn manhattan(&self, other: &Self) -> T {
+        abs_diff(self.x, other.x) + abs_diff(self.y, other.y)
+    }
+}
+
+impl<T> Point3<T> {
+    pub const fn new(x: T, y: T, z: T) -> Self {
+        Point3 {x, y, z}
+    }
+}
+
+impl<T: Copy + Ord + Add<Output = T> + Sub<Output = T>> Point3<T> {
+    pub fn manhattan(&self, other: &Self) -> T {
+        abs_diff(self.x, other.x) + abs_diff(self.y, other.y) + abs_diff(self.z, other.z)
+    }
+}
+
+/// Implements the component-wise operators and the scalar product of a point type
+macro_rules! impl_ops {
+    ($point:ident { $($c:ident),+ }) => {
+        impl<T: Add<Output = T>> Add for $point<T> {
+            type Output = Self;
+            fn add(self, other: Self) -> Self { $point {$($c: self.$c + other.$c),+} }
+        }
+
+        impl<T: Sub<Output = T>> Sub for $point<T> {
+            type Output = Self;
+            fn sub(self, other: Self) -> Self { $point {$($c: self.$c - other.$c),+} }
+        }
+
+        impl<T: Neg<Output = T>> Neg for $point<T> {
+            type Output = Self;
+            fn neg(self) -> Self { $point {$($c: -self.$c),+} }
+        }
+
+        impl<T: Copy + Mul<Output = T>> Mul<T> for $point<T> {
+            type Output = Self;
+            fn mul(self, scalar: T) -> Self { $point {$($c: self.$c * scalar),+} }
+        }
+
+        impl<T: AddAssign> AddAssign for $point<T> {
+            fn add_assign(&mut self, other: Self) { $(self.$c += other.$c;)+ }
+        }
+
+        impl<T: SubAssign> SubAssign for $point<T> {
+            fn sub_assign(&mut self, other: Self) { $(self.$c -= other.$c;)+ }
+        }
+    };
+}
+
+impl_ops!(Point2 {x, y});
+impl_ops!(Point3 {x, y, z});
+
+/// A rotation of space which maps every axis onto an axis, stored as a matrix of rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rotation([[i32; 3]; 3]);
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation([[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+
+    /// All 24 rotations: every permutation of the axes with every choice of signs,
+    /// excluding the reflections.
+    pub fn all() -> Vec<Rotation> {
+        const PERMUTATIONS: [[usize; 3]; 6] = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+        let mut rotations = Vec::with_capacity(24);
+        for perm in PERMUTATIONS {
+            for signs in 0..8 {
+                let mut m = [[0; 3]; 3];
+                for (row, &axis) in perm.iter().enumerate() {
+                    m[row][axis] = if signs & (1 << row) == 0 { 1 } else { -1 };
+                }
+                let rotation = Rotation(m);
+                if rotation.det() == 1 {
+                    rotations.push(rotation);
+                }
+            }
+        }
+        rotations
+    }
+
+    fn det(&self) -> i32 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+impl Mul<Point3<i32>> for Rotation {
+    type Output = Point3<i32>;
+
+    fn mul(self, p: Point3<i32>) -> Point3<i32> {
+        let row = |r: [i32; 3]| r[0] * p.x + r[1] * p.y + r[2] * p.z;
+        Point3 {x: row(self.0[0]), y: row(self.0[1]), z: row(self.0[2])}
+    }
+}
+
+/// Composition, `(a * b) * p == a * (b * p)`
+impl Mul for Rotation {
+    type Output = Rotation;
+
+    fn mul(self, other: Rotation) -> Rotation {
+        let mut m = [[0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = (0..3).map(|k| self.0[i][k] * other.0[k][j]).sum();
+            }
+        }
+        Rotation(m)
+    }
+}
+
+/// Axis-aligned rectangle, including its edges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect<T = i32> {
+    pub min: Point2<T>,
+    pub max: Point2<T>,
+}
+
+impl<T: Copy + Ord> Rect<T> {
+    /// The rectangle spanned by two opposite corners
+    pub fn from_corners(a: Point2<T>, b: Point2<T>) -> Self {
+        Rect {
+            min: Point2::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Point2::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    pub fn contains(&self, p: &Point2<T>) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+}
+
+/// Axis-aligned cuboid, including its faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cuboid<T = i32> {
+    pub min: Point3<T>,
+    pub max: Point3<T>,
+}
+
+impl<T: Copy + Ord> Cuboid<T> {
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn contains_cuboid(&self, other: &Self) -> bool {
+        self.min.x <= other.min.x && other.max.x <= self.max.x
+            && self.min.y <= other.min.y && other.max.y <= self.max.y
+            && self.min.z <= other.min.z && other.max.z <= self.max.z
+    }
+
+    /// The overlapping region, if there is one
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let intersection = Cuboid {
+            min: Point3::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y), self.min.z.max(other.min.z)),
+            max: Point3::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y), self.max.z.min(other.max.z)),
+        };
+        if intersection.is_empty() { None } else { Some(intersection) }
+    }
+}
+
+impl Cuboid<i32> {
+    /// Number of unit cubes within the cuboid
+    pub fn volume(&self) -> u64 {
+        if self.is_empty() { return 0; }
+        let width = |min: i32, max: i32| (max as i64 - min as i64 + 1) as u64;
+        width(self.min.x, self.max.x) * width(self.min.y, self.max.y) * width(self.min.z, self.max.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ops() {
+        let (p, q) = (Point3::new(0, 2, -2), Point3::new(0, -2, 1));
+        assert_eq!(p + q, Point3::new(0, 0, -1));
+        assert_eq!(p - q, Point3::new(0, 4, -3));
+        assert_eq!(-p * 2, Point3::new(0, -4, 4));
+        assert_eq!(p.manhattan(&q), 7);
+        assert_eq!(Point2::new(3u32, 1).manhattan(&Point2::new(1, 4)), 5);
+        assert_eq!(Point2::new(-2, 0).manhattan(&Point2::new(2, -3)), 7);
+        assert!(Point3::new(-1, 5, 5) < Point3::new(0, 0, 0));
+    }
+
+    #[test]
+    fn test_rotations() {
+        let rotations = Rotation::all();
+        assert_eq!(rotations.len(), 24);
+        let p = Point3::new(1, 2, 3);
+        let mut images: Vec<Point3> = rotations.iter().map(|r| *r * p).collect();
+        images.sort();
+        images.dedup();
+        assert_eq!(images.len(), 24);
+        // the rotations form a group
+        for (a, b) in rotations.iter().zip(rotations.iter().rev()) {
+            assert!(rotations.contains(&(*a * *b)));
+            assert_eq!((*a * *b) * p, *a * (*b * p));
+        }
+    }
+
+    #[test]
+    fn test_boxes() {
+        let rect = Rect::from_corners(Point2::new(30, -5), Point2::new(20, -10));
+        assert!(rect.contains(&Point2::new(20, -5)) && !rect.contains(&Point2::new(19, -5)));
+
+        let c1 = Cuboid {min: Point3::new(-2, -2, -2), max: Point3::new(2, 2, 2)};
+        let c2 = Cuboid {min: Point3::new(-2, -1, -1), max: Point3::new(2, 3, 3)};
+        assert_eq!(c1.volume(), 125);
+        assert_eq!(c1.intersection(&c2).map(|c| c.volume()), Some(80));
+        assert_eq!(c1.intersection(&Cuboid {min: Point3::new(3, 0, 0), max: Point3::new(4, 0, 0)}), None);
+        assert!(c1.contains_cuboid(&c1.intersection(&c2).unwrap()));
+    }
+}
@@ -0,0 +1,193 @@
+/*
+ * Two dimensional grids
+ *
+ * Cells are stored row by row in a single vector and addressed by `(x, y)` coordinates.
+ */
+
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::utils::{parse, ParseError};
+
+pub type Coord = (usize, usize);
+
+const OFFSETS4: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const OFFSETS8: [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+/// A non-empty rectangular grid
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from its rows, which must be non-empty and of equal length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, ParseError> {
+        let width = rows.first().map_or(0, |r| r.len());
+        if width == 0 {
+            return Err(ParseError::new("empty grid"));
+        }
+        if rows.iter().any(|r| r.len() != width) {
+            return Err(ParseError::new("rows differ in length"));
+        }
+        let height = rows.len();
+        Ok(Grid {width, height, cells: rows.into_iter().flatten().collect()})
+    }
+
+    /// Builds a grid by computing every cell from its coordinate.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Coord) -> T) -> Self {
+        let cells = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(&mut f).collect();
+        Grid {width, height, cells}
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+    pub fn len(&self) -> usize { self.cells.len() }
+
+    pub fn get(&self, (x, y): Coord) -> Option<&T> {
+        if x < self.width && y < self.height { self.cells.get(y * self.width + x) } else { None }
+    }
+
+    /// All coordinates, row by row
+    pub fn coords(&self) -> impl Iterator<Item = Coord> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.cells.chunks(self.width)
+    }
+
+    /// Horizontally and vertically adjacent coordinates within the grid
+    pub fn neighbours4(&self, coord: Coord) -> impl Iterator<Item = Coord> {
+        self.neighbours(coord, &OFFSETS4)
+    }
+
+    /// Horizontally, vertically and diagonally adjacent coordinates within the grid
+    pub fn neighbours8(&self, coord: Coord) -> impl Iterator<Item = Coord> {
+        self.neighbours(coord, &OFFSETS8)
+    }
+
+    fn neighbours(&self, (x, y): Coord, offsets: &'static [(isize, isize)]) -> impl Iterator<Item = Coord> {
+        let (width, height) = (self.width, self.height);
+        offsets.iter().filter_map(move |&(dx, dy)| {
+            let x = x.checked_add_signed(dx).filter(|x| *x < width)?;
+            let y = y.checked_add_signed(dy).filter(|y| *y < height)?;
+            Some((x, y))
+        })
+    }
+
+    /// Moves a coordinate by an offset, wrapping around the edges as if the grid is a torus.
+    pub fn wrapping_offset(&self, (x, y): Coord, (dx, dy): (isize, isize)) -> Coord {
+        let x = (x as isize + dx).rem_euclid(self.width as isize) as usize;
+        let y = (y as isize + dy).rem_euclid(self.height as isize) as usize;
+        (x, y)
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {width: self.width, height: self.height, cells: self.cells.iter().map(f).collect()}
+    }
+
+    /// Repeats the grid `xtiles` times horizontally and `ytiles` times vertically.
+    /// Cells are derived from the original cell and the position `(tx, ty)` of their tile.
+    pub fn tile(&self, xtiles: usize, ytiles: usize, f: impl Fn(&T, usize, usize) -> T) -> Grid<T> {
+        Grid::from_fn(self.width * xtiles, self.height * ytiles, |(x, y)| {
+            f(&self[(x % self.width, y % self.height)], x / self.width, y / self.height)
+        })
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Grid {width, height, cells: vec![value; width * height]}
+    }
+
+    /// Surrounds the grid by a border of `n` cells on every side.
+    pub fn pad(&self, n: usize, value: T) -> Grid<T> {
+        Grid::from_fn(self.width + 2 * n, self.height + 2 * n, |(x, y)| {
+            match (x.checked_sub(n), y.checked_sub(n)) {
+                (Some(x), Some(y)) => self.get((x, y)).unwrap_or(&value).clone(),
+                _ => value.clone(),
+            }
+        })
+    }
+}
+
+impl<T: From<u8>> Grid<T> {
+    /// Parses a map of single digits
+    pub fn parse_digits(input: &str) -> Result<Self, ParseError> {
+        parse::grid(input, |c| c.to_digit(10).map(|d| T::from(d as u8)).ok_or_else(|| ParseError::new(format!("invalid digit {:?}", c))))
+    }
+}
+
+impl<T> Index<Coord> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): Coord) -> &T {
+        assert!(x < self.width && y < self.height, "({}, {}) is outside of the grid", x, y);
+        &self.cells[y * self.width + x]
+    }
+}
+
+impl<T> IndexMut<Coord> for Grid<T> {
+    fn index_mut(&mut self, (x, y): Coord) -> &mut T {
+        assert!(x < self.width && y < self.height, "({}, {}) is outside of the grid", x, y);
+        &mut self.cells[y * self.width + x]
+    }
+}
+
+/// Writes every row on a separate line, without separators between cells
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows() {
+            row.iter().try_for_each(|cell| write!(f, "{}", cell))?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let grid: Grid<u32> = Grid::parse_digits("123\r\n456\n").unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[(2, 1)], 6);
+        assert_eq!(grid.to_string(), "123\n456\n");
+        assert!(Grid::<u32>::parse_digits("12\n345\n").is_err());
+        assert!(Grid::<u32>::parse_digits("1a\n").is_err());
+        assert!(Grid::<u32>::parse_digits("").is_err());
+        assert_eq!(Grid::<u8>::parse_digits("09\n").map(|g| g[(1, 0)]), Ok(9u8));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = Grid::filled(3, 2, 0);
+        assert_eq!(grid.neighbours4((0, 0)).collect::<Vec<_>>(), vec![(0, 1), (1, 0)]);
+        assert_eq!(grid.neighbours4((1, 1)).count(), 3);
+        assert_eq!(grid.neighbours8((1, 0)).count(), 5);
+        assert_eq!(grid.neighbours8((2, 1)).collect::<Vec<_>>(), vec![(1, 0), (1, 1), (2, 0)]);
+        assert_eq!(grid.wrapping_offset((2, 1), (1, 1)), (0, 0));
+        assert_eq!(grid.wrapping_offset((0, 0), (-1, -1)), (2, 1));
+    }
+
+    #[test]
+    fn test_tile() {
+        let grid: Grid<u32> = Grid::parse_digits("12\n").unwrap();
+        assert_eq!(grid.tile(2, 2, |n, tx, ty| n + (tx + 2 * ty) as u32).to_string(), "1223\n3445\n");
+        assert_eq!(grid.pad(1, 0).to_string(), "0000\n0120\n0000\n");
+    }
+}
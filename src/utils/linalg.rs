@@ -0,0 +1,158 @@
+/*
+ * Linear algebra over rings
+ *
+ * Simulations that update a vector of counts linearly every step are a matrix product per step,
+ * such that `n` steps take a single matrix power, computed with O(log n) multiplications.
+ */
+
+use std::fmt;
+use std::ops::{Add, Index, IndexMut, Mul};
+
+/// Values matrices can be built from, `From<u64>` provides zero and one.
+pub trait Ring: Clone + From<u64> + Add<Output = Self> + Mul<Output = Self> {}
+
+impl<T: Clone + From<u64> + Add<Output = T> + Mul<Output = T>> Ring for T {}
+
+/// Integers modulo `M`, where `M` is below 2^63
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ModInt<const M: u64>(u64);
+
+/// A prime modulus for answers which do not fit any integer type
+pub const PRIME: u64 = 1_000_000_007;
+
+impl<const M: u64> From<u64> for ModInt<M> {
+    fn from(n: u64) -> Self {
+        ModInt(n % M)
+    }
+}
+
+impl<const M: u64> Add for ModInt<M> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        ModInt((self.0 + other.0) % M)
+    }
+}
+
+impl<const M: u64> Mul for ModInt<M> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        ModInt((self.0 as u128 * other.0 as u128 % M as u128) as u64)
+    }
+}
+
+impl<const M: u64> fmt::Display for ModInt<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Square matrix stored row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix<T> {
+    size: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Ring> Matrix<T> {
+    pub fn from_fn(size: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let cells = (0..size * size).map(|i| f(i / size, i % size)).collect();
+        Matrix {size, cells}
+    }
+
+    pub fn zero(size: usize) -> Self {
+        Matrix {size, cells: vec![T::from(0); size * size]}
+    }
+
+    pub fn identity(size: usize) -> Self {
+        Matrix::from_fn(size, |r, c| T::from((r == c) as u64))
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The product `self * v` with the column vector `v`
+    pub fn apply(&self, v: &[T]) -> Vec<T> {
+        assert_eq!(v.len(), self.size, "vector does not match the matrix size");
+        self.cells.chunks(self.size)
+            .map(|row| row.iter().zip(v).fold(T::from(0), |sum, (a, b)| sum + a.clone() * b.clone()))
+            .collect()
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    /// The cell at `(row, column)`
+    fn index(&self, (r, c): (usize, usize)) -> &T {
+        &self.cells[r * self.size + c]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut T {
+        &mut self.cells[r * self.size + c]
+    }
+}
+
+impl<T: Ring> Mul for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, other: &Matrix<T>) -> Matrix<T> {
+        assert_eq!(self.size, other.size, "matrices differ in size");
+        let n = self.size;
+        let mut product: Matrix<T> = Matrix::zero(n);
+        for r in 0..n {
+            for k in 0..n {
+                let a = &self[(r, k)];
+                for c in 0..n {
+                    product[(r, c)] = product[(r, c)].clone() + a.clone() * other[(k, c)].clone();
+                }
+            }
+        }
+        product
+    }
+}
+
+/// The matrix to the power `n`, by repeated squaring
+pub fn pow<T: Ring>(matrix: &Matrix<T>, mut n: u64) -> Matrix<T> {
+    let mut result = Matrix::identity(matrix.size());
+    let mut square = matrix.clone();
+    while n > 0 {
+        if n & 1 == 1 {
+            result = &result * &square;
+        }
+        n >>= 1;
+        if n > 0 {
+            square = &square * &square;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bigint::BigUint;
+
+    /// Maps (a, b) to (b, a + b)
+    fn fibonacci<T: Ring>() -> Matrix<T> {
+        Matrix::from_fn(2, |r, c| T::from((r + c > 0) as u64))
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(pow(&fibonacci::<u64>(), 0), Matrix::identity(2));
+        assert_eq!(pow(&fibonacci::<u64>(), 90).apply(&[0, 1])[0], 2880067194370816120);
+        let big = pow(&fibonacci::<BigUint>(), 300).apply(&[BigUint::from(0), BigUint::from(1)]);
+        assert_eq!(big[0].to_string(), "222232244629420445529739893461909967206666939096499764990979600");
+
+        // Pisano period of 10^9 + 7 divides 2 * (p + 1)
+        let fib = pow(&fibonacci::<ModInt<PRIME>>(), 2 * (PRIME + 1));
+        assert_eq!(fib, Matrix::identity(2));
+        let fib = pow(&fibonacci::<ModInt<PRIME>>(), 1_000_000_000_000_000_000);
+        assert_eq!(fib.apply(&[0.into(), 1.into()])[0], ModInt::from(209783453));
+    }
+}
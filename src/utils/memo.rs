@@ -0,0 +1,148 @@
+/*
+ * Memoization for recursive counting problems
+ *
+ * `Memo` caches a recursive function by its argument, the function receives the cache to recurse
+ * through. `Counter` is a multiset, storing how often every state occurs instead of the states.
+ *
+ * Day 12 counts its paths with `memoize`. Days 14 and 21 count forward step by step instead, because
+ * their depth grows with the steps and scores of their variants.
+ */
+
+use std::collections::hash_map::{self, HashMap};
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+use crate::stats;
+use crate::utils::bigint::Natural;
+
+/// Recursive function over states, calling `Memo::get` for its subproblems
+type Recursion<'f, S, V> = dyn Fn(&mut Memo<'f, S, V>, &S) -> V + 'f;
+
+/// Cache of a recursive function. Every subproblem that is not cached yet is solved on the stack of
+/// the one asking for it, so the recursion depth should stay in the thousands: count step by step
+/// with `Counter::step` instead where the depth grows with a parameter.
+pub struct Memo<'f, S, V> {
+    cache: HashMap<S, V>,
+    f: &'f Recursion<'f, S, V>,
+}
+
+impl<'f, S: Clone + Eq + Hash, V: Clone> Memo<'f, S, V> {
+    pub fn new(f: &'f Recursion<'f, S, V>) -> Self {
+        Memo {cache: HashMap::new(), f}
+    }
+
+    /// The value of the function at `state`, computed at most once
+    pub fn get(&mut self, state: S) -> V {
+        if let Some(value) = self.cache.get(&state) {
+            return value.clone();
+        }
+        let f = self.f;
+        let value = f(self, &state);
+        self.cache.insert(state, value.clone());
+        value
+    }
+
+    /// Number of cached states
+    fn len(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+/// Evaluates the recursive function `f` at `state`, caching the subproblems for this call only.
+pub fn memoize<S: Clone + Eq + Hash, V: Clone>(state: S, f: impl Fn(&mut Memo<S, V>, &S) -> V) -> V {
+    let mut memo = Memo::new(&f);
+    let value = memo.get(state);
+    stats::count("memo: cached states", memo.len() as u64);
+    value
+}
+
+/// Multiset, counting the occurrences of every element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter<K: Eq + Hash, N = u64>(HashMap<K, N>);
+
+impl<K: Eq + Hash, N: Natural> Counter<K, N> {
+    pub fn new() -> Self {
+        Counter(HashMap::new())
+    }
+
+    /// Adds `n` occurrences of `key`
+    pub fn add(&mut self, key: K, n: N) {
+        *self.0.entry(key).or_insert_with(N::zero) += n;
+    }
+
+    /// The distinct elements and their number of occurrences, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &N)> {
+        self.0.iter()
+    }
+
+    /// Replaces every element by its successors, each successor occurs as often as the element
+    /// times the multiplicity of the step towards it.
+    pub fn step<I: IntoIterator<Item = (K, N)>>(&self, mut successors: impl FnMut(&K) -> I) -> Self {
+        let mut next = Counter::new();
+        for (key, n) in self.iter() {
+            for (successor, m) in successors(key) {
+                next.add(successor, n.clone() * m);
+            }
+        }
+        next
+    }
+}
+
+impl<K: Eq + Hash, N: Natural> Default for Counter<K, N> {
+    fn default() -> Self {
+        Counter::new()
+    }
+}
+
+impl<K: Eq + Hash, N: Natural> FromIterator<K> for Counter<K, N> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut counter = Counter::new();
+        for key in iter {
+            counter.add(key, N::one());
+        }
+        counter
+    }
+}
+
+impl<K: Eq + Hash, N> IntoIterator for Counter<K, N> {
+    type Item = (K, N);
+    type IntoIter = hash_map::IntoIter<K, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memo() {
+        let fib = |rec: &mut Memo<u64, u64>, &n: &u64| if n < 2 { n } else { rec.get(n - 1) + rec.get(n - 2) };
+        assert_eq!(memoize(90, fib), 2880067194370816120);
+
+        let mut memo = Memo::new(&fib);
+        assert_eq!(memo.get(10), 55);
+        assert_eq!(memo.len(), 11);
+        assert_eq!(memo.get(5), 5);
+        assert_eq!(memo.len(), 11);
+    }
+
+    #[test]
+    fn test_counter() {
+        let counter: Counter<char> = "abracadabra".chars().collect();
+        let mut letters: Vec<_> = counter.iter().map(|(c, n)| (*c, *n)).collect();
+        letters.sort();
+        assert_eq!(letters, vec![('a', 5), ('b', 2), ('c', 1), ('d', 1), ('r', 2)]);
+
+        // a random walk on a line, where every position branches into two
+        let mut walk: Counter<i32> = std::iter::once(0).collect();
+        for _ in 0..4 {
+            walk = walk.step(|&x| [(x - 1, 1), (x + 1, 1)]);
+        }
+        let mut positions: Vec<_> = walk.into_iter().collect();
+        positions.sort();
+        assert_eq!(positions, vec![(-4, 1), (-2, 4), (0, 6), (2, 4), (4, 1)]);
+    }
+}
@@ -0,0 +1,284 @@
+/*
+ * Parsing toolkit
+ *
+ * Small combinators for the recurring parts of puzzle inputs.
+ * Errors carry the line and column of the offending text (relative to the text being parsed),
+ * such that they can be rendered against the input.
+ */
+
+use std::fmt;
+use std::io::BufRead;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use nom::{Parser, combinator::all_consuming};
+
+use crate::utils::grid::Grid;
+
+/// Error for malformed puzzle input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+    line: Option<usize>, // 1-based
+    col: Option<usize>, // 1-based, in characters
+}
+
+impl ParseError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        ParseError {message: msg.into(), line: None, col: None}
+    }
+
+    pub fn at(mut self, line: usize, col: usize) -> Self {
+        self.line = Some(line);
+        self.col = Some(col);
+        self
+    }
+
+    /// Locates the error at `fragment`, which has to be a slice of `text`.
+    /// Errors which are located already are left untouched.
+    pub fn locate(self, text: &str, fragment: &str) -> Self {
+        if self.line.is_some() { return self; }
+        let offset = (fragment.as_ptr() as usize).wrapping_sub(text.as_ptr() as usize);
+        if offset > text.len() { return self; }
+        let before = &text[..offset];
+        let line = before.matches('\n').count() + 1;
+        let col = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        self.at(line, col)
+    }
+
+    /// Makes the position relative to a larger text, in which the parsed text starts on line `first_line`.
+    /// Errors without a position are placed on that line.
+    pub fn offset(mut self, first_line: usize) -> Self {
+        self.line = Some(first_line + self.line.unwrap_or(1) - 1);
+        self
+    }
+
+    pub fn line(&self) -> Option<usize> { self.line }
+    pub fn col(&self) -> Option<usize> { self.col }
+
+    /// Shows the error together with the offending line of the input and a marker below the column.
+    pub fn render(&self, input: &str) -> String {
+        let line = match self.line.and_then(|l| Some((l, input.lines().nth(l - 1)?))) {
+            Some(line) => line,
+            None => return self.to_string(),
+        };
+        let number = line.0.to_string();
+        let mut rendered = format!("{}\n{} | {}", self, number, line.1);
+        if let Some(col) = self.col {
+            rendered.push_str(&format!("\n{} | {}^", " ".repeat(number.len()), " ".repeat(col - 1)));
+        }
+        rendered
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.col) {
+            (Some(line), Some(col)) => write!(f, "Parse error at line {}, column {}: {}", line, col, self.message),
+            (Some(line), None) => write!(f, "Parse error at line {}: {}", line, self.message),
+            _ => write!(f, "Parse error: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseIntError> for ParseError {
+    fn from(e: ParseIntError) -> Self {
+        ParseError::new(format!("invalid number ({})", e))
+    }
+}
+
+impl From<nom::Err<nom::error::Error<&str>>> for ParseError {
+    fn from(e: nom::Err<nom::error::Error<&str>>) -> Self {
+        match e {
+            nom::Err::Incomplete(_) => ParseError::new("incomplete input"),
+            nom::Err::Error(e) | nom::Err::Failure(e) => ParseError::new(format!("unexpected {:?} ({:?})", e.input, e.code)),
+        }
+    }
+}
+
+/// Parses every line of the text
+pub fn lines<'a, T>(text: &'a str, mut parse_line: impl FnMut(&'a str) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+    text.lines().enumerate().map(|(i, line)| parse_line(line).map_err(|e| e.offset(i + 1))).collect()
+}
+
+/// Feeds the lines of a reader to `parse_line` one at a time, normalized like `Input`:
+/// without byte order mark, line endings, trailing whitespace and trailing blank lines.
+pub fn stream_lines(reader: &mut dyn BufRead, mut parse_line: impl FnMut(&str) -> Result<(), ParseError>) -> Result<(), ParseError> {
+    let mut buf = String::new();
+    let mut number = 0;
+    let mut blank = 0; // blank lines are only passed on once another line follows
+    loop {
+        buf.clear();
+        let read = reader.read_line(&mut buf).map_err(|e| ParseError::new(format!("could not read input ({})", e)))?;
+        if read == 0 {
+            return Ok(());
+        }
+        number += 1;
+        let line = if number == 1 { buf.strip_prefix('\u{feff}').unwrap_or(&buf) } else { &buf };
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        for i in number - blank..number {
+            parse_line("").map_err(|e| e.offset(i))?;
+        }
+        blank = 0;
+        parse_line(line).map_err(|e| e.offset(number))?;
+    }
+}
+
+/// Lines of the text which are separated from the rest by blank lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    pub line: usize, // line number of the first line
+    pub text: &'a str,
+}
+
+impl<'a> Section<'a> {
+    /// Parses the section, positions of errors are relative to the whole text.
+    pub fn parse<T>(&self, parse: impl FnOnce(&'a str) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        parse(self.text).map_err(|e| e.offset(self.line))
+    }
+}
+
+/// Splits the text on blank lines
+pub fn sections(text: &str) -> Vec<Section<'_>> {
+    let mut sections = Vec::new();
+    let mut start: Option<(usize, usize)> = None; // (line, byte offset)
+    let mut offset = 0;
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if line.trim().is_empty() {
+            if let Some((line, begin)) = start.take() {
+                sections.push(Section {line, text: text[begin..offset].trim_end()});
+            }
+        } else if start.is_none() {
+            start = Some((i + 1, offset));
+        }
+        offset += line.len();
+    }
+    if let Some((line, begin)) = start {
+        sections.push(Section {line, text: text[begin..].trim_end()});
+    }
+    sections
+}
+
+/// Parses a number, locating errors within `text`
+pub fn number<T: FromStr<Err = ParseIntError>>(text: &str, token: &str) -> Result<T, ParseError> {
+    token.parse().map_err(|e| ParseError::from(e).locate(text, token))
+}
+
+/// Signed or unsigned integers, separated by commas or whitespace
+pub fn ints<T: FromStr<Err = ParseIntError>>(text: &str) -> Result<Vec<T>, ParseError> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| number(text, token))
+        .collect()
+}
+
+/// A range written as `key=a..b`, where `field` is a slice of `text`
+pub fn range<T: FromStr<Err = ParseIntError>>(text: &str, field: &str, key: &str) -> Result<(T, T), ParseError> {
+    let expected = || ParseError::new(format!("expected {}=a..b", key)).locate(text, field);
+    let bounds = field.strip_prefix(key).and_then(|f| f.strip_prefix('=')).ok_or_else(expected)?;
+    let (a, b) = bounds.split_once("..").ok_or_else(expected)?;
+    Ok((number(text, a)?, number(text, b)?))
+}
+
+/// A non-empty rectangular grid, converting every character into a cell
+pub fn grid<T>(text: &str, cell: impl Fn(char) -> Result<T, ParseError>) -> Result<Grid<T>, ParseError> {
+    let rows: Vec<Vec<T>> = lines(text, |line| {
+        line.char_indices().map(|(i, c)| cell(c).map_err(|e| e.locate(line, &line[i..]))).collect()
+    })?;
+    let width = rows.first().map_or(0, |r| r.len());
+    if let Some(y) = rows.iter().position(|r| r.len() != width) {
+        return Err(ParseError::new(format!("row has length {} instead of {}", rows[y].len(), width)).offset(y + 1));
+    }
+    Grid::from_rows(rows)
+}
+
+/// Runs a nom parser, which has to consume the whole text
+pub fn complete<'a, O>(text: &'a str, parser: impl Parser<&'a str, O, nom::error::Error<&'a str>>) -> Result<O, ParseError> {
+    match all_consuming(parser)(text) {
+        Ok((_, output)) => Ok(output),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            let unexpected = if e.input.is_empty() {String::from("end of line")} else {format!("{:?}", e.input)};
+            Err(ParseError::new(format!("unexpected {}", unexpected)).locate(text, e.input))
+        },
+        Err(e) => Err(ParseError::from(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::{bytes::complete::tag, character::complete, sequence::preceded};
+
+    #[test]
+    fn test_locate() {
+        let text = "1,2\n3,x,5\n";
+        let e = ints::<u32>(text).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(2), Some(3)));
+        assert_eq!(e.render(text), "Parse error at line 2, column 3: invalid number (invalid digit found in string)\n2 | 3,x,5\n  |   ^");
+        assert_eq!(ParseError::new("oops").render(text), "Parse error: oops");
+    }
+
+    #[test]
+    fn test_lines() {
+        let e = lines("1\n2\n-3\n", |l| Ok(number::<u32>(l, l)?)).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(3), Some(1)));
+        let e = lines("1\n\n", |l| if l.is_empty() {Err(ParseError::new("empty"))} else {Ok(l)}).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(2), None));
+    }
+
+    #[test]
+    fn test_stream_lines() {
+        let raw = "\u{feff}a \r\n\nb\n\n \n";
+        let mut streamed = Vec::new();
+        stream_lines(&mut raw.as_bytes(), |l| { streamed.push(String::from(l)); Ok(()) }).unwrap();
+        assert_eq!(streamed, crate::input::Input::new(raw).as_str().lines().collect::<Vec<_>>());
+
+        let e = stream_lines(&mut "1\n\n\nx\n".as_bytes(), |l| if l.is_empty() {Err(ParseError::new("empty"))} else {Ok(())}).unwrap_err();
+        assert_eq!(e.line(), Some(2));
+        let e = stream_lines(&mut "1\n2\n-3\n".as_bytes(), |l| number::<u32>(l, l).map(|_| ())).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(3), Some(1)));
+    }
+
+    #[test]
+    fn test_sections() {
+        let text = "a\nb\n\n\nc\n  \nd\n";
+        let s = sections(text);
+        assert_eq!(s, vec![Section {line: 1, text: "a\nb"}, Section {line: 5, text: "c"}, Section {line: 7, text: "d"}]);
+        let e = s[2].parse(|t| ints::<u32>(t)).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(7), Some(1)));
+        assert!(sections("\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_range() {
+        assert_eq!(range("x=-20..26", "x=-20..26", "x"), Ok((-20, 26)));
+        let line = "x=1..2, y=1..x";
+        assert_eq!(range::<i32>(line, &line[8..], "y").unwrap_err().col(), Some(14));
+        assert_eq!(range::<i32>(line, &line[8..], "x").unwrap_err().col(), Some(9));
+        assert!(range::<i32>("x=1.2", "x=1.2", "x").is_err());
+    }
+
+    #[test]
+    fn test_grid() {
+        let digit = |c: char| c.to_digit(10).ok_or_else(|| ParseError::new("invalid digit"));
+        assert_eq!(grid("12\n34\n", digit).map(|g| g.to_string()), Ok(String::from("12\n34\n")));
+        let e = grid("12\n3x\n", digit).unwrap_err();
+        assert_eq!((e.line(), e.col()), (Some(2), Some(2)));
+        assert_eq!(grid("12\n345\n", digit).unwrap_err().line(), Some(2));
+        assert!(grid("", digit).is_err());
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut forward = preceded(tag("forward "), complete::u32);
+        assert_eq!(complete("forward 5", &mut forward), Ok(5));
+        assert_eq!(complete("forward x", &mut forward).unwrap_err().col(), Some(9));
+        assert_eq!(complete("forward 5 ", &mut forward).unwrap_err().col(), Some(10));
+    }
+}
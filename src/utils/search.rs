@@ -0,0 +1,257 @@
+/*
+ * Shortest path searches over implicit graphs
+ *
+ * States are discovered lazily through a `neighbours` callback, which yields every successor
+ * together with the cost of the step towards it.
+ */
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+use crate::utils::grid::{Coord, Grid};
+
+/// Bookkeeping of a search
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub expanded: usize, // states of which the neighbours have been generated
+    pub frontier_peak: usize, // maximum number of states waiting in the queue
+}
+
+/// A cheapest path from the start to a goal state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution<S, C> {
+    pub cost: C,
+    pub path: Vec<S>, // includes both the start and the goal
+    pub stats: Stats,
+}
+
+/// Queue entry, ordered such that `BinaryHeap` pops the lowest estimate first
+struct Entry<S, C> {
+    estimate: C,
+    cost: C,
+    state: S,
+}
+
+impl<S, C: Ord> PartialEq for Entry<S, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl<S, C: Ord> Eq for Entry<S, C> {}
+
+impl<S, C: Ord> PartialOrd for Entry<S, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S, C: Ord> Ord for Entry<S, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.estimate.cmp(&other.estimate).reverse()
+    }
+}
+
+/// Cheapest known cost of every discovered state
+pub trait Distances<S, C> {
+    fn cost(&self, state: &S) -> Option<C>;
+
+    /// Records a cheaper path towards `state`, which arrives from `prev`
+    fn improve(&mut self, state: &S, cost: C, prev: &S);
+}
+
+/// Sparse distances of any state, keeping the predecessor on the cheapest path such that it can be rebuilt
+impl<S: Clone + Eq + Hash, C: Copy> Distances<S, C> for HashMap<S, (C, Option<S>)> {
+    fn cost(&self, state: &S) -> Option<C> {
+        self.get(state).map(|(cost, _)| *cost)
+    }
+
+    fn improve(&mut self, state: &S, cost: C, prev: &S) {
+        self.insert(state.clone(), (cost, Some(prev.clone())));
+    }
+}
+
+/// Dense distances of the cells of a grid, without predecessors
+impl<C: Copy> Distances<Coord, C> for Grid<Option<C>> {
+    fn cost(&self, state: &Coord) -> Option<C> {
+        self[*state]
+    }
+
+    fn improve(&mut self, state: &Coord, cost: C, _: &Coord) {
+        self[*state] = Some(cost);
+    }
+}
+
+/// Dijkstra's algorithm, `C::default()` is the cost of the empty path.
+pub fn dijkstra<S, C, I>(start: S, neighbours: impl FnMut(&S) -> I, is_goal: impl Fn(&S) -> bool) -> Option<Solution<S, C>>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = (S, C)>,
+{
+    astar(start, neighbours, |_| C::default(), is_goal)
+}
+
+/// A* search. The heuristic must never overestimate the remaining cost towards a goal.
+pub fn astar<S, C, I>(start: S, neighbours: impl FnMut(&S) -> I, heuristic: impl Fn(&S) -> C, is_goal: impl Fn(&S) -> bool) -> Option<Solution<S, C>>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = (S, C)>,
+{
+    // cheapest known cost of every discovered state and its predecessor on that path
+    let mut dist: HashMap<S, (C, Option<S>)> = HashMap::new();
+    dist.insert(start.clone(), (C::default(), None));
+    let (cost, goal, stats) = search(start, &mut dist, neighbours, heuristic, is_goal)?;
+    Some(Solution {cost, path: reconstruct(&dist, goal), stats})
+}
+
+/// A* search for the cost of a cheapest path only, keeping the distances in `dist`,
+/// which has to hold the cost of the start. Dense stores avoid hashing every state.
+pub fn astar_cost<S, C, I>(start: S, dist: &mut impl Distances<S, C>, neighbours: impl FnMut(&S) -> I, heuristic: impl Fn(&S) -> C, is_goal: impl Fn(&S) -> bool) -> Option<C>
+where
+    S: Clone,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = (S, C)>,
+{
+    search(start, dist, neighbours, heuristic, is_goal).map(|(cost, _, _)| cost)
+}
+
+/// Expands the state of lowest estimate until reaching a goal, returns the goal and its cost
+fn search<S, C, I>(start: S, dist: &mut impl Distances<S, C>, mut neighbours: impl FnMut(&S) -> I, heuristic: impl Fn(&S) -> C, is_goal: impl Fn(&S) -> bool) -> Option<(C, S, Stats)>
+where
+    S: Clone,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = (S, C)>,
+{
+    let mut stats = Stats::default();
+    let mut queue = BinaryHeap::new();
+    queue.push(Entry {estimate: heuristic(&start), cost: C::default(), state: start});
+
+    while let Some(Entry {cost, state, ..}) = queue.pop() {
+        if is_goal(&state) {
+            record(&stats);
+            return Some((cost, state, stats));
+        }
+        if dist.cost(&state).is_some_and(|c| cost > c) { continue; } // there is a shorter way to reach the state
+
+        stats.expanded += 1;
+        for (next, step) in neighbours(&state) {
+            let cost = cost + step;
+            if dist.cost(&next).is_none_or(|c| cost < c) {
+                dist.improve(&next, cost, &state);
+                queue.push(Entry {estimate: cost + heuristic(&next), cost, state: next});
+            }
+        }
+        stats.frontier_peak = stats.frontier_peak.max(queue.len());
+    }
+    record(&stats);
+    None
+}
+
+/// Breadth first search, every step costs 1.
+pub fn bfs<S, I>(start: S, mut neighbours: impl FnMut(&S) -> I, is_goal: impl Fn(&S) -> bool) -> Option<Solution<S, usize>>
+where
+    S: Clone + Eq + Hash,
+    I: IntoIterator<Item = S>,
+{
+    let mut stats = Stats::default();
+    let mut dist: HashMap<S, (usize, Option<S>)> = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    dist.insert(start.clone(), (0, None));
+    queue.push_back((0, start));
+
+    while let Some((cost, state)) = queue.pop_front() {
+        if is_goal(&state) {
+            record(&stats);
+            return Some(Solution {cost, path: reconstruct(&dist, state), stats});
+        }
+        stats.expanded += 1;
+        for next in neighbours(&state) {
+            if !dist.contains_key(&next) {
+                dist.insert(next.clone(), (cost + 1, Some(state.clone())));
+                queue.push_back((cost + 1, next));
+            }
+        }
+        stats.frontier_peak = stats.frontier_peak.max(queue.len());
+    }
+    record(&stats);
+    None
+}
+
+/// Records the bookkeeping of a finished search in the statistics of the solution
+fn record(stats: &Stats) {
+    crate::stats::count("search: expanded states", stats.expanded as u64);
+    crate::stats::peak("search: frontier", stats.frontier_peak as u64);
+}
+
+/// Follows the predecessors from the goal back to the start
+fn reconstruct<S: Clone + Eq + Hash, C>(dist: &HashMap<S, (C, Option<S>)>, goal: S) -> Vec<S> {
+    let mut path = vec![goal];
+    while let Some((_, Some(prev))) = dist.get(&path[path.len() - 1]) {
+        path.push(prev.clone());
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Weighted graph: 0 -> 1 (4), 0 -> 2 (1), 2 -> 1 (2), 1 -> 3 (1), 4 is unreachable
+    fn edges(n: &u32) -> Vec<(u32, u32)> {
+        match n {
+            0 => vec![(1, 4), (2, 1)],
+            1 => vec![(3, 1)],
+            2 => vec![(1, 2)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let solution = dijkstra(0, edges, |n| *n == 3).unwrap();
+        assert_eq!(solution.cost, 4);
+        assert_eq!(solution.path, vec![0, 2, 1, 3]);
+        assert_eq!(solution.stats.expanded, 3);
+        assert_eq!(dijkstra(0, edges, |n| *n == 4), None);
+        assert_eq!(dijkstra(0, edges, |n| *n == 0).map(|s| s.path), Some(vec![0]));
+    }
+
+    #[test]
+    fn test_astar() {
+        // walk along a line towards 10, the heuristic is the exact remaining distance
+        let neighbours = |n: &i32| vec![(n - 1, 1), (n + 1, 1)];
+        let solution = astar(0, neighbours, |n| (10 - n).abs(), |n| *n == 10).unwrap();
+        assert_eq!(solution.cost, 10);
+        assert_eq!(solution.path, (0..=10).collect::<Vec<i32>>());
+        assert_eq!(solution.stats.expanded, 10);
+        // without heuristic the search explores in both directions
+        assert!(dijkstra(0, neighbours, |n| *n == 10).unwrap().stats.expanded > 10);
+    }
+
+    #[test]
+    fn test_bfs() {
+        let solution = bfs(0, |n| edges(n).into_iter().map(|(m, _)| m), |n| *n == 3).unwrap();
+        assert_eq!(solution.cost, 2);
+        assert_eq!(solution.path, vec![0, 1, 3]);
+        assert_eq!(bfs(0, |n| edges(n).into_iter().map(|(m, _)| m), |n| *n == 4), None);
+    }
+
+    #[test]
+    fn test_dense() {
+        // the cheapest path avoids the expensive middle column
+        let grid = Grid::from_rows(vec![vec![1, 9, 1], vec![1, 9, 1], vec![1, 1, 1]]).unwrap();
+        let neighbours = |&c: &Coord| grid.neighbours4(c).map(|n| (n, grid[n])).collect::<Vec<_>>();
+        let mut dist = Grid::filled(3, 3, None);
+        dist[(0, 0)] = Some(0);
+        assert_eq!(astar_cost((0, 0), &mut dist, neighbours, |_| 0, |c| *c == (2, 0)), Some(6));
+        let solution = dijkstra((0, 0), neighbours, |c| *c == (2, 0)).unwrap();
+        assert_eq!(solution.cost, 6);
+        assert_eq!(solution.path, vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)]);
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ * Puzzles with parameters beyond those of the official puzzles
+ *
+ * The counts of the growth puzzles grow exponentially with the parameters, so they are computed with
+ * arbitrary precision, or modulo a prime for the simulations that run in logarithmic time.
+ */
+
+use std::fmt::Display;
+use std::fs;
+
+use crate::days::{day1, day10, day14, day2, day21, day3, day4, day5, day6, day7, day8};
+use crate::input::Input;
+use crate::utils::bigint::BigUint;
+use crate::utils::linalg::{ModInt, PRIME};
+use crate::ParseError;
+
+/// Why a variant has no answer: its input is invalid, or the answer exceeds what can be shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantError {
+    Input(ParseError),
+    Limit(String),
+}
+
+impl From<ParseError> for VariantError {
+    fn from(e: ParseError) -> Self {
+        VariantError::Input(e)
+    }
+}
+
+/// Solves a variant on the input of day `num`, errors in the input are rendered against it.
+fn run<T: Display, E: Into<VariantError>>(num: u32, variant: impl FnOnce(&str) -> Result<T, E>) -> Result<String, String> {
+    let input = Input::new(&crate::get_day_input(num));
+    variant(input.as_str()).map(|n| n.to_string()).map_err(|e| match e.into() {
+        VariantError::Input(e) => e.render(input.as_str()),
+        VariantError::Limit(message) => message,
+    })
+}
+
+/// Statistics of the sonar sweep over sliding windows of `window` measurements
+pub fn sonar(window: u64) -> Result<String, String> {
+    let window = usize::try_from(window).map_err(|_| format!("window of {} is too large", window))?;
+    run(1, |input| day1::sonar(input, window))
+}
+
+/// Trajectory of the submarine of day 2 as CSV, following the aim if `aimed` is set
+pub fn submarine(aimed: bool) -> Result<String, String> {
+    run(2, |input| day2::trajectory_csv(input, aimed))
+}
+
+/// The candidates left after every step of filtering the diagnostic report of day 3
+pub fn diagnostics() -> Result<String, String> {
+    run(3, day3::explain)
+}
+
+/// The `k`-th board to win at bingo on day 4, optionally counting complete diagonals
+pub fn bingo(k: u64, diagonals: bool) -> Result<String, String> {
+    let k = usize::try_from(k).map_err(|_| format!("there is no winner {}", k))?;
+    run(4, |input| day4::winner(input, k, diagonals))
+}
+
+/// Number of points where vent lines of day 5 overlap, taking the lines at `angles` into account
+pub fn vents(angles: &str, exact: bool, heatmap: bool) -> Result<String, String> {
+    let angles = match angles {
+        "straight" => day5::Angles::Straight,
+        "diagonal" => day5::Angles::Diagonal,
+        "any" => day5::Angles::Any,
+        _ => return Err(format!("unknown angles {:?}", angles)),
+    };
+    run(5, |input| day5::vents(input, angles, exact, heatmap).map_err(|e| match e {
+        day5::VentsError::Input(e) => VariantError::Input(e),
+        e => VariantError::Limit(e.to_string()),
+    }))
+}
+
+/// The cheapest position to align the crabs of day 7 at with the given cost per distance
+pub fn crabs(cost: &str) -> Result<String, String> {
+    let cost = match cost {
+        "linear" => day7::Cost::Linear,
+        "triangular" => day7::Cost::Triangular,
+        "quadratic" => day7::Cost::Quadratic,
+        _ => return Err(format!("unknown cost {:?}", cost)),
+    };
+    run(7, |input| day7::align(input, cost).map(|a| format!("position {} with fuel {}", a.position, a.cost)))
+}
+
+/// The wiring and output value of every display of day 8, or why it can not be decoded
+pub fn displays() -> Result<String, String> {
+    run(8, day8::explain)
+}
+
+/// Diagnostics of every line of `file`, or the input of day 10, that is not complete in the language of `brackets`
+pub fn lint(file: Option<&str>, brackets: &str) -> Result<String, String> {
+    let language = day10::Language::new(brackets).map_err(|e| e.render(brackets))?;
+    match file {
+        None => run(10, |input| Ok::<_, ParseError>(day10::lint(input, &language))),
+        Some(file) => {
+            let text = fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+            Ok(day10::lint(Input::new(&text).as_str(), &language))
+        }
+    }
+}
+
+/// Number of lanternfish after `days` days, optionally modulo `PRIME`, or their number on every day as CSV.
+/// Fishes give birth every `cycle` days, newborns `delay` days later.
+pub fn lanternfish(days: u64, cycle: u64, delay: u64, modulo: bool, series: bool) -> Result<String, String> {
+    let parameter = |n: u64, name: &str| usize::try_from(n).map_err(|_| format!("{} of {} days is too long", name, n));
+    let model = day6::Model::new(parameter(cycle, "cycle")?, parameter(delay, "delay")?)?;
+    match (series, modulo) {
+        (true, true) => run(6, |input| day6::series::<ModInt<PRIME>>(input, model, days).map(|counts| daily_csv(&counts))),
+        (true, false) => run(6, |input| day6::series::<BigUint>(input, model, days).map(|counts| daily_csv(&counts))),
+        (false, true) => run(6, |input| day6::lanternfish::<ModInt<PRIME>>(input, model, days)),
+        (false, false) => run(6, |input| day6::population::<BigUint>(input, model, days).map(|p| p.total())),
+    }
+}
+
+/// The number of fishes on every day as CSV
+fn daily_csv<T: Display>(counts: &[T]) -> String {
+    let rows: Vec<String> = counts.iter().enumerate().map(|(day, n)| format!("{},{}", day, n)).collect();
+    format!("day,fish\n{}", rows.join("\n"))
+}
+
+/// Difference between the most and least common element of the polymer after `steps` steps.
+/// Modulo `PRIME` the difference is undefined, so the count of every element is listed instead.
+pub fn polymer(steps: u64, modulo: bool) -> Result<String, String> {
+    if modulo {
+        run(14, |input| day14::elements_after::<ModInt<PRIME>>(input, steps).map(|counts| {
+            counts.iter().map(|(c, n)| format!("{}: {}", c, n)).collect::<Vec<_>>().join("\n")
+        }))
+    } else {
+        let steps = usize::try_from(steps).map_err(|_| format!("{} steps are too many", steps))?;
+        run(14, |input| day14::polymer::<BigUint>(input, steps))
+    }
+}
+
+/// Number of universes won by the most successful player of Dirac Dice played up to `score` points
+pub fn dirac(score: u64) -> Result<String, String> {
+    let score = u32::try_from(score).map_err(|_| format!("score {} is too large", score))?;
+    run(21, |input| day21::dirac::<BigUint>(input, score))
+}
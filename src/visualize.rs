@@ -0,0 +1,166 @@
+/*
+ * Visualization of step simulations
+ *
+ * Days which are simulations can implement `Visualize` to emit a sequence of frames.
+ * Frames are either animated in the terminal through ANSI escape codes, or dumped to a
+ * directory as PBM/PPM images.
+ */
+
+use std::fs::{create_dir_all, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::utils::ParseError;
+
+/// RGB colour
+pub type Colour = (u8, u8, u8);
+
+/// Visualization trait, optionally implemented by days next to `AOCDay`
+pub trait Visualize {
+    /// Returns the frames of the simulation for an input, or why the input can not be simulated.
+    fn frames(&self, input: &str) -> Result<Box<dyn Iterator<Item = Frame>>, ParseError>;
+}
+
+/// A single frame of a simulation. Every cell is an index into the palette.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<u8>,
+    palette: &'static [Colour],
+}
+
+impl Frame {
+    /// Creates a frame filled with the first colour of the palette.
+    pub fn new(width: usize, height: usize, palette: &'static [Colour]) -> Self {
+        assert!(!palette.is_empty(), "palette requires at least one colour");
+        Frame { width, height, cells: vec![0; width * height], palette }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, colour: u8) {
+        assert!((colour as usize) < self.palette.len(), "colour not in palette");
+        self.cells[y * self.width + x] = colour;
+    }
+
+    fn colour(&self, x: usize, y: usize) -> Colour {
+        self.palette[self.get(x, y) as usize]
+    }
+
+    /// Frames with at most two colours are stored as bitmaps.
+    fn is_bitmap(&self) -> bool {
+        self.palette.len() <= 2
+    }
+
+    /// Renders the frame with 24-bit ANSI colours.
+    /// Every character holds two rows of cells by using the upper half block.
+    pub fn to_ansi(&self) -> String {
+        let mut s = String::with_capacity(self.width * self.height * 20);
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let (r1, g1, b1) = self.colour(x, y);
+                let (r2, g2, b2) = if y + 1 < self.height { self.colour(x, y + 1) } else { (0, 0, 0) };
+                s.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀", r1, g1, b1, r2, g2, b2));
+            }
+            s.push_str("\x1b[0m\n");
+        }
+        s
+    }
+
+    /// Whether a cell is closer to black than to white, the ink of a PBM
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        let (r, g, b) = self.colour(x, y);
+        let luminance = 299 * r as u32 + 587 * g as u32 + 114 * b as u32;
+        luminance < 128 * 1000
+    }
+
+    /// Encodes the frame as a plain PBM (bitmaps) or a binary PPM image.
+    pub fn to_pnm(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if self.is_bitmap() {
+            bytes.extend(format!("P1\n{} {}\n", self.width, self.height).bytes());
+            for y in 0..self.height {
+                let row: Vec<&str> = (0..self.width).map(|x| if self.is_dark(x, y) { "1" } else { "0" }).collect();
+                bytes.extend(row.join(" ").bytes());
+                bytes.push(b'\n');
+            }
+        } else {
+            bytes.extend(format!("P6\n{} {}\n255\n", self.width, self.height).bytes());
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let (r, g, b) = self.colour(x, y);
+                    bytes.extend([r, g, b]);
+                }
+            }
+        }
+        bytes
+    }
+
+    fn extension(&self) -> &'static str {
+        if self.is_bitmap() { "pbm" } else { "ppm" }
+    }
+}
+
+/// Animates frames in the terminal, waiting `delay` between frames.
+pub fn animate(frames: impl Iterator<Item = Frame>, delay: Duration) -> io::Result<usize> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut count = 0;
+    for frame in frames {
+        // clear screen and move cursor to the top left, frames may change in size
+        write!(out, "\x1b[2J\x1b[H{}", frame.to_ansi())?;
+        writeln!(out, "frame {}", count)?;
+        out.flush()?;
+        count += 1;
+        thread::sleep(delay);
+    }
+    Ok(count)
+}
+
+/// Writes every frame as an image into `dir`, returns the number of frames written.
+pub fn dump(frames: impl Iterator<Item = Frame>, dir: &Path) -> io::Result<usize> {
+    create_dir_all(dir)?;
+    let mut count = 0;
+    for frame in frames {
+        let path = dir.join(format!("frame_{:05}.{}", count, frame.extension()));
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&frame.to_pnm())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONO: [Colour; 2] = [(0, 0, 0), (255, 255, 255)];
+    const RGB: [Colour; 3] = [(0, 0, 0), (255, 0, 0), (0, 0, 255)];
+
+    #[test]
+    fn test_pnm() {
+        let mut frame = Frame::new(3, 2, &MONO);
+        frame.set(1, 0, 1);
+        frame.set(2, 1, 1);
+        assert_eq!(String::from_utf8(frame.to_pnm()).unwrap(), "P1\n3 2\n1 0 1\n1 1 0\n");
+
+        let mut frame = Frame::new(2, 1, &RGB);
+        frame.set(1, 0, 2);
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend([0, 0, 0, 0, 0, 255]);
+        assert_eq!(frame.to_pnm(), expected);
+    }
+
+    #[test]
+    fn test_ansi() {
+        let frame = Frame::new(2, 3, &MONO);
+        let ansi = frame.to_ansi();
+        assert_eq!(ansi.lines().count(), 2); // two rows per line
+        assert_eq!(ansi.matches('▀').count(), 4);
+    }
+}